@@ -0,0 +1,52 @@
+// --------------------------------------------------------------------
+// archive
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A gzip compressed tarball which matches are packed into before they
+/// are removed, with `archive_path`
+///
+/// Entries are streamed into the archive one at a time, so memory use
+/// does not grow with their size.
+pub struct Archive {
+    path: PathBuf,
+    builder: tar::Builder<GzEncoder<fs::File>>,
+}
+
+impl Archive {
+    /// create the archive file, failing if it already exists
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        // store symlinks themselves rather than what they point to
+        builder.follow_symlinks(false);
+        Ok(Self {
+            path: path.to_path_buf(),
+            builder,
+        })
+    }
+
+    /// append a file or symlink, or a directory with all its contents,
+    /// stored under the given relative name
+    pub fn append(&mut self, path: &Path, name: &Path) -> io::Result<()> {
+        if fs::symlink_metadata(path)?.is_dir() {
+            self.builder.append_dir_all(name, path)
+        } else {
+            self.builder.append_path_with_name(path, name)
+        }
+    }
+
+    /// write the end of the archive, returning its size in bytes
+    pub fn finish(self) -> io::Result<u64> {
+        let Self { path, builder } = self;
+        builder.into_inner()?.finish()?.sync_all()?;
+        Ok(fs::metadata(path)?.len())
+    }
+}
@@ -0,0 +1,771 @@
+// --------------------------------------------------------------------
+// config
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::constants::{GLOBAL_SETTINGS_FILENAMES, SETTINGS_FILENAMES};
+use crate::utils::{parse_duration, parse_size, SizeUnits};
+use crate::{
+    AgeField, CleanError, CleaningJob, ConditionalPattern, EntryType, ErrorPolicy, HiddenPolicy,
+    KeepPolicy, OutputFormat, Result, RetentionPattern, SymlinkBehavior,
+};
+
+/// The options of a cleaning job as written to and read from a settings
+/// file, without any of its runtime state
+///
+/// Durations are written as strings such as `"30d"` so that they stay easy
+/// to edit; plain numbers of seconds, and the `*_secs` names of earlier
+/// versions, are still read.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CleanConfig {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    pub patterns: Vec<String>,
+    pub dry_run: bool,
+    pub skip_confirmation: bool,
+    pub include_symlinks: bool,
+    #[serde(default)]
+    pub trash_mode: bool,
+    #[serde(default)]
+    pub trash_fallback_delete: bool,
+    #[serde(default)]
+    pub secure_delete: bool,
+    #[serde(default)]
+    pub stage: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_path: Option<PathBuf>,
+    #[serde(default)]
+    pub skip_in_use: bool,
+    #[serde(default = "crate::default_force")]
+    pub force: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_dir_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delete_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delete_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_deletions: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_size: Option<u64>,
+    #[serde(default)]
+    pub limit_ok: bool,
+    #[serde(default)]
+    pub error_policy: ErrorPolicy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    #[serde(default)]
+    pub parallel_delete: bool,
+    #[serde(
+        default,
+        alias = "older_than_secs",
+        skip_serializing_if = "Option::is_none",
+        with = "duration"
+    )]
+    pub older_than: Option<u64>,
+    #[serde(
+        default,
+        alias = "newer_than_secs",
+        skip_serializing_if = "Option::is_none",
+        with = "duration"
+    )]
+    pub newer_than: Option<u64>,
+    #[serde(
+        default,
+        alias = "protect_newer_than_secs",
+        skip_serializing_if = "Option::is_none",
+        with = "duration"
+    )]
+    pub protect_newer_than: Option<u64>,
+    #[serde(default)]
+    pub age_field: AgeField,
+    #[serde(default)]
+    pub size_units: SizeUnits,
+    #[serde(default)]
+    pub hidden_policy: HiddenPolicy,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regex_patterns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_regex_patterns: Vec<String>,
+    #[serde(default)]
+    pub stats_mode: bool,
+    #[serde(default)]
+    pub report_free_space: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_free_after: Option<u64>,
+    #[serde(default)]
+    pub quiet: bool,
+    #[serde(default)]
+    pub remove_empty_files: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub match_types: Vec<EntryType>,
+    #[serde(default)]
+    pub prune_empty_dirs: bool,
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    #[serde(default)]
+    pub tree: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csv_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_depth: Option<usize>,
+    #[serde(default)]
+    pub use_gitignore: bool,
+    #[serde(default)]
+    pub respect_gitignore: bool,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    #[serde(default)]
+    pub interactive: bool,
+    #[serde(default)]
+    pub select: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm_threshold: Option<u64>,
+    #[serde(default)]
+    pub skip_typed_confirmation: bool,
+    #[serde(default)]
+    pub confirm_dirs: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditional_patterns: Vec<ConditionalPattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owned_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prune_patterns: Vec<String>,
+    #[serde(default)]
+    pub no_default_prune: bool,
+    #[serde(default)]
+    pub no_rcleanignore: bool,
+    #[serde(default)]
+    pub accurate_size: bool,
+    #[serde(default)]
+    pub duplicates: bool,
+    #[serde(default)]
+    pub keep: KeepPolicy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hash_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_newest: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_oldest: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retention_patterns: Vec<RetentionPattern>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncate_patterns: Vec<String>,
+    #[serde(default)]
+    pub same_file_system: bool,
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    #[serde(default)]
+    pub symlink_behavior: SymlinkBehavior,
+    #[serde(default)]
+    pub untracked_only: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<String>,
+    #[serde(default)]
+    pub anchored: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_files: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_hook: Option<String>,
+    #[serde(default)]
+    pub hook_strict: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_delete_hook: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_delete_hook: Option<String>,
+    #[serde(default)]
+    pub hook_per_run: bool,
+    #[serde(default)]
+    pub hook_shell: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec: Option<String>,
+    #[serde(default)]
+    pub exec_batch: bool,
+}
+
+impl CleanConfig {
+    /// the default options with those given in `RCLEAN_*` environment
+    /// variables applied, see `EnvConfig` for the variables
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ConfigError` naming the variable if a value is invalid.
+    pub fn from_env() -> Result<Self> {
+        EnvConfig::from_env().map(|env| env.to_config())
+    }
+}
+
+impl From<&CleaningJob> for CleanConfig {
+    fn from(job: &CleaningJob) -> Self {
+        Self {
+            path: job.path.clone(),
+            paths: job.paths.clone(),
+            patterns: job.patterns.clone(),
+            dry_run: job.dry_run,
+            skip_confirmation: job.skip_confirmation,
+            include_symlinks: job.include_symlinks,
+            trash_mode: job.trash_mode,
+            trash_fallback_delete: job.trash_fallback_delete,
+            secure_delete: job.secure_delete,
+            stage: job.stage,
+            archive_path: job.archive_path.clone(),
+            skip_in_use: job.skip_in_use,
+            force: job.force,
+            min_size: job.min_size,
+            max_size: job.max_size,
+            min_dir_size: job.min_dir_size,
+            max_delete_size: job.max_delete_size,
+            max_delete_count: job.max_delete_count,
+            max_deletions: job.max_deletions,
+            max_total_size: job.max_total_size,
+            limit_ok: job.limit_ok,
+            error_policy: job.error_policy,
+            jobs: job.jobs,
+            parallel_delete: job.parallel_delete,
+            older_than: job.older_than_secs,
+            newer_than: job.newer_than_secs,
+            protect_newer_than: job.protect_newer_than_secs,
+            age_field: job.age_field,
+            size_units: job.size_units,
+            hidden_policy: job.hidden_policy,
+            regex_patterns: job.regex_patterns.clone(),
+            exclude_regex_patterns: job.exclude_regex_patterns.clone(),
+            stats_mode: job.stats_mode,
+            report_free_space: job.report_free_space,
+            min_free_after: job.min_free_after,
+            quiet: job.quiet,
+            remove_empty_files: job.remove_empty_files,
+            match_types: job.match_types.clone(),
+            prune_empty_dirs: job.prune_empty_dirs,
+            output_format: job.output_format,
+            tree: job.tree,
+            output_file: job.output_file.clone(),
+            csv_file: job.csv_file.clone(),
+            backup_dir: job.backup_dir.clone(),
+            quarantine_dir: job.quarantine_dir.clone(),
+            history_dir: job.history_dir.clone(),
+            max_depth: job.max_depth,
+            min_depth: job.min_depth,
+            use_gitignore: job.use_gitignore,
+            respect_gitignore: job.respect_gitignore,
+            case_insensitive: job.case_insensitive,
+            interactive: job.interactive,
+            select: job.select,
+            confirm_threshold: job.confirm_threshold,
+            skip_typed_confirmation: job.skip_typed_confirmation,
+            confirm_dirs: job.confirm_dirs,
+            conditional_patterns: job.conditional_patterns.clone(),
+            owned_by: job.owned_by.clone(),
+            prune_patterns: job.prune_patterns.clone(),
+            no_default_prune: job.no_default_prune,
+            no_rcleanignore: job.no_rcleanignore,
+            accurate_size: job.accurate_size,
+            duplicates: job.duplicates,
+            keep: job.keep,
+            max_hash_size: job.max_hash_size,
+            keep_newest: job.keep_newest,
+            keep_oldest: job.keep_oldest,
+            retention_patterns: job.retention_patterns.clone(),
+            truncate_patterns: job.truncate_patterns.clone(),
+            same_file_system: job.same_file_system,
+            follow_symlinks: job.follow_symlinks,
+            symlink_behavior: job.symlink_behavior,
+            untracked_only: job.untracked_only,
+            presets: job.presets.clone(),
+            anchored: job.anchored,
+            exclude_files: job.exclude_files.clone(),
+            post_hook: job.post_hook.clone(),
+            hook_strict: job.hook_strict,
+            pre_delete_hook: job.pre_delete_hook.clone(),
+            post_delete_hook: job.post_delete_hook.clone(),
+            hook_per_run: job.hook_per_run,
+            hook_shell: job.hook_shell,
+            exec: job.exec.clone(),
+            exec_batch: job.exec_batch,
+        }
+    }
+}
+
+impl From<CleanConfig> for CleaningJob {
+    fn from(config: CleanConfig) -> Self {
+        CleaningJob {
+            path: config.path,
+            paths: config.paths,
+            patterns: config.patterns,
+            dry_run: config.dry_run,
+            skip_confirmation: config.skip_confirmation,
+            include_symlinks: config.include_symlinks,
+            trash_mode: config.trash_mode,
+            trash_fallback_delete: config.trash_fallback_delete,
+            secure_delete: config.secure_delete,
+            stage: config.stage,
+            archive_path: config.archive_path,
+            skip_in_use: config.skip_in_use,
+            force: config.force,
+            min_size: config.min_size,
+            max_size: config.max_size,
+            min_dir_size: config.min_dir_size,
+            max_delete_size: config.max_delete_size,
+            max_delete_count: config.max_delete_count,
+            max_deletions: config.max_deletions,
+            max_total_size: config.max_total_size,
+            limit_ok: config.limit_ok,
+            error_policy: config.error_policy,
+            jobs: config.jobs,
+            parallel_delete: config.parallel_delete,
+            older_than_secs: config.older_than,
+            newer_than_secs: config.newer_than,
+            protect_newer_than_secs: config.protect_newer_than,
+            age_field: config.age_field,
+            size_units: config.size_units,
+            hidden_policy: config.hidden_policy,
+            regex_patterns: config.regex_patterns,
+            exclude_regex_patterns: config.exclude_regex_patterns,
+            stats_mode: config.stats_mode,
+            report_free_space: config.report_free_space,
+            min_free_after: config.min_free_after,
+            quiet: config.quiet,
+            remove_empty_files: config.remove_empty_files,
+            match_types: config.match_types,
+            prune_empty_dirs: config.prune_empty_dirs,
+            output_format: config.output_format,
+            tree: config.tree,
+            output_file: config.output_file,
+            csv_file: config.csv_file,
+            backup_dir: config.backup_dir,
+            quarantine_dir: config.quarantine_dir,
+            history_dir: config.history_dir,
+            max_depth: config.max_depth,
+            min_depth: config.min_depth,
+            use_gitignore: config.use_gitignore,
+            respect_gitignore: config.respect_gitignore,
+            case_insensitive: config.case_insensitive,
+            interactive: config.interactive,
+            select: config.select,
+            confirm_threshold: config.confirm_threshold,
+            skip_typed_confirmation: config.skip_typed_confirmation,
+            confirm_dirs: config.confirm_dirs,
+            conditional_patterns: config.conditional_patterns,
+            owned_by: config.owned_by,
+            prune_patterns: config.prune_patterns,
+            no_default_prune: config.no_default_prune,
+            no_rcleanignore: config.no_rcleanignore,
+            accurate_size: config.accurate_size,
+            duplicates: config.duplicates,
+            keep: config.keep,
+            max_hash_size: config.max_hash_size,
+            keep_newest: config.keep_newest,
+            keep_oldest: config.keep_oldest,
+            retention_patterns: config.retention_patterns,
+            truncate_patterns: config.truncate_patterns,
+            same_file_system: config.same_file_system,
+            follow_symlinks: config.follow_symlinks,
+            symlink_behavior: config.symlink_behavior,
+            untracked_only: config.untracked_only,
+            presets: config.presets,
+            anchored: config.anchored,
+            exclude_files: config.exclude_files,
+            post_hook: config.post_hook,
+            hook_strict: config.hook_strict,
+            pre_delete_hook: config.pre_delete_hook,
+            post_delete_hook: config.post_delete_hook,
+            hook_per_run: config.hook_per_run,
+            hook_shell: config.hook_shell,
+            exec: config.exec,
+            exec_batch: config.exec_batch,
+            ..CleaningJob::default()
+        }
+    }
+}
+
+/// (de)serialize a number of seconds as a duration string such as "30d",
+/// also reading a plain number of seconds
+mod duration {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::utils::{format_duration, parse_duration};
+
+    pub fn serialize<S: Serializer>(secs: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match secs {
+            Some(secs) => serializer.serialize_str(&format_duration(*secs)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Duration {
+        Secs(u64),
+        Text(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        match Option::<Duration>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(Duration::Secs(secs)) => Ok(Some(secs)),
+            Some(Duration::Text(text)) => parse_duration(&text)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// File format of a settings file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// pick the format from a settings file's extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("yaml") | Some("yml") => Ok(ConfigFormat::Yaml),
+            _ => Err(CleanError::ConfigError(format!(
+                "unknown settings file format: {:?}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// default settings filename for this format
+    pub fn filename(self) -> &'static str {
+        match self {
+            ConfigFormat::Toml => ".rclean.toml",
+            ConfigFormat::Yaml => ".rclean.yaml",
+        }
+    }
+
+    /// serialize a job's options in this format
+    pub fn serialize(self, job: &CleaningJob) -> Result<String> {
+        let config = CleanConfig::from(job);
+        match self {
+            ConfigFormat::Toml => {
+                toml::to_string(&config).map_err(|e| CleanError::ConfigError(e.to_string()))
+            }
+            ConfigFormat::Yaml => {
+                serde_yaml::to_string(&config).map_err(|e| CleanError::ConfigError(e.to_string()))
+            }
+        }
+    }
+
+    /// parse a settings file in this format into a generic value, so
+    /// that it can be merged with another one before deserializing
+    pub fn parse_value(self, contents: &str) -> Result<serde_json::Value> {
+        match self {
+            ConfigFormat::Toml => {
+                toml::from_str(contents).map_err(|e| CleanError::ConfigError(e.to_string()))
+            }
+            ConfigFormat::Yaml => {
+                serde_yaml::from_str(contents).map_err(|e| CleanError::ConfigError(e.to_string()))
+            }
+        }
+    }
+
+    /// deserialize a job's options from this format
+    pub fn deserialize(self, contents: &str) -> Result<CleaningJob> {
+        let config: CleanConfig = match self {
+            ConfigFormat::Toml => {
+                toml::from_str(contents).map_err(|e| CleanError::ConfigError(e.to_string()))?
+            }
+            ConfigFormat::Yaml => serde_yaml::from_str(contents)
+                .map_err(|e| CleanError::ConfigError(e.to_string()))?,
+        };
+        Ok(config.into())
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Toml => write!(f, "toml"),
+            ConfigFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "toml" => Ok(ConfigFormat::Toml),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            _ => Err(format!(
+                "invalid config format: '{s}' (expected toml or yaml)"
+            )),
+        }
+    }
+}
+
+/// find the first settings file present in a directory
+///
+/// Settings files are tried in the order of `SETTINGS_FILENAMES`.
+pub fn discover_config(dir: &Path) -> Option<PathBuf> {
+    SETTINGS_FILENAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// load a cleaning job from a settings file, picking the format by extension
+pub fn load_config(path: &Path) -> Result<CleaningJob> {
+    let format = ConfigFormat::from_path(path)?;
+    let contents = fs::read_to_string(path)
+        .map_err(|e| CleanError::ConfigError(format!("cannot read {:?}: {e}", path.display())))?;
+    format.deserialize(&contents).map_err(|e| match e {
+        CleanError::ConfigError(msg) => {
+            CleanError::ConfigError(format!("{:?}: {msg}", path.display()))
+        }
+        e => e,
+    })
+}
+
+/// find the global settings file, in `$XDG_CONFIG_HOME/rclean` or
+/// `~/.config/rclean`
+pub fn discover_global_config() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    GLOBAL_SETTINGS_FILENAMES
+        .iter()
+        .map(|name| config_dir.join("rclean").join(name))
+        .find(|path| path.is_file())
+}
+
+/// merge the options of an overlay settings file into a base one
+///
+/// Options set in the overlay replace those of the base, except for
+/// lists such as `patterns`, which are concatenated without duplicates.
+pub fn merge(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    use serde_json::Value;
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay)) => {
+            for value in overlay {
+                if !base.contains(&value) {
+                    base.push(value);
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// load a cleaning job from a global and a local settings file, the
+/// local one overriding and extending the global one
+pub fn load_merged_config(global: &Path, local: &Path) -> Result<CleaningJob> {
+    let mut merged = read_config_value(global)?;
+    merge(&mut merged, read_config_value(local)?);
+    let config: CleanConfig = serde_json::from_value(merged).map_err(|e| {
+        CleanError::ConfigError(format!(
+            "{:?} merged with {:?}: {e}",
+            global.display(),
+            local.display()
+        ))
+    })?;
+    Ok(config.into())
+}
+
+/// read a settings file into a generic value, picking the format by extension
+fn read_config_value(path: &Path) -> Result<serde_json::Value> {
+    let format = ConfigFormat::from_path(path)?;
+    let contents = fs::read_to_string(path)
+        .map_err(|e| CleanError::ConfigError(format!("cannot read {:?}: {e}", path.display())))?;
+    format.parse_value(&contents).map_err(|e| match e {
+        CleanError::ConfigError(msg) => {
+            CleanError::ConfigError(format!("{:?}: {msg}", path.display()))
+        }
+        e => e,
+    })
+}
+
+/// read glob patterns from an exclude file, one per line, skipping blank
+/// lines and `#` comments like a `.gitignore` file
+///
+/// # Errors
+///
+/// Returns a `ConfigError` naming the file, and the line of an invalid
+/// pattern, if the file cannot be read or a pattern does not compile.
+pub fn read_exclude_file(path: &Path) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| CleanError::ConfigError(format!("cannot read {:?}: {e}", path.display())))?;
+    let mut patterns = vec![];
+    for (number, line) in contents.lines().enumerate() {
+        let pattern = line.trim();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            continue;
+        }
+        if let Err(e) = globset::Glob::new(pattern) {
+            return Err(CleanError::ConfigError(format!(
+                "{}:{}: invalid glob pattern: {e}",
+                path.display(),
+                number + 1
+            )));
+        }
+        patterns.push(pattern.to_string());
+    }
+    Ok(patterns)
+}
+
+/// Options given in `RCLEAN_*` environment variables, each `None` if the
+/// variable is unset or empty
+///
+/// | Variable | Option |
+/// | --- | --- |
+/// | `RCLEAN_PATH` | `path` |
+/// | `RCLEAN_PATTERNS` | `patterns`, comma-separated |
+/// | `RCLEAN_EXCLUDE` | globs to exclude, comma-separated |
+/// | `RCLEAN_DRY_RUN` | `dry_run` |
+/// | `RCLEAN_SKIP_CONFIRMATION` | `skip_confirmation` |
+/// | `RCLEAN_TRASH` | `trash_mode` |
+/// | `RCLEAN_OLDER_THAN` | `older_than_secs`, as a duration such as `7d` |
+/// | `RCLEAN_NEWER_THAN` | `newer_than_secs`, as a duration |
+/// | `RCLEAN_MIN_SIZE` | `min_size`, as a size such as `10M` |
+/// | `RCLEAN_MAX_SIZE` | `max_size`, as a size |
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvConfig {
+    pub path: Option<String>,
+    pub patterns: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub dry_run: Option<bool>,
+    pub skip_confirmation: Option<bool>,
+    pub trash_mode: Option<bool>,
+    pub older_than_secs: Option<u64>,
+    pub newer_than_secs: Option<u64>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+}
+
+impl EnvConfig {
+    /// read the options from the environment of the process
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ConfigError` naming the variable if a value is invalid,
+    /// e.g. a bad duration in `RCLEAN_OLDER_THAN`.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// read the options from the variables `lookup` returns
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+        let list = |name: &str| {
+            var(name).map(|value| {
+                value
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+        };
+        let parsed = |name: &str, parse: fn(&str) -> std::result::Result<u64, String>| {
+            var(name)
+                .map(|value| {
+                    parse(value.trim()).map_err(|e| CleanError::ConfigError(format!("{name}: {e}")))
+                })
+                .transpose()
+        };
+        let flag = |name: &str| var(name).map(|value| parse_flag(name, &value)).transpose();
+        Ok(Self {
+            path: var("RCLEAN_PATH"),
+            patterns: list("RCLEAN_PATTERNS"),
+            exclude: list("RCLEAN_EXCLUDE"),
+            dry_run: flag("RCLEAN_DRY_RUN")?,
+            skip_confirmation: flag("RCLEAN_SKIP_CONFIRMATION")?,
+            trash_mode: flag("RCLEAN_TRASH")?,
+            older_than_secs: parsed("RCLEAN_OLDER_THAN", parse_duration)?,
+            newer_than_secs: parsed("RCLEAN_NEWER_THAN", parse_duration)?,
+            min_size: parsed("RCLEAN_MIN_SIZE", parse_size)?,
+            max_size: parsed("RCLEAN_MAX_SIZE", parse_size)?,
+        })
+    }
+
+    /// set the options given in the environment on a job, adding the
+    /// excluded globs as negated patterns
+    pub fn apply(&self, job: &mut CleaningJob) {
+        if let Some(path) = &self.path {
+            job.path = path.clone();
+        }
+        if let Some(patterns) = &self.patterns {
+            job.patterns = patterns.clone();
+        }
+        job.patterns.extend(self.negated_excludes());
+        if let Some(dry_run) = self.dry_run {
+            job.dry_run = dry_run;
+        }
+        if let Some(skip_confirmation) = self.skip_confirmation {
+            job.skip_confirmation = skip_confirmation;
+        }
+        if let Some(trash_mode) = self.trash_mode {
+            job.trash_mode = trash_mode;
+        }
+        job.older_than_secs = self.older_than_secs.or(job.older_than_secs);
+        job.newer_than_secs = self.newer_than_secs.or(job.newer_than_secs);
+        job.min_size = self.min_size.or(job.min_size);
+        job.max_size = self.max_size.or(job.max_size);
+    }
+
+    /// the default options of a job with these applied, as
+    /// `CleanConfig::from_env` returns them
+    pub fn to_config(&self) -> CleanConfig {
+        let mut job = CleaningJob::default();
+        self.apply(&mut job);
+        CleanConfig::from(&job)
+    }
+
+    /// the excluded globs as `!`-prefixed patterns
+    pub fn negated_excludes(&self) -> Vec<String> {
+        self.exclude
+            .iter()
+            .flatten()
+            .map(|glob| format!("!{glob}"))
+            .collect()
+    }
+}
+
+/// parse a boolean variable: 1, true, yes or on, and 0, false, no or off
+fn parse_flag(name: &str, value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(CleanError::ConfigError(format!(
+            "{name}: invalid value '{value}' (expected true or false)"
+        ))),
+    }
+}
@@ -3,6 +3,69 @@
 
 pub const SETTINGS_FILENAME: &str = ".rclean.toml";
 
+/// settings filenames recognised by `--configfile`, in order of preference
+pub const SETTINGS_FILENAMES: [&str; 3] = [SETTINGS_FILENAME, ".rclean.yaml", ".rclean.yml"];
+
+/// global settings filenames, looked up in `~/.config/rclean`
+pub const GLOBAL_SETTINGS_FILENAMES: [&str; 3] = ["config.toml", "config.yaml", "config.yml"];
+
+/// per-directory file of patterns to leave alone, in `.gitignore` syntax
+pub const IGNORE_FILENAME: &str = ".rcleanignore";
+
+/// stats key for empty files matched by `--empty-files`
+pub const EMPTY_FILE_PATTERN: &str = "<empty-file>";
+
+/// stats key for directories pruned by `--prune-empty`
+pub const EMPTY_DIR_PATTERN: &str = "<empty-dir>";
+
+/// stats key for entries matched by `--gitignore` without other patterns
+pub const GITIGNORED_PATTERN: &str = "<gitignored>";
+
+/// stats key for paths given to `--stdin` without other patterns
+pub const CANDIDATE_PATTERN: &str = "<stdin>";
+
+/// files larger than this are not hashed by `--duplicates` (256 MiB)
+pub const DEFAULT_MAX_HASH_SIZE: u64 = 256 * 1024 * 1024;
+
+/// directories never descended into unless `--no-default-prune` is given
+pub const DEFAULT_PRUNE_PATTERNS: [&str; 1] = ["**/.git"];
+
+/// beyond this many targets `--select` lists their top-level directories
+pub const SELECT_LIMIT: usize = 2000;
+
+/// targets removed at a time by `--parallel-delete`, between which their
+/// outcome is logged and reported
+pub const PARALLEL_DELETE_CHUNK: usize = 256;
+
+/// prefix of the per-run directory targets are moved into by `--stage`,
+/// followed by the process id
+pub const STAGING_DIR_PREFIX: &str = ".rclean-staging-";
+
+/// most bytes of paths passed to a single command by `--exec-batch`,
+/// well below the command line limits of all platforms
+pub const EXEC_BATCH_MAX_BYTES: usize = 24 * 1024;
+
+/// log target of the record written for every removed entry, whatever
+/// `quiet` says, e.g. for a `--log-file` audit trail
+pub const AUDIT_LOG_TARGET: &str = "rclean::audit";
+
+/// log target of the confirmation listing, the summary of a run and the
+/// output asked for such as `stats`, shown at the default `warn` level
+/// which hides the per-item lines
+pub const SUMMARY_LOG_TARGET: &str = "rclean::summary";
+
+/// exit code: items were matched and the job completed
+pub const EXIT_SUCCESS: i32 = 0;
+
+/// exit code: the job could not run, e.g. an invalid pattern or option
+pub const EXIT_FATAL: i32 = 1;
+
+/// exit code: some matched items could not be removed
+pub const EXIT_PARTIAL: i32 = 2;
+
+/// exit code: nothing matched
+pub const EXIT_NO_MATCH: i32 = 3;
+
 pub fn get_default_patterns() -> Vec<String> {
     vec![
         // directory
@@ -19,3 +82,32 @@ pub fn get_default_patterns() -> Vec<String> {
         String::from("**/pip-log.txt"),
     ]
 }
+
+/// names accepted by `get_preset_patterns`
+pub const PRESET_NAMES: [&str; 5] = ["python", "go", "java", "cpp", "all"];
+
+/// glob patterns of a named preset, or None for an unknown name
+///
+/// The "all" preset combines every other preset without duplicates.
+pub fn get_preset_patterns(name: &str) -> Option<Vec<String>> {
+    let patterns: Vec<&str> = match name {
+        "python" => return Some(get_default_patterns()),
+        // '**/bin' and '**/vendor' are often checked in, so are left out
+        "go" => vec!["**/*.test", "**/coverage.out"],
+        "java" => vec!["**/target", "**/*.class", "**/.gradle", "**/build"],
+        "cpp" => vec!["**/*.o", "**/*.obj", "**/CMakeFiles", "**/cmake-build-*"],
+        "all" => {
+            let mut all: Vec<String> = vec![];
+            for preset in PRESET_NAMES.iter().filter(|&&p| p != "all") {
+                for pattern in get_preset_patterns(preset).unwrap_or_default() {
+                    if !all.contains(&pattern) {
+                        all.push(pattern);
+                    }
+                }
+            }
+            return Some(all);
+        }
+        _ => return None,
+    };
+    Some(patterns.into_iter().map(String::from).collect())
+}
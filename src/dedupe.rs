@@ -0,0 +1,140 @@
+// --------------------------------------------------------------------
+// dedupe
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// Which copy of a set of identical files is kept
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeepPolicy {
+    /// the least recently modified copy
+    #[default]
+    Oldest,
+    /// the copy with the shortest path
+    Shortest,
+}
+
+impl fmt::Display for KeepPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeepPolicy::Oldest => write!(f, "oldest"),
+            KeepPolicy::Shortest => write!(f, "shortest"),
+        }
+    }
+}
+
+impl FromStr for KeepPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "oldest" => Ok(KeepPolicy::Oldest),
+            "shortest" | "shortest-path" => Ok(KeepPolicy::Shortest),
+            _ => Err(format!(
+                "invalid keep policy: '{s}' (expected oldest or shortest)"
+            )),
+        }
+    }
+}
+
+/// A set of files with identical contents, of which one copy is kept
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub kept: PathBuf,
+    pub removed: Vec<PathBuf>,
+    /// size of each copy in bytes
+    pub size: u64,
+}
+
+/// A candidate file with the metadata needed to group it
+struct Candidate {
+    path: PathBuf,
+    modified: SystemTime,
+}
+
+/// group files with identical contents
+///
+/// Files are first grouped by size, so only files sharing their size
+/// with another file are hashed. Empty files and files larger than
+/// `max_hash_size` are never considered duplicates.
+pub fn find_duplicates(
+    files: impl IntoIterator<Item = PathBuf>,
+    max_hash_size: u64,
+    keep: KeepPolicy,
+) -> Vec<DuplicateGroup> {
+    let mut by_size: BTreeMap<u64, Vec<Candidate>> = BTreeMap::new();
+    for path in files {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if !metadata.is_file() || metadata.len() == 0 {
+            continue;
+        }
+        if metadata.len() > max_hash_size {
+            warn!(
+                "not hashing {:?}: larger than max hash size",
+                path.display()
+            );
+            continue;
+        }
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        by_size
+            .entry(metadata.len())
+            .or_default()
+            .push(Candidate { path, modified });
+    }
+
+    let mut groups = vec![];
+    for (size, candidates) in by_size {
+        if candidates.len() < 2 {
+            continue;
+        }
+        let mut by_hash: HashMap<blake3::Hash, Vec<Candidate>> = HashMap::new();
+        for candidate in candidates {
+            match hash_file(&candidate.path) {
+                Ok(hash) => by_hash.entry(hash).or_default().push(candidate),
+                Err(e) => warn!("could not hash {:?}: {}", candidate.path.display(), e),
+            }
+        }
+        for (_, mut copies) in by_hash {
+            if copies.len() < 2 {
+                continue;
+            }
+            match keep {
+                KeepPolicy::Oldest => {
+                    copies.sort_by(|a, b| (a.modified, &a.path).cmp(&(b.modified, &b.path)))
+                }
+                KeepPolicy::Shortest => copies.sort_by(|a, b| {
+                    let a_len = a.path.as_os_str().len();
+                    let b_len = b.path.as_os_str().len();
+                    (a_len, &a.path).cmp(&(b_len, &b.path))
+                }),
+            }
+            let kept = copies.remove(0).path;
+            let mut removed: Vec<PathBuf> = copies.into_iter().map(|c| c.path).collect();
+            removed.sort();
+            groups.push(DuplicateGroup {
+                kept,
+                removed,
+                size,
+            });
+        }
+    }
+    groups.sort_by(|a, b| a.kept.cmp(&b.kept));
+    groups
+}
+
+/// hash the contents of a file
+fn hash_file(path: &Path) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(fs::File::open(path)?)?;
+    Ok(hasher.finalize())
+}
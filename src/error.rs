@@ -0,0 +1,71 @@
+// --------------------------------------------------------------------
+// errors
+
+use std::fmt;
+
+/// Errors which can abort a cleaning job
+#[derive(Debug)]
+pub enum CleanError {
+    /// a glob pattern could not be compiled
+    GlobError(globset::Error),
+    /// a regex pattern could not be compiled
+    RegexError(regex::Error),
+    /// the job configuration is invalid
+    ConfigError(String),
+    /// a file needed by the job could not be read or written
+    IoError(std::io::Error),
+    /// a hook command failed, with `hook_strict` set
+    HookError(String),
+    /// removal stopped at the failure limit of `error_policy`, leaving
+    /// `remaining` targets untouched
+    DeletionAborted { failed: usize, remaining: usize },
+}
+
+impl fmt::Display for CleanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CleanError::GlobError(e) => write!(f, "invalid glob pattern: {e}"),
+            CleanError::RegexError(e) => write!(f, "invalid regex pattern: {e}"),
+            CleanError::ConfigError(msg) => write!(f, "invalid configuration: {msg}"),
+            CleanError::IoError(e) => write!(f, "i/o error: {e}"),
+            CleanError::HookError(msg) => write!(f, "hook failed: {msg}"),
+            CleanError::DeletionAborted { failed, remaining } => write!(
+                f,
+                "removal aborted after {failed} failure(s), {remaining} item(s) not attempted"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CleanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CleanError::GlobError(e) => Some(e),
+            CleanError::RegexError(e) => Some(e),
+            CleanError::ConfigError(_)
+            | CleanError::HookError(_)
+            | CleanError::DeletionAborted { .. } => None,
+            CleanError::IoError(e) => Some(e),
+        }
+    }
+}
+
+impl From<globset::Error> for CleanError {
+    fn from(e: globset::Error) -> Self {
+        CleanError::GlobError(e)
+    }
+}
+
+impl From<regex::Error> for CleanError {
+    fn from(e: regex::Error) -> Self {
+        CleanError::RegexError(e)
+    }
+}
+
+impl From<std::io::Error> for CleanError {
+    fn from(e: std::io::Error) -> Self {
+        CleanError::IoError(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, CleanError>;
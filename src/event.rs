@@ -0,0 +1,28 @@
+// --------------------------------------------------------------------
+// event
+
+use std::path::Path;
+
+/// Progress reported to the callback of `CleaningJob::run_with_callback`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanEvent<'a> {
+    /// an entry matched a pattern and passed the filters
+    Matched {
+        path: &'a Path,
+        pattern: &'a str,
+        size: u64,
+    },
+    /// the confirmed targets are about to be removed; not sent when
+    /// `skip_confirmation` removes entries as soon as they match
+    RemovalStarted { total: usize },
+    /// an entry was removed, or moved to the trash in `trash_mode`,
+    /// truncated, or handed to the `exec` command, with its size as matched
+    Deleted { path: &'a Path, size: u64 },
+    /// an entry could not be removed
+    Failed { path: &'a Path, error: &'a str },
+    /// number of entries walked and matched so far
+    ScanProgress { scanned: usize, matched: usize },
+}
+
+/// How many walked entries between two `ScanProgress` events
+pub(crate) const SCAN_PROGRESS_INTERVAL: usize = 1000;
@@ -0,0 +1,48 @@
+// --------------------------------------------------------------------
+// git
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{CleanError, Result};
+
+/// canonical paths of the files tracked by the repository enclosing
+/// `root`, together with every directory containing one of them
+///
+/// A root outside of any repository tracks nothing.
+///
+/// # Errors
+///
+/// Returns a `ConfigError` if the repository or its index cannot be read.
+pub fn tracked_paths(root: &Path) -> Result<HashSet<PathBuf>> {
+    let repo = match git2::Repository::discover(root) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(git_error(root, e)),
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Ok(HashSet::new());
+    };
+    let workdir = fs::canonicalize(workdir)?;
+    let index = repo.index().map_err(|e| git_error(root, e))?;
+    let mut tracked = HashSet::new();
+    for entry in index.iter() {
+        let relative = String::from_utf8_lossy(&entry.path).into_owned();
+        let mut path = workdir.join(relative);
+        while path.starts_with(&workdir) && tracked.insert(path.clone()) {
+            if !path.pop() {
+                break;
+            }
+        }
+    }
+    Ok(tracked)
+}
+
+fn git_error(root: &Path, e: git2::Error) -> CleanError {
+    CleanError::ConfigError(format!(
+        "cannot read git repository of {:?}: {}",
+        root.display(),
+        e.message()
+    ))
+}
@@ -0,0 +1,159 @@
+// --------------------------------------------------------------------
+// history
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::report::Disposition;
+use crate::restore::{self, RestoreReport};
+use crate::{CleanError, Result};
+
+/// A removed entry as recorded in the history of a run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// absolute path the entry was removed from
+    pub original: PathBuf,
+    pub disposition: Disposition,
+    pub size: u64,
+    /// where a quarantined entry was moved to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<PathBuf>,
+}
+
+impl HistoryEntry {
+    /// true if `undo` can bring the entry back
+    pub fn is_restorable(&self) -> bool {
+        matches!(
+            self.disposition,
+            Disposition::Trashed | Disposition::Quarantined
+        )
+    }
+}
+
+/// Record of what a run removed, written to the history directory
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct History {
+    /// UTC time of the run, as used for the file name
+    pub time: String,
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// default history directory: `$XDG_DATA_HOME/rclean/history` or
+/// `~/.local/share/rclean/history`
+pub fn default_history_dir() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("share"))
+        })?;
+    Some(data_dir.join("rclean").join("history"))
+}
+
+/// write the history of a run as `<time>.json` into `dir`
+pub fn save_history(dir: &Path, history: &History) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = restore::unique_path(&dir.join(format!("{}.json", history.time)));
+    let json = serde_json::to_string_pretty(history).map_err(io::Error::other)?;
+    fs::write(&path, json)?;
+    Ok(path)
+}
+
+/// read the history of a run
+pub fn load_history(path: &Path) -> Result<History> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| CleanError::ConfigError(format!("cannot read {:?}: {e}", path.display())))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| CleanError::ConfigError(format!("{:?}: {e}", path.display())))
+}
+
+/// history files in `dir`, oldest first
+pub fn list_history(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    // timestamps sort chronologically as text
+    paths.sort();
+    paths
+}
+
+/// put the trashed and quarantined entries of a history file back
+///
+/// Entries whose original path exists again are skipped, as are
+/// permanently deleted ones, which are reported as failures.
+pub fn restore_from_manifest(path: &Path) -> Result<RestoreReport> {
+    let history = load_history(path)?;
+    let mut report = RestoreReport::default();
+    for entry in history.entries.iter() {
+        let original = &entry.original;
+        if !entry.is_restorable() {
+            report
+                .failed
+                .push((original.clone(), "deleted permanently".to_string()));
+        } else if original.symlink_metadata().is_ok() {
+            report.skipped.push(original.clone());
+        } else {
+            match restore_entry(entry) {
+                Ok(()) => report.restored.push(original.clone()),
+                Err(e) => report.failed.push((original.clone(), e.to_string())),
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// move a single trashed or quarantined entry back
+fn restore_entry(entry: &HistoryEntry) -> io::Result<()> {
+    if let Some(parent) = entry.original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match (&entry.disposition, &entry.location) {
+        (Disposition::Quarantined, Some(location)) => {
+            restore::move_entry(location, &entry.original, entry.size).map(|_| ())
+        }
+        (Disposition::Trashed, _) => restore_from_trash(&entry.original),
+        _ => Err(io::Error::other("location missing from history")),
+    }
+}
+
+/// restore the most recently trashed item with the given original path
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn restore_from_trash(original: &Path) -> io::Result<()> {
+    let items = trash::os_limited::list().map_err(io::Error::other)?;
+    let item = items
+        .into_iter()
+        .filter(|item| item.original_path() == original)
+        .max_by_key(|item| item.time_deleted)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found in the trash"))?;
+    trash::os_limited::restore_all([item]).map_err(io::Error::other)
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn restore_from_trash(_original: &Path) -> io::Result<()> {
+    Err(io::Error::other(
+        "restoring from the trash is not supported on this platform",
+    ))
+}
@@ -0,0 +1,88 @@
+// --------------------------------------------------------------------
+// ignore_file
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use log::warn;
+use std::path::{Path, PathBuf};
+
+use crate::constants::IGNORE_FILENAME;
+
+/// The `.rcleanignore` files of the directories around the entry being
+/// walked, from the base path down to the entry's parent
+///
+/// Entries have to be checked in walk order, so that the directories
+/// walked out of can be dropped. The lines of an ignore file follow the
+/// `.gitignore` syntax and apply to everything below its directory, a
+/// deeper file taking precedence over those above it.
+#[derive(Debug)]
+pub struct IgnoreStack {
+    root: PathBuf,
+    dirs: Vec<(PathBuf, Option<Gitignore>)>,
+}
+
+impl IgnoreStack {
+    /// a stack for walking the given base path
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dirs: vec![],
+        }
+    }
+
+    /// true if an ignore file in one of the entry's ancestors ignores it,
+    /// or one of its directories below the base path
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        // leave the directories walked out of
+        while self
+            .dirs
+            .last()
+            .is_some_and(|(dir, _)| !path.starts_with(dir) || path == dir)
+        {
+            self.dirs.pop();
+        }
+        // enter those between the deepest one so far and the entry, some
+        // of which were not walked with `min_depth`
+        let deepest = self.dirs.last().map(|(dir, _)| dir.clone());
+        let mut entered: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root) && Some(*dir) != deepest.as_deref())
+            .collect();
+        entered.reverse();
+        for dir in entered {
+            self.dirs.push((dir.to_path_buf(), load(dir)));
+        }
+        for (_, ignore_file) in self.dirs.iter().rev() {
+            let Some(ignore_file) = ignore_file else {
+                continue;
+            };
+            match ignore_file.matched_path_or_any_parents(path, is_dir) {
+                Match::None => continue,
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+            }
+        }
+        false
+    }
+}
+
+/// the ignore file of a directory, if it has one with any patterns
+fn load(dir: &Path) -> Option<Gitignore> {
+    let path = dir.join(IGNORE_FILENAME);
+    if !path.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(e) = builder.add(&path) {
+        warn!("{:?}: {e}", path.display());
+    }
+    match builder.build() {
+        Ok(ignore_file) if !ignore_file.is_empty() => Some(ignore_file),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("ignoring {:?}: {e}", path.display());
+            None
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// --------------------------------------------------------------------
+// in_use
+
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+#[cfg(target_os = "linux")]
+use std::collections::HashSet;
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::MetadataExt;
+
+/// Best-effort check whether files are open in some process, for
+/// `skip_in_use`
+///
+/// On Linux the open files of all processes readable under `/proc` are
+/// collected once, when the check is created. On Windows each file is
+/// opened without sharing, which fails while another handle is open.
+/// Elsewhere no file is ever reported as in use.
+#[derive(Debug, Default)]
+pub struct InUseCheck {
+    /// (device, inode) of every file open in a readable process
+    #[cfg(target_os = "linux")]
+    open: HashSet<(u64, u64)>,
+}
+
+impl InUseCheck {
+    /// take a snapshot of the open files where that is needed
+    pub fn new() -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            Self { open: open_files() }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Self {}
+        }
+    }
+
+    /// true if the check can tell anything on this platform
+    pub fn is_supported() -> bool {
+        cfg!(any(target_os = "linux", windows))
+    }
+
+    /// true if a file, or any file inside a directory, is open
+    pub fn is_in_use(&self, path: &Path) -> bool {
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return false;
+        };
+        if !metadata.is_dir() {
+            return self.is_file_in_use(path, &metadata);
+        }
+        WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .any(|e| {
+                e.metadata()
+                    .is_ok_and(|metadata| self.is_file_in_use(e.path(), &metadata))
+            })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn is_file_in_use(&self, _path: &Path, metadata: &fs::Metadata) -> bool {
+        metadata.is_file() && self.open.contains(&(metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(windows)]
+    fn is_file_in_use(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        use std::os::windows::fs::OpenOptionsExt;
+        // ERROR_SHARING_VIOLATION
+        const SHARING_VIOLATION: i32 = 32;
+        metadata.is_file()
+            && fs::OpenOptions::new()
+                .read(true)
+                .share_mode(0)
+                .open(path)
+                .is_err_and(|e| e.raw_os_error() == Some(SHARING_VIOLATION))
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    fn is_file_in_use(&self, _path: &Path, _metadata: &fs::Metadata) -> bool {
+        false
+    }
+}
+
+/// (device, inode) of the files open in every process whose descriptors
+/// can be read; others are silently passed over
+#[cfg(target_os = "linux")]
+fn open_files() -> HashSet<(u64, u64)> {
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return HashSet::new();
+    };
+    processes
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .bytes()
+                .all(|b| b.is_ascii_digit())
+        })
+        .filter_map(|e| fs::read_dir(e.path().join("fd")).ok())
+        .flat_map(|fds| fds.filter_map(|fd| fd.ok()))
+        // following the descriptor's link gives the open file itself
+        .filter_map(|fd| fs::metadata(fd.path()).ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+        .collect()
+}
@@ -1,33 +1,768 @@
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod config;
 pub mod constants;
+pub mod dedupe;
+pub mod error;
+pub mod event;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod history;
+pub mod ignore_file;
+pub mod in_use;
+pub mod plan;
+pub mod prompt;
+pub mod report;
+pub mod restore;
+pub mod utils;
 
 use dialoguer::Confirm;
 use fs_extra::dir::get_size;
-use globset::{Glob, GlobSetBuilder};
-use log::{info, warn};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use log::{error, info, warn};
 use logging_timer::time;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 use walkdir::WalkDir;
 
+use constants::{
+    get_preset_patterns, AUDIT_LOG_TARGET, CANDIDATE_PATTERN, DEFAULT_MAX_HASH_SIZE,
+    DEFAULT_PRUNE_PATTERNS, EMPTY_DIR_PATTERN, EMPTY_FILE_PATTERN, EXEC_BATCH_MAX_BYTES,
+    GITIGNORED_PATTERN, PARALLEL_DELETE_CHUNK, PRESET_NAMES, SELECT_LIMIT, STAGING_DIR_PREFIX,
+    SUMMARY_LOG_TARGET,
+};
+pub use dedupe::{DuplicateGroup, KeepPolicy};
+pub use error::{CleanError, Result};
+pub use event::CleanEvent;
+use event::SCAN_PROGRESS_INTERVAL;
+use history::{History, HistoryEntry};
+use ignore_file::IgnoreStack;
+use in_use::InUseCheck;
+use plan::{ApplyReport, Fingerprint, PlanEntry, ScanReport};
+pub use prompt::{Answer, Prompter, TargetPrompt};
+pub use report::{CleanReport, Disposition, MatchedItem, OutputFormat, Summary};
+use report::{Record, Report, ReportItem};
+use restore::{Manifest, MANIFEST_FILENAME};
+pub use utils::SizeUnits;
+use utils::{
+    format_rfc3339, format_size_in, format_timestamp, long_path, split_command, split_negation,
+};
+
 // --------------------------------------------------------------------
 // core
 
 /// Main configuration object for cleaning jobs with partial
 /// with selective (de)serialization
+///
+/// Settings files go through `config::CleanConfig`, which needs a field
+/// for every option as well.
 #[derive(Serialize, Deserialize)]
 pub struct CleaningJob {
     pub path: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
     pub patterns: Vec<String>,
     pub dry_run: bool,
     pub skip_confirmation: bool,
     pub include_symlinks: bool,
+    #[serde(default)]
+    pub trash_mode: bool,
+    #[serde(default)]
+    pub trash_fallback_delete: bool,
+    #[serde(default)]
+    pub secure_delete: bool,
+    #[serde(default)]
+    pub stage: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_path: Option<PathBuf>,
+    #[serde(default)]
+    pub skip_in_use: bool,
+    #[serde(default = "default_force")]
+    pub force: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_dir_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delete_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delete_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_deletions: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_size: Option<u64>,
+    #[serde(default)]
+    pub limit_ok: bool,
+    #[serde(default)]
+    pub error_policy: ErrorPolicy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    #[serde(default)]
+    pub parallel_delete: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub older_than_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newer_than_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protect_newer_than_secs: Option<u64>,
+    #[serde(default)]
+    pub age_field: AgeField,
+    /// units in which sizes are logged and reported
+    #[serde(default)]
+    pub size_units: SizeUnits,
+    #[serde(default)]
+    pub hidden_policy: HiddenPolicy,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regex_patterns: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_regex_patterns: Vec<String>,
+    #[serde(default)]
+    pub stats_mode: bool,
+    #[serde(default)]
+    pub report_free_space: bool,
+    /// warn if the volume is estimated to have less free space than this
+    /// once the matches are removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_free_after: Option<u64>,
+    #[serde(default)]
+    pub quiet: bool,
+    #[serde(default)]
+    pub remove_empty_files: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub match_types: Vec<EntryType>,
+    #[serde(default)]
+    pub prune_empty_dirs: bool,
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    #[serde(default)]
+    pub tree: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csv_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_depth: Option<usize>,
+    #[serde(default)]
+    pub use_gitignore: bool,
+    #[serde(default)]
+    pub respect_gitignore: bool,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    #[serde(default)]
+    pub interactive: bool,
+    #[serde(default)]
+    pub select: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirm_threshold: Option<u64>,
+    #[serde(default)]
+    pub skip_typed_confirmation: bool,
+    #[serde(default)]
+    pub confirm_dirs: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditional_patterns: Vec<ConditionalPattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owned_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prune_patterns: Vec<String>,
+    #[serde(default)]
+    pub no_default_prune: bool,
+    #[serde(default)]
+    pub no_rcleanignore: bool,
+    #[serde(default)]
+    pub accurate_size: bool,
+    #[serde(default)]
+    pub duplicates: bool,
+    #[serde(default)]
+    pub keep: KeepPolicy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hash_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_newest: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_oldest: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retention_patterns: Vec<RetentionPattern>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncate_patterns: Vec<String>,
+    #[serde(default)]
+    pub same_file_system: bool,
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    #[serde(default)]
+    pub symlink_behavior: SymlinkBehavior,
+    #[serde(default)]
+    pub untracked_only: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<String>,
+    #[serde(default)]
+    pub anchored: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_files: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_hook: Option<String>,
+    #[serde(default)]
+    pub hook_strict: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_delete_hook: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_delete_hook: Option<String>,
+    #[serde(default)]
+    pub hook_per_run: bool,
+    #[serde(default)]
+    pub hook_shell: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exec: Option<String>,
+    #[serde(default)]
+    pub exec_batch: bool,
     #[serde(skip_serializing, skip_deserializing)]
     targets: Vec<walkdir::DirEntry>,
     #[serde(skip_serializing, skip_deserializing)]
     size: u64,
     #[serde(skip_serializing, skip_deserializing)]
     counter: i32,
+    #[serde(skip_serializing, skip_deserializing)]
+    failed_deletions: Vec<(PathBuf, String)>,
+    #[serde(skip_serializing, skip_deserializing)]
+    skipped_in_use: Vec<PathBuf>,
+    #[serde(skip_serializing, skip_deserializing)]
+    in_use: Option<InUseCheck>,
+    #[serde(skip_serializing, skip_deserializing)]
+    skipped_by_hook: Vec<PathBuf>,
+    #[serde(skip_serializing, skip_deserializing)]
+    not_attempted: Vec<PathBuf>,
+    /// created along with its first entry
+    #[cfg(feature = "archive")]
+    #[serde(skip_serializing, skip_deserializing)]
+    archive: Option<archive::Archive>,
+    #[serde(skip_serializing, skip_deserializing)]
+    archive_size: Option<u64>,
+    /// available space on the volume of the path before and after the
+    /// removal, with `report_free_space`
+    #[serde(skip_serializing, skip_deserializing)]
+    free_space: (Option<u64>, Option<u64>),
+    /// available space before the removal and as estimated after it, with
+    /// `report_free_space` or `min_free_after`
+    #[serde(skip_serializing, skip_deserializing)]
+    free_space_estimate: Option<(u64, u64)>,
+    /// outcome of `pre_delete_hook` with `hook_per_run`, once it has run
+    #[serde(skip_serializing, skip_deserializing)]
+    pre_hook_passed: Option<bool>,
+    #[serde(skip_serializing, skip_deserializing)]
+    stats: HashMap<String, (usize, u64)>,
+    #[serde(skip_serializing, skip_deserializing)]
+    retained: HashMap<String, usize>,
+    #[serde(skip_serializing, skip_deserializing)]
+    removed: Vec<PathBuf>,
+    #[serde(skip_serializing, skip_deserializing)]
+    dispositions: HashMap<PathBuf, Disposition>,
+    #[serde(skip_serializing, skip_deserializing)]
+    matched: Vec<MatchedItem>,
+    #[serde(skip_serializing, skip_deserializing)]
+    matched_metadata: HashMap<PathBuf, fs::Metadata>,
+    #[serde(skip_serializing, skip_deserializing)]
+    matched_sizes: HashMap<PathBuf, u64>,
+    #[serde(skip_serializing, skip_deserializing)]
+    truncating: HashSet<PathBuf>,
+    #[serde(skip_serializing, skip_deserializing)]
+    truncated: bool,
+    #[serde(skip_serializing, skip_deserializing)]
+    scan_only: bool,
+    #[serde(skip_serializing, skip_deserializing)]
+    duplicate_groups: Vec<DuplicateGroup>,
+    #[serde(skip_serializing, skip_deserializing)]
+    manifest: Manifest,
+    #[serde(skip_serializing, skip_deserializing)]
+    quarantine_run: Option<PathBuf>,
+    #[serde(skip_serializing, skip_deserializing)]
+    history: History,
+    #[serde(skip_serializing, skip_deserializing)]
+    output: Option<Mutex<Box<dyn Write + Send>>>,
+}
+
+/// read-only items are made writable and retried by default on Windows,
+/// where build outputs and `.git` objects often carry the attribute
+fn default_force() -> bool {
+    cfg!(windows)
+}
+
+/// Timestamp compared against by the age filters
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgeField {
+    #[default]
+    Modified,
+    Accessed,
+    Created,
+}
+
+/// set once the unsupported timestamp warning has been shown
+static AGE_FIELD_WARNED: AtomicBool = AtomicBool::new(false);
+
+impl AgeField {
+    /// read this timestamp, falling back to the modification time
+    /// if the platform or filesystem does not record it
+    pub fn timestamp(self, metadata: &fs::Metadata) -> Option<SystemTime> {
+        let time = match self {
+            AgeField::Modified => metadata.modified(),
+            AgeField::Accessed => metadata.accessed(),
+            AgeField::Created => metadata.created(),
+        };
+        match time {
+            Ok(time) => Some(time),
+            Err(e) if self != AgeField::Modified => {
+                if !AGE_FIELD_WARNED.swap(true, Ordering::Relaxed) {
+                    warn!("{self} time unavailable ({e}), using modified time instead");
+                }
+                metadata.modified().ok()
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for AgeField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgeField::Modified => write!(f, "modified"),
+            AgeField::Accessed => write!(f, "accessed"),
+            AgeField::Created => write!(f, "created"),
+        }
+    }
+}
+
+impl FromStr for AgeField {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "modified" => Ok(AgeField::Modified),
+            "accessed" => Ok(AgeField::Accessed),
+            "created" => Ok(AgeField::Created),
+            _ => Err(format!(
+                "invalid age field: '{s}' (expected modified, accessed or created)"
+            )),
+        }
+    }
+}
+
+/// Whether hidden entries, those with a path component below the base
+/// path starting with `.`, are matched
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HiddenPolicy {
+    /// match hidden and other entries alike
+    #[default]
+    Include,
+    /// never match hidden entries, nor descend into hidden directories
+    Exclude,
+    /// only match hidden entries
+    Only,
+}
+
+impl HiddenPolicy {
+    /// true if an entry which is hidden or not may be matched
+    pub fn allows(self, hidden: bool) -> bool {
+        match self {
+            HiddenPolicy::Include => true,
+            HiddenPolicy::Exclude => !hidden,
+            HiddenPolicy::Only => hidden,
+        }
+    }
+}
+
+impl fmt::Display for HiddenPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HiddenPolicy::Include => write!(f, "include"),
+            HiddenPolicy::Exclude => write!(f, "exclude"),
+            HiddenPolicy::Only => write!(f, "only"),
+        }
+    }
+}
+
+impl FromStr for HiddenPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "include" => Ok(HiddenPolicy::Include),
+            "exclude" => Ok(HiddenPolicy::Exclude),
+            "only" => Ok(HiddenPolicy::Only),
+            _ => Err(format!(
+                "invalid hidden policy: '{s}' (expected include, exclude or only)"
+            )),
+        }
+    }
+}
+
+/// What removing a matched symlink does, with `include_symlinks`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkBehavior {
+    /// remove the symlink itself, leaving what it points to alone
+    #[default]
+    Unlink,
+    /// remove what the symlink points to, a whole tree for a directory,
+    /// then the symlink; only allowed inside the base paths
+    FollowAndDelete,
+}
+
+impl fmt::Display for SymlinkBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymlinkBehavior::Unlink => write!(f, "unlink"),
+            SymlinkBehavior::FollowAndDelete => write!(f, "follow-and-delete"),
+        }
+    }
+}
+
+impl FromStr for SymlinkBehavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "unlink" => Ok(SymlinkBehavior::Unlink),
+            "follow-and-delete" => Ok(SymlinkBehavior::FollowAndDelete),
+            _ => Err(format!(
+                "invalid symlink behavior: '{s}' (expected unlink or follow-and-delete)"
+            )),
+        }
+    }
+}
+
+/// What a job does when a target cannot be removed
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPolicy {
+    /// try every target, reporting the failures at the end
+    #[default]
+    Continue,
+    /// leave the remaining targets untouched after the first failure
+    AbortOnFirstError,
+    /// leave the remaining targets untouched once this many failed
+    AbortAfterN(u32),
+}
+
+impl ErrorPolicy {
+    /// true if removal stops once this many targets failed
+    pub fn aborts_at(self, failures: usize) -> bool {
+        match self {
+            ErrorPolicy::Continue => false,
+            ErrorPolicy::AbortOnFirstError => failures >= 1,
+            ErrorPolicy::AbortAfterN(n) => failures >= n as usize,
+        }
+    }
+}
+
+/// Kind of filesystem entry, as selected by `--type`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryType {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl EntryType {
+    /// true if a (non-followed) file type is of this kind
+    pub fn matches(self, file_type: fs::FileType) -> bool {
+        match self {
+            EntryType::File => file_type.is_file(),
+            EntryType::Dir => file_type.is_dir(),
+            EntryType::Symlink => file_type.is_symlink(),
+        }
+    }
+}
+
+impl fmt::Display for EntryType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryType::File => write!(f, "file"),
+            EntryType::Dir => write!(f, "dir"),
+            EntryType::Symlink => write!(f, "symlink"),
+        }
+    }
+}
+
+impl FromStr for EntryType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "f" | "file" => Ok(EntryType::File),
+            "d" | "dir" | "directory" => Ok(EntryType::Dir),
+            "l" | "symlink" | "link" => Ok(EntryType::Symlink),
+            _ => Err(format!("invalid entry type: '{s}' (expected f, d or l)")),
+        }
+    }
+}
+
+/// A glob which only matches when a marker file sits next to the match,
+/// e.g. `**/target` requiring a sibling `Cargo.toml`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ConditionalPattern {
+    pub pattern: String,
+    pub requires_sibling: String,
+}
+
+impl FromStr for ConditionalPattern {
+    type Err = String;
+
+    /// parse a 'PATTERN=FILE' pair
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.rsplit_once('=') {
+            Some((pattern, sibling)) if !pattern.is_empty() && !sibling.is_empty() => {
+                Ok(ConditionalPattern {
+                    pattern: pattern.to_string(),
+                    requires_sibling: sibling.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "invalid conditional pattern: '{s}' (expected PATTERN=FILE)"
+            )),
+        }
+    }
+}
+
+/// A glob of which the newest `keep` matches in each directory are
+/// retained, e.g. `**/*.log` keeping the 3 most recent logs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RetentionPattern {
+    pub pattern: String,
+    pub keep: usize,
+}
+
+impl FromStr for RetentionPattern {
+    type Err = String;
+
+    /// parse a 'PATTERN=N' pair
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.rsplit_once('=') {
+            Some((pattern, keep)) if !pattern.is_empty() => match keep.parse() {
+                Ok(keep) => Ok(RetentionPattern {
+                    pattern: pattern.to_string(),
+                    keep,
+                }),
+                Err(_) => Err(format!("invalid retention count: '{keep}'")),
+            },
+            _ => Err(format!(
+                "invalid retention pattern: '{s}' (expected PATTERN=N)"
+            )),
+        }
+    }
+}
+
+/// Compiled include patterns of a cleaning job
+struct Matchers {
+    patterns: Vec<String>,
+    /// sibling required by the glob at the same index, if any
+    siblings: Vec<Option<String>>,
+    globs: GlobSet,
+    exclude_globs: GlobSet,
+    prune_globs: GlobSet,
+    regexes: Vec<Regex>,
+    exclude_regexes: Vec<Regex>,
+    empty_files: bool,
+    quiet: bool,
+    match_types: Vec<EntryType>,
+    /// only match entries owned by this uid
+    owner: Option<u32>,
+    /// paths not ignored by git, set in either gitignore mode
+    unignored: Option<HashSet<PathBuf>>,
+    /// only match ignored paths rather than skipping them
+    only_ignored: bool,
+    /// match every candidate, set when paths are given without patterns
+    match_all: bool,
+    /// canonical paths tracked by git, and their directories
+    tracked: Option<HashSet<PathBuf>>,
+    /// base paths, as given and canonicalized, which patterns are matched
+    /// relative to in `anchored` mode
+    anchors: Option<Vec<PathBuf>>,
+    hidden: HiddenPolicy,
+    /// base paths below which path components count as hidden
+    hidden_roots: Vec<PathBuf>,
+}
+
+impl Matchers {
+    /// the first glob, or failing that the first regex, matching a path
+    ///
+    /// Regexes are searched for anywhere in the path, so anchor them
+    /// with `^` or `$` where needed.
+    fn find_matching_pattern(&self, path: &Path) -> Option<&str> {
+        let target = self.match_path(path)?;
+        let index = self.globs.matches(&target).into_iter().find(|&index| {
+            self.siblings[index]
+                .as_ref()
+                .is_none_or(|sibling| has_sibling(path, sibling))
+        });
+        if let Some(index) = index {
+            return Some(&self.patterns[index]);
+        }
+        let path = target.to_string_lossy();
+        self.regexes
+            .iter()
+            .find(|regex| regex.is_match(&path))
+            .map(|regex| regex.as_str())
+    }
+
+    /// the form of a path which patterns are matched against: relative to
+    /// its base path in `anchored` mode, the path itself otherwise
+    ///
+    /// None for a base path itself, which is never matched when anchored.
+    fn match_path<'p>(&self, path: &'p Path) -> Option<Cow<'p, Path>> {
+        match self.anchors.as_ref() {
+            Some(anchors) => relative_path(anchors, path),
+            None => Some(Cow::Borrowed(path)),
+        }
+    }
+
+    /// true if a path component below the base path starts with `.`
+    ///
+    /// Only the file name is checked for a path outside of the base paths.
+    fn is_hidden(&self, path: &Path) -> bool {
+        let is_dot = |name: &std::ffi::OsStr| name.to_string_lossy().starts_with('.');
+        match relative_path(&self.hidden_roots, path) {
+            Some(relative) => relative.components().any(|component| match component {
+                std::path::Component::Normal(name) => is_dot(name),
+                _ => false,
+            }),
+            None => path.file_name().is_some_and(is_dot),
+        }
+    }
+
+    /// match the topmost ignored entries when gitignore is the only pattern
+    ///
+    /// Entries inside an ignored directory are left to the directory itself.
+    fn match_gitignored(&self, path: &Path) -> Option<&str> {
+        let unignored = self.unignored.as_ref()?;
+        if !self.only_ignored || !self.patterns.is_empty() || !self.regexes.is_empty() {
+            return None;
+        }
+        let parent = path.parent()?;
+        unignored.contains(parent).then_some(GITIGNORED_PATTERN)
+    }
+
+    /// true if the entry itself, not a symlink target, has the required owner
+    #[cfg(unix)]
+    fn is_owned(&self, entry: &walkdir::DirEntry) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        self.owner
+            .is_none_or(|uid| fs::symlink_metadata(entry.path()).is_ok_and(|m| m.uid() == uid))
+    }
+
+    #[cfg(not(unix))]
+    fn is_owned(&self, _entry: &walkdir::DirEntry) -> bool {
+        true
+    }
+
+    /// true if the entry, or anything inside a directory entry, is
+    /// tracked by git
+    fn is_tracked(&self, path: &Path) -> bool {
+        let Some(tracked) = self.tracked.as_ref() else {
+            return false;
+        };
+        // resolve the parent only, so a symlink itself is not followed
+        let resolved = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => fs::canonicalize(parent).map(|p| p.join(name)),
+            _ => fs::canonicalize(path),
+        };
+        resolved.is_ok_and(|resolved| tracked.contains(&resolved))
+    }
+
+    /// true if the walk should not descend into this directory
+    ///
+    /// Hidden directories are pruned when hidden entries are excluded.
+    fn is_pruned(&self, entry: &walkdir::DirEntry) -> bool {
+        entry.depth() > 0
+            && entry.file_type().is_dir()
+            && (self.prune_globs.is_match(entry.path())
+                || (self.hidden == HiddenPolicy::Exclude
+                    && entry.file_name().to_string_lossy().starts_with('.')))
+    }
+
+    /// true if a negated glob matches the path or an exclude regex is found in it
+    fn is_excluded(&self, path: &Path) -> bool {
+        let path = self.match_path(path).unwrap_or(Cow::Borrowed(path));
+        if self.exclude_globs.is_match(&path) {
+            return true;
+        }
+        if self.exclude_regexes.is_empty() {
+            return false;
+        }
+        let path = path.to_string_lossy();
+        self.exclude_regexes
+            .iter()
+            .any(|regex| regex.is_match(&path))
+    }
+
+    /// the pattern matching an entry, including the synthetic empty-file
+    /// and gitignored patterns
+    fn match_entry(&self, entry: &walkdir::DirEntry) -> Option<&str> {
+        if let Some(unignored) = self.unignored.as_ref().filter(|_| self.only_ignored) {
+            if unignored.contains(entry.path()) {
+                return None;
+            }
+        }
+        let pattern = self
+            .find_matching_pattern(entry.path())
+            .or_else(|| {
+                let is_empty_file = self.empty_files
+                    && entry.file_type().is_file()
+                    && entry.metadata().is_ok_and(|m| m.len() == 0);
+                is_empty_file.then_some(EMPTY_FILE_PATTERN)
+            })
+            .or_else(|| self.match_gitignored(entry.path()))
+            .or_else(|| self.match_all.then_some(CANDIDATE_PATTERN))?;
+        if !self.match_types.is_empty()
+            && !self
+                .match_types
+                .iter()
+                .any(|t| t.matches(entry.file_type()))
+        {
+            return None;
+        }
+        if !self.is_owned(entry) {
+            return None;
+        }
+        if self.hidden != HiddenPolicy::Include && !self.hidden.allows(self.is_hidden(entry.path()))
+        {
+            return None;
+        }
+        if self.is_excluded(entry.path()) {
+            if !self.quiet {
+                info!("Excluded: {:?}", entry.path().display());
+            }
+            return None;
+        }
+        if self.is_tracked(entry.path()) {
+            if !self.quiet {
+                info!("Skipped (tracked by git): {:?}", entry.path().display());
+            }
+            return None;
+        }
+        Some(pattern)
+    }
 }
 
 /// Default values for a cleaningjob instance
@@ -36,20 +771,125 @@ impl Default for CleaningJob {
     fn default() -> Self {
         Self {
             path: ".".to_string(),
+            paths: vec![],
             patterns: vec![],
             dry_run: true,
             skip_confirmation: false,
             include_symlinks: false,
+            trash_mode: false,
+            trash_fallback_delete: false,
+            secure_delete: false,
+            stage: false,
+            archive_path: None,
+            skip_in_use: false,
+            force: default_force(),
+            min_size: None,
+            max_size: None,
+            min_dir_size: None,
+            max_delete_size: None,
+            max_delete_count: None,
+            max_deletions: None,
+            max_total_size: None,
+            limit_ok: false,
+            error_policy: ErrorPolicy::default(),
+            jobs: None,
+            parallel_delete: false,
+            older_than_secs: None,
+            newer_than_secs: None,
+            protect_newer_than_secs: None,
+            age_field: AgeField::Modified,
+            size_units: SizeUnits::Binary,
+            hidden_policy: HiddenPolicy::Include,
+            regex_patterns: vec![],
+            exclude_regex_patterns: vec![],
+            stats_mode: false,
+            report_free_space: false,
+            min_free_after: None,
+            quiet: false,
+            remove_empty_files: false,
+            match_types: vec![],
+            prune_empty_dirs: false,
+            output_format: OutputFormat::Text,
+            tree: false,
+            output_file: None,
+            csv_file: None,
+            backup_dir: None,
+            quarantine_dir: None,
+            history_dir: None,
+            max_depth: None,
+            min_depth: None,
+            use_gitignore: false,
+            respect_gitignore: false,
+            case_insensitive: false,
+            interactive: false,
+            select: false,
+            confirm_threshold: None,
+            skip_typed_confirmation: false,
+            confirm_dirs: false,
+            conditional_patterns: vec![],
+            owned_by: None,
+            prune_patterns: vec![],
+            no_default_prune: false,
+            no_rcleanignore: false,
+            accurate_size: false,
+            duplicates: false,
+            keep: KeepPolicy::Oldest,
+            max_hash_size: None,
+            keep_newest: None,
+            keep_oldest: None,
+            retention_patterns: vec![],
+            truncate_patterns: vec![],
+            same_file_system: false,
+            follow_symlinks: false,
+            symlink_behavior: SymlinkBehavior::default(),
+            untracked_only: false,
+            presets: vec![],
+            anchored: false,
+            exclude_files: vec![],
+            post_hook: None,
+            hook_strict: false,
+            pre_delete_hook: None,
+            post_delete_hook: None,
+            hook_per_run: false,
+            hook_shell: false,
+            exec: None,
+            exec_batch: false,
             targets: Vec::new(),
             size: 0,
             counter: 0,
+            failed_deletions: Vec::new(),
+            skipped_in_use: Vec::new(),
+            in_use: None,
+            skipped_by_hook: Vec::new(),
+            not_attempted: Vec::new(),
+            #[cfg(feature = "archive")]
+            archive: None,
+            archive_size: None,
+            free_space: (None, None),
+            free_space_estimate: None,
+            pre_hook_passed: None,
+            stats: HashMap::new(),
+            retained: HashMap::new(),
+            removed: Vec::new(),
+            dispositions: HashMap::new(),
+            matched: Vec::new(),
+            matched_metadata: HashMap::new(),
+            matched_sizes: HashMap::new(),
+            truncating: HashSet::new(),
+            truncated: false,
+            scan_only: false,
+            duplicate_groups: Vec::new(),
+            manifest: Manifest::default(),
+            quarantine_run: None,
+            history: History::default(),
+            output: None,
         }
     }
 }
 
 /// CleaningJob methods
 impl CleaningJob {
-    /// constructor
+    /// constructor, leaving every other option at its default
     pub fn new(
         path: String,
         patterns: Vec<String>,
@@ -63,101 +903,3414 @@ impl CleaningJob {
             dry_run,
             skip_confirmation,
             include_symlinks,
-            targets: Vec::new(),
-            size: 0,
-            counter: 0,
+            ..Self::default()
         }
     }
 
-    /// run the cleaning job
-    #[time("info")]
-    pub fn run(&mut self) {
-        // path cases
-        let path = Path::new(&self.path);
-        let current_path = Path::new(".");
-        let parent_path = Path::new("..");
+    /// check a job, e.g. one read from a settings file, before running
+    ///
+    /// Besides the contradictions checked on every run, all patterns must
+    /// compile, the base paths must be directories and there must be
+    /// something to match. All problems are reported together.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = self.contradictions();
+        let globs = self
+            .all_patterns()
+            .into_iter()
+            .map(|pattern| split_negation(&pattern).1.to_string())
+            .chain(self.conditional_patterns.iter().map(|c| c.pattern.clone()))
+            .chain(self.retention_patterns.iter().map(|r| r.pattern.clone()))
+            .chain(self.truncate_patterns.iter().cloned())
+            .chain(self.prune_patterns.iter().cloned());
+        for glob in globs {
+            if let Err(e) = GlobBuilder::new(&glob).build() {
+                problems.push(format!("invalid glob pattern: {e}"));
+            }
+        }
+        for exclude_file in self.exclude_files.iter() {
+            if let Err(CleanError::ConfigError(msg)) = config::read_exclude_file(exclude_file) {
+                problems.push(msg);
+            }
+        }
+        for regex in self
+            .regex_patterns
+            .iter()
+            .chain(self.exclude_regex_patterns.iter())
+        {
+            if let Err(e) = Regex::new(regex) {
+                problems.push(format!("invalid regex pattern '{regex}': {e}"));
+            }
+        }
+        for path in std::iter::once(&self.path).chain(self.paths.iter()) {
+            if !Path::new(path).is_dir() {
+                problems.push(format!("path '{path}' is not an existing directory"));
+            }
+        }
+        let has_patterns = self.patterns.iter().any(|p| !split_negation(p).0)
+            || !self.presets.is_empty()
+            || !self.regex_patterns.is_empty()
+            || !self.conditional_patterns.is_empty()
+            || !self.retention_patterns.is_empty()
+            || !self.truncate_patterns.is_empty();
+        if !has_patterns && !self.remove_empty_files && !self.use_gitignore {
+            problems.push("no patterns to match".to_string());
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(CleanError::ConfigError(problems.join("; ")))
+        }
+    }
 
-        let mut builder = GlobSetBuilder::new();
-        for pattern in self.patterns.iter() {
-            builder.add(Glob::new(pattern).unwrap());
+    /// contradictory job options, checked before every run
+    fn contradictions(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.error_policy == ErrorPolicy::AbortAfterN(0) {
+            problems.push("error_policy abort_after_n needs a limit of at least 1".to_string());
         }
-        let gset = builder.build().unwrap();
-        for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
-            let entry_path = entry.path();
-            // silently handle "." || ".." cases
-            if entry_path == current_path || entry_path == parent_path {
-                continue;
+        if let (Some(min_size), Some(max_size)) = (self.min_size, self.max_size) {
+            if min_size > max_size {
+                problems.push(format!(
+                    "min_size ({min_size}) is larger than max_size ({max_size})"
+                ));
             }
-            // skip paths which startwith ".."
-            if entry_path.starts_with("..") {
-                warn!("skipping {:?}", entry_path.display());
-                continue;
+        }
+        if let (Some(older), Some(newer)) = (self.older_than_secs, self.newer_than_secs) {
+            if older > newer {
+                problems.push(format!(
+                    "no entry can be older than {older}s and newer than {newer}s"
+                ));
             }
-            if gset.is_match(entry_path) {
-                match entry.path().metadata() {
-                    // Ok(info) => self.size += info.len(),
-                    Ok(_info) => self.size += get_size(entry_path).unwrap(),
-                    Err(e) => eprintln!("metadata not found: {:?}", e),
-                }
-                self.counter += 1;
-                if self.skip_confirmation {
-                    self.remove_entry(&entry);
-                    info!("Deleted: {:?}", entry_path.display());
-                } else {
-                    self.targets.push(entry.clone());
-                    info!("Matched: {:?}", entry_path.display());
+        }
+        if let (Some(min_depth), Some(max_depth)) = (self.min_depth, self.max_depth) {
+            if min_depth > max_depth {
+                problems.push(format!(
+                    "min_depth ({min_depth}) is larger than max_depth ({max_depth})"
+                ));
+            }
+        }
+        for preset in self
+            .presets
+            .iter()
+            .filter(|preset| get_preset_patterns(preset).is_none())
+        {
+            problems.push(format!(
+                "unknown preset '{preset}' (expected one of: {})",
+                PRESET_NAMES.join(", ")
+            ));
+        }
+        if self.quarantine_dir.is_some() && (self.backup_dir.is_some() || self.trash_mode) {
+            problems.push(
+                "quarantine_dir cannot be combined with backup_dir or trash_mode".to_string(),
+            );
+        }
+        if self.tree && self.output_format != OutputFormat::Text {
+            problems.push(format!(
+                "tree cannot be combined with the {} output format",
+                self.output_format
+            ));
+        }
+        if self.keep_newest.is_some() && self.keep_oldest.is_some() {
+            problems.push("keep_newest and keep_oldest cannot be combined".to_string());
+        }
+        if self.select && self.interactive {
+            problems.push("select and interactive cannot be combined".to_string());
+        }
+        if self.confirm_dirs && (self.interactive || self.select || self.stage || self.exec_batch) {
+            problems.push(
+                "confirm_dirs cannot be combined with interactive, select, stage or exec_batch"
+                    .to_string(),
+            );
+        }
+        if self.stage
+            && (self.trash_mode
+                || self.quarantine_dir.is_some()
+                || self.backup_dir.is_some()
+                || self.secure_delete
+                || self.interactive)
+        {
+            problems.push(
+                "stage cannot be combined with trash_mode, quarantine_dir, backup_dir, \
+                 secure_delete or interactive"
+                    .to_string(),
+            );
+        }
+        if self.parallel_delete && self.error_policy != ErrorPolicy::Continue {
+            problems.push(
+                "parallel_delete cannot be combined with an error_policy which aborts".to_string(),
+            );
+        }
+        if self.symlink_behavior == SymlinkBehavior::FollowAndDelete {
+            if !self.include_symlinks {
+                problems.push(
+                    "symlink_behavior follow_and_delete requires include_symlinks".to_string(),
+                );
+            }
+            if self.trash_mode
+                || self.quarantine_dir.is_some()
+                || self.backup_dir.is_some()
+                || self.stage
+                || self.archive_path.is_some()
+            {
+                problems.push(
+                    "symlink_behavior follow_and_delete cannot be combined with trash_mode, \
+                     quarantine_dir, backup_dir, stage or archive_path"
+                        .to_string(),
+                );
+            }
+        }
+        if self.secure_delete && (self.trash_mode || self.quarantine_dir.is_some()) {
+            problems.push(
+                "secure_delete cannot be combined with trash_mode or quarantine_dir".to_string(),
+            );
+        }
+        if self.use_gitignore && self.respect_gitignore {
+            problems.push("use_gitignore and respect_gitignore cannot be combined".to_string());
+        }
+        if self.exec.is_some()
+            && (self.archive_path.is_some()
+                || self.trash_mode
+                || self.quarantine_dir.is_some()
+                || self.backup_dir.is_some()
+                || self.stage
+                || self.secure_delete
+                || !self.truncate_patterns.is_empty())
+        {
+            problems.push(
+                "exec cannot be combined with archive_path, trash_mode, quarantine_dir, \
+                 backup_dir, stage, secure_delete or truncate_patterns"
+                    .to_string(),
+            );
+        }
+        if self.archive_path.is_some() && (self.quarantine_dir.is_some() || self.stage) {
+            problems
+                .push("archive_path cannot be combined with quarantine_dir or stage".to_string());
+        }
+        if cfg!(not(feature = "archive")) && self.archive_path.is_some() {
+            problems.push(
+                "archive_path requires rclean to be built with the 'archive' feature".to_string(),
+            );
+        }
+        if self.exec_batch && self.exec.is_none() {
+            problems.push("exec_batch requires exec".to_string());
+        }
+        if let Some(Err(e)) = self.exec.as_deref().map(split_command) {
+            problems.push(format!("exec: {e}"));
+        }
+        let hooks = [
+            ("pre_delete_hook", &self.pre_delete_hook),
+            ("post_delete_hook", &self.post_delete_hook),
+        ];
+        for (name, hook) in hooks {
+            if let (false, Some(hook)) = (self.hook_shell, hook) {
+                if let Err(e) = split_command(hook) {
+                    problems.push(format!("{name}: {e}"));
                 }
             }
         }
+        problems
+    }
 
-        if !self.targets.is_empty() && !self.skip_confirmation {
-            let confirmation = Confirm::new()
-                .with_prompt("Do you want to delete the above?")
-                .interact()
-                .unwrap();
-
-            if confirmation {
-                self.remove_targets();
+    /// compile the glob and regex patterns into matchers
+    fn build_matchers(&self) -> Result<Matchers> {
+        let problems = self.contradictions();
+        if !problems.is_empty() {
+            return Err(CleanError::ConfigError(problems.join("; ")));
+        }
+        let mut patterns = vec![];
+        let mut siblings = vec![];
+        let mut builder = GlobSetBuilder::new();
+        let mut exclude_builder = GlobSetBuilder::new();
+        // the first matching glob wins, so truncating takes precedence
+        for pattern in self.truncate_patterns.iter() {
+            builder.add(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(self.case_insensitive)
+                    .build()?,
+            );
+            patterns.push(pattern.clone());
+            siblings.push(None);
+        }
+        for pattern in self.all_patterns().iter() {
+            let (negated, glob) = split_negation(pattern);
+            let glob = GlobBuilder::new(glob)
+                .case_insensitive(self.case_insensitive)
+                .build()?;
+            if negated {
+                exclude_builder.add(glob);
             } else {
-                warn!("Cleaning operation cancelled.");
-                return;
+                patterns.push(pattern.clone());
+                siblings.push(None);
+                builder.add(glob);
             }
         }
-
-        if !self.dry_run {
-            info!(
-                "Deleted {} item(s) totalling {:.2} MB",
-                self.counter,
-                (self.size as f64) / 1000000.
+        for exclude_file in self.exclude_files.iter() {
+            for pattern in config::read_exclude_file(exclude_file)? {
+                exclude_builder.add(
+                    GlobBuilder::new(&pattern)
+                        .case_insensitive(self.case_insensitive)
+                        .build()?,
+                );
+            }
+        }
+        for conditional in self.conditional_patterns.iter() {
+            builder.add(
+                GlobBuilder::new(&conditional.pattern)
+                    .case_insensitive(self.case_insensitive)
+                    .build()?,
+            );
+            patterns.push(conditional.pattern.clone());
+            siblings.push(Some(conditional.requires_sibling.clone()));
+        }
+        for retention in self.retention_patterns.iter() {
+            builder.add(
+                GlobBuilder::new(&retention.pattern)
+                    .case_insensitive(self.case_insensitive)
+                    .build()?,
             );
+            patterns.push(retention.pattern.clone());
+            siblings.push(None);
         }
+        Ok(Matchers {
+            patterns,
+            siblings,
+            globs: builder.build()?,
+            exclude_globs: exclude_builder.build()?,
+            prune_globs: self.build_prune_globs()?,
+            regexes: build_regexes(&self.regex_patterns, self.case_insensitive)?,
+            exclude_regexes: build_regexes(&self.exclude_regex_patterns, self.case_insensitive)?,
+            empty_files: self.remove_empty_files,
+            quiet: self.quiet,
+            match_types: self.match_types.clone(),
+            owner: self.resolve_owner()?,
+            unignored: (self.use_gitignore || self.respect_gitignore)
+                .then(|| self.collect_unignored()),
+            only_ignored: self.use_gitignore,
+            match_all: false,
+            tracked: self
+                .untracked_only
+                .then(|| self.collect_tracked())
+                .transpose()?,
+            anchors: self.anchored.then(|| self.anchors()),
+            hidden: self.hidden_policy,
+            hidden_roots: if self.hidden_policy == HiddenPolicy::Include {
+                vec![]
+            } else {
+                self.anchors()
+            },
+        })
     }
 
-    /// remove collected targets
-    pub fn remove_targets(&self) {
-        for name in self.targets.iter() {
-            if !self.dry_run {                
-                self.remove_entry(name);
+    /// the base paths as given and canonicalized, longest first so that a
+    /// path is stripped of the most specific one
+    fn anchors(&self) -> Vec<PathBuf> {
+        let mut anchors = vec![];
+        for root in self.roots() {
+            if let Ok(canonical) = fs::canonicalize(&root) {
+                anchors.push(canonical);
             }
+            anchors.push(root);
         }
+        anchors.sort_by_key(|a| std::cmp::Reverse(a.as_os_str().len()));
+        anchors.dedup();
+        anchors
     }
 
-    /// remove file or directory with some safety measures
-    pub fn remove_entry(&self, entry: &walkdir::DirEntry) {
-        let p = entry.path();
-        let target = entry.metadata().unwrap();
-        if target.is_symlink() {
-            if self.include_symlinks {
-                fs::remove_file(p).expect("could not remove symlink: {p}");
-            } else {
-                warn!("skipping symlink: {:?}", entry.path().display());
+    /// the glob patterns followed by those of the presets, without
+    /// duplicates
+    fn all_patterns(&self) -> Vec<String> {
+        let mut patterns = self.patterns.clone();
+        for preset in self.presets.iter() {
+            for pattern in get_preset_patterns(preset).unwrap_or_default() {
+                if !patterns.contains(&pattern) {
+                    patterns.push(pattern);
+                }
             }
-        } else if target.is_file() {
-            fs::remove_file(p).expect("could not remove file: {p}");
-        } else if target.is_dir() {
-            fs::remove_dir_all(p).expect("could not remove directory: {p}");
-        } else {
-            warn!("skipping unknowm: {:?}", entry.path().display());
         }
+        patterns
+    }
+
+    /// paths tracked by the git repositories enclosing the base paths
+    #[cfg(feature = "git")]
+    fn collect_tracked(&self) -> Result<HashSet<PathBuf>> {
+        let mut tracked = HashSet::new();
+        for root in self.roots() {
+            tracked.extend(git::tracked_paths(&root)?);
+        }
+        Ok(tracked)
     }
+
+    #[cfg(not(feature = "git"))]
+    fn collect_tracked(&self) -> Result<HashSet<PathBuf>> {
+        Err(CleanError::ConfigError(
+            "untracked_only requires rclean to be built with the 'git' feature".to_string(),
+        ))
+    }
+
+    /// compile the prune patterns, including the defaults unless disabled
+    fn build_prune_globs(&self) -> Result<GlobSet> {
+        let defaults = DEFAULT_PRUNE_PATTERNS
+            .iter()
+            .copied()
+            .filter(|_| !self.no_default_prune);
+        let mut builder = GlobSetBuilder::new();
+        for pattern in defaults.chain(self.prune_patterns.iter().map(|p| p.as_str())) {
+            builder.add(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(self.case_insensitive)
+                    .build()?,
+            );
+        }
+        Ok(builder.build()?)
+    }
+
+    /// the uid entries must be owned by, if `owned_by` is set
+    #[cfg(unix)]
+    fn resolve_owner(&self) -> Result<Option<u32>> {
+        self.owned_by
+            .as_deref()
+            .map(utils::resolve_uid)
+            .transpose()
+            .map_err(CleanError::ConfigError)
+    }
+
+    #[cfg(not(unix))]
+    fn resolve_owner(&self) -> Result<Option<u32>> {
+        match self.owned_by {
+            Some(_) => Err(CleanError::ConfigError(
+                "owned_by is only supported on unix".to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// paths below the base paths which git does not ignore
+    ///
+    /// Honours `.gitignore`, `.git/info/exclude` and the global gitignore,
+    /// also outside of a git repository. The base paths are always included.
+    fn collect_unignored(&self) -> HashSet<PathBuf> {
+        let mut unignored = HashSet::new();
+        for root in self.roots() {
+            let walk = ignore::WalkBuilder::new(root)
+                .hidden(false)
+                .ignore(false)
+                .parents(true)
+                .require_git(false)
+                .build();
+            unignored.extend(walk.filter_map(|e| e.ok()).map(|e| e.into_path()));
+        }
+        unignored
+    }
+
+    /// the base paths to clean: `path` followed by any extra `paths`
+    ///
+    /// Duplicate paths and paths inside another base path are dropped,
+    /// comparing their canonical forms.
+    fn roots(&self) -> Vec<PathBuf> {
+        let mut roots: Vec<(PathBuf, PathBuf)> = vec![];
+        for path in std::iter::once(&self.path).chain(self.paths.iter()) {
+            let root = PathBuf::from(path);
+            let canonical = fs::canonicalize(&root).unwrap_or_else(|_| root.clone());
+            if roots.iter().any(|(_, c)| canonical.starts_with(c)) {
+                continue;
+            }
+            roots.retain(|(_, c)| !c.starts_with(&canonical));
+            roots.push((root, canonical));
+        }
+        roots.into_iter().map(|(root, _)| root).collect()
+    }
+
+    /// the base path an entry was found under
+    fn root_of<'a>(roots: &'a [PathBuf], path: &Path) -> Option<(usize, &'a Path)> {
+        roots
+            .iter()
+            .enumerate()
+            .find(|(_, root)| path.starts_with(root))
+            .map(|(index, root)| (index, root.as_path()))
+    }
+
+    /// run the cleaning job
+    ///
+    /// The returned report is a snapshot of the outcome; the accessors
+    /// such as `summary` and `failed_deletions` remain available as well.
+    pub fn run(&mut self) -> Result<CleanReport> {
+        self.run_with_callback(|_| {})
+    }
+
+    /// run the cleaning job, reporting progress to `callback`
+    ///
+    /// The callback is invoked on the calling thread, also when `jobs` is set.
+    pub fn run_with_callback<F: FnMut(&CleanEvent)>(
+        &mut self,
+        mut callback: F,
+    ) -> Result<CleanReport> {
+        self.execute(None, &mut callback, &mut prompt::Terminal)?;
+        Ok(self.report())
+    }
+
+    /// run the cleaning job, asking `prompt` instead of the terminal about
+    /// each target in `interactive` mode
+    pub fn run_with_prompt<P: FnMut(&TargetPrompt) -> Answer>(
+        &mut self,
+        mut prompt: P,
+    ) -> Result<CleanReport> {
+        self.execute(None, &mut |_| {}, &mut prompt)?;
+        Ok(self.report())
+    }
+
+    /// run the cleaning job, letting `prompter` instead of the terminal
+    /// choose the targets in `interactive` and `select` mode
+    pub fn run_with_prompter(&mut self, prompter: &mut dyn Prompter) -> Result<CleanReport> {
+        self.execute(None, &mut |_| {}, prompter)?;
+        Ok(self.report())
+    }
+
+    /// run the cleaning job on the given candidate paths instead of walking
+    ///
+    /// Candidates are filtered as walked entries would be, and must lie
+    /// inside one of the base paths. Missing paths are skipped.
+    pub fn run_on_paths<I: IntoIterator<Item = PathBuf>>(
+        &mut self,
+        paths: I,
+    ) -> Result<CleanReport> {
+        self.run_on_paths_with_callback(paths, |_| {})
+    }
+
+    /// run the cleaning job on the given candidate paths, reporting
+    /// progress to `callback`
+    pub fn run_on_paths_with_callback<I, F>(
+        &mut self,
+        paths: I,
+        mut callback: F,
+    ) -> Result<CleanReport>
+    where
+        I: IntoIterator<Item = PathBuf>,
+        F: FnMut(&CleanEvent),
+    {
+        self.execute(
+            Some(&mut paths.into_iter()),
+            &mut callback,
+            &mut prompt::Terminal,
+        )?;
+        Ok(self.report())
+    }
+
+    /// collect the targets from the candidates, or by walking the base
+    /// paths, then remove them
+    #[time("info")]
+    fn execute(
+        &mut self,
+        candidates: Option<&mut dyn Iterator<Item = PathBuf>>,
+        on_event: &mut dyn FnMut(&CleanEvent),
+        prompter: &mut dyn Prompter,
+    ) -> Result<()> {
+        let mut matchers = self.build_matchers()?;
+        // the caller supplies the candidates directly, so without any
+        // patterns every one of them is taken
+        matchers.match_all = candidates.is_some()
+            && matchers.patterns.is_empty()
+            && matchers.regexes.is_empty()
+            && !matchers.empty_files
+            && !matchers.only_ignored;
+        let sizer = EntrySizer::new(self.accurate_size);
+        if self.roots().len() <= self.paths.len() {
+            warn!("skipping paths which are duplicates of or inside other paths");
+        }
+        self.start_run()?;
+        if let (false, None, Some(output_file)) = (self.scan_only, &self.output, &self.output_file)
+        {
+            self.output = Some(Mutex::new(Box::new(fs::File::create(output_file)?)));
+        }
+        match (candidates, self.jobs) {
+            (_, _) if self.duplicates => self.collect_duplicates(&matchers, on_event),
+            (Some(paths), _) => self.collect_candidates(&matchers, &sizer, paths, on_event),
+            (None, Some(jobs)) => {
+                self.collect_targets_parallel(&matchers, &sizer, jobs, on_event)?
+            }
+            (None, None) => self.collect_targets(&matchers, &sizer, on_event),
+        }
+        // keep the confirmation listing and removal order deterministic
+        self.targets.sort_by(|a, b| a.path().cmp(b.path()));
+        self.matched.sort_by(|a, b| a.path.cmp(&b.path));
+        self.drop_nested_targets();
+        if self.scan_only {
+            return Ok(());
+        }
+
+        if !self.check_removal(prompter)? {
+            return Ok(());
+        }
+        let typed_confirmation = self.needs_typed_confirmation();
+
+        if !self.targets.is_empty() && self.skip_confirmation {
+            // removal was deferred until the totals were known
+            self.remove_confirmed_targets(prompter, on_event);
+        } else if !self.targets.is_empty() && self.select {
+            self.remove_selected_targets(prompter, on_event)?;
+        } else if !self.targets.is_empty() && self.interactive {
+            self.remove_targets_interactively(prompter, on_event);
+        } else if !self.targets.is_empty() {
+            let confirmation = typed_confirmation || {
+                self.display_confirmation_summary();
+                Confirm::new()
+                    .with_prompt(match &self.exec {
+                        Some(_) => format!(
+                            "Do you want to run the command on {} item(s)?",
+                            self.counter
+                        ),
+                        None => "Do you want to delete the above?".to_string(),
+                    })
+                    .interact()
+                    .unwrap()
+            };
+
+            if confirmation {
+                self.remove_confirmed_targets(prompter, on_event);
+            } else {
+                warn!("Cleaning operation cancelled.");
+                return Ok(());
+            }
+        }
+
+        if self.prune_empty_dirs {
+            self.prune_empty_dirs(&matchers, on_event);
+        }
+
+        self.run_post_delete_hook_per_run();
+        self.finish_archive()?;
+        self.save_run_records()?;
+
+        for (path, reason) in self.failed_deletions.iter() {
+            error!("could not remove {:?}: {}", path.display(), reason);
+        }
+        if self.aborted() {
+            warn!(
+                "Stopped after {} failure(s), leaving {} item(s) untouched",
+                self.failed_deletions.len(),
+                self.not_attempted.len()
+            );
+        }
+        if !self.skipped_in_use.is_empty() {
+            warn!(
+                "Skipped {} item(s) which were in use",
+                self.skipped_in_use.len()
+            );
+        }
+        if !self.skipped_by_hook.is_empty() {
+            warn!(
+                "Skipped {} item(s) for which the pre-delete hook failed",
+                self.skipped_by_hook.len()
+            );
+        }
+
+        if let (false, Some(exec)) = (self.dry_run, &self.exec) {
+            info!(target: SUMMARY_LOG_TARGET, "Ran {exec:?} on {} item(s)", self.counter);
+        } else if let (false, Some(run_dir)) = (self.dry_run, &self.quarantine_run) {
+            info!(
+                target: SUMMARY_LOG_TARGET,
+                "Quarantined {} item(s) totalling {} into {:?}",
+                self.counter,
+                self.display_size(self.size),
+                run_dir.display()
+            );
+        } else if !self.dry_run && self.trash_mode {
+            info!(
+                target: SUMMARY_LOG_TARGET,
+                "Moved {} item(s) totalling {} to trash",
+                self.counter,
+                self.display_size(self.size)
+            );
+            let deleted = self.count_disposition(Disposition::Deleted);
+            if deleted > 0 {
+                warn!("Deleted {deleted} item(s) which could not be trashed");
+            }
+        } else if !self.dry_run {
+            info!(
+                target: SUMMARY_LOG_TARGET,
+                "Deleted {} item(s) totalling {}",
+                self.counter,
+                self.display_size(self.size)
+            );
+        } else {
+            info!(
+                target: SUMMARY_LOG_TARGET,
+                "Dry-run: {} item(s) totalling {} would be removed",
+                self.counter,
+                self.display_size(self.size)
+            );
+        }
+        self.report_free_space();
+        let truncated = self.count_disposition(Disposition::Truncated);
+        if truncated > 0 {
+            info!(target: SUMMARY_LOG_TARGET, "Truncated {truncated} file(s) in place");
+        }
+        if self.stats_mode {
+            self.display_stats();
+        }
+        if self.truncated {
+            warn!("Results are incomplete: matching stopped at the configured limit");
+        }
+        match self.output_format {
+            OutputFormat::Text if self.tree => self.emit(self.to_tree().trim_end())?,
+            OutputFormat::Text => {}
+            OutputFormat::Json => self.emit(&self.to_json())?,
+            OutputFormat::Ndjson => {
+                self.emit(&report::to_json_line(&Record::Summary(&self.summary())))?
+            }
+            OutputFormat::Csv => self.emit(self.to_csv().trim_end())?,
+            OutputFormat::Markdown => self.emit(self.to_markdown().trim_end())?,
+            OutputFormat::Print0 => match self.output.as_ref() {
+                Some(output) => report::write_print0(&mut *output.lock().unwrap(), &self.matched)?,
+                None => report::write_print0(std::io::stdout().lock(), &self.matched)?,
+            },
+        }
+        if let Some(output) = self.output.take() {
+            output.into_inner().unwrap().flush()?;
+        }
+        if let Some(csv_file) = &self.csv_file {
+            self.write_csv(fs::File::create(csv_file)?)?;
+        }
+        self.run_post_hook()?;
+        if self.aborted() {
+            return Err(CleanError::DeletionAborted {
+                failed: self.failed_deletions.len(),
+                remaining: self.not_attempted.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// start the history of a run and prepare the directories it moves
+    /// or copies entries into
+    fn start_run(&mut self) -> Result<()> {
+        // each run quarantines into its own dated subdirectory
+        let timestamp = format_timestamp(SystemTime::now());
+        self.quarantine_run = self.quarantine_dir.as_ref().map(|dir| dir.join(&timestamp));
+        self.history = History {
+            time: timestamp,
+            entries: vec![],
+        };
+        if !self.dry_run && !self.scan_only {
+            if self.secure_delete {
+                warn!(
+                    "shredding overwrites files once with zeros; on SSDs and copy-on-write \
+                     or journaling file systems the old data may still be recoverable"
+                );
+            }
+            self.prepare_backup_dir()?;
+            self.prepare_quarantine_dir()?;
+            self.prepare_archive()?;
+        }
+        self.free_space = (None, None);
+        if self.report_free_space && !self.dry_run && !self.scan_only {
+            self.free_space.0 = self.available_space();
+        }
+        self.in_use = match (self.skip_in_use, self.scan_only) {
+            (true, false) if InUseCheck::is_supported() => Some(InUseCheck::new()),
+            (true, false) => {
+                warn!("files in use cannot be detected on this platform");
+                None
+            }
+            _ => None,
+        };
+        Ok(())
+    }
+
+    /// available space on the volume of the path, only warning if it
+    /// cannot be read
+    fn available_space(&self) -> Option<u64> {
+        match fs2::available_space(&self.path) {
+            Ok(space) => Some(space),
+            Err(e) => {
+                warn!("cannot read the free space of {:?}: {e}", self.path);
+                None
+            }
+        }
+    }
+
+    /// log the free space on the volume of the path and an estimate of
+    /// what it will be once the targets are removed, with
+    /// `report_free_space` or `min_free_after`, warning if the estimate
+    /// is below `min_free_after`
+    ///
+    /// The estimate assumes the whole size of the targets comes back,
+    /// except when they are moved to the trash or a command is run on
+    /// them, so it is optimistic for hardlinked, sparse or reflinked files.
+    fn estimate_free_space(&mut self) {
+        self.free_space_estimate = None;
+        if !self.report_free_space && self.min_free_after.is_none() {
+            return;
+        }
+        let Some(before) = self.available_space() else {
+            return;
+        };
+        let reclaimed = if self.trash_mode || self.exec.is_some() {
+            0
+        } else {
+            self.size
+        };
+        let after = before.saturating_add(reclaimed);
+        self.free_space_estimate = Some((before, after));
+        info!(
+            target: SUMMARY_LOG_TARGET,
+            "Disk free on {:?}: {} before, {} after (estimated)",
+            self.path,
+            self.display_size(before),
+            self.display_size(after)
+        );
+        if let Some(min_free_after) = self.min_free_after.filter(|&min| after < min) {
+            warn!(
+                "{:?} would still have only {} free after the removal, below the minimum of {}",
+                self.path,
+                self.display_size(after),
+                self.display_size(min_free_after)
+            );
+        }
+    }
+
+    /// sample the free space again once the removal is done and log how
+    /// much came back, next to the size of what was removed
+    ///
+    /// The two differ for hardlinked, sparse or reflinked files, for
+    /// items moved to a trash on the same volume, and when other
+    /// processes write to the volume meanwhile.
+    fn report_free_space(&mut self) {
+        let Some(before) = self.free_space.0 else {
+            return;
+        };
+        let Some(after) = self.available_space() else {
+            return;
+        };
+        self.free_space.1 = Some(after);
+        let change = if after >= before {
+            format!("{} reclaimed", self.display_size(after - before))
+        } else {
+            format!("{} less", self.display_size(before - after))
+        };
+        info!(
+            target: SUMMARY_LOG_TARGET,
+            "Free space on {:?}: {} before, {} after ({change}, {} removed)",
+            self.path,
+            self.display_size(before),
+            self.display_size(after),
+            self.display_size(self.size)
+        );
+    }
+
+    /// write the backup manifest and the history of a run
+    fn save_run_records(&self) -> Result<()> {
+        if let Some(backup_dir) = self.backup_dir.as_ref().or(self.quarantine_run.as_ref()) {
+            if !self.manifest.entries.is_empty() {
+                let manifest_path = backup_dir.join(MANIFEST_FILENAME);
+                self.manifest.save(&manifest_path)?;
+                info!(
+                    target: SUMMARY_LOG_TARGET,
+                    "Backup manifest written to {:?}",
+                    manifest_path.display()
+                );
+            }
+        }
+        if let Some(history_dir) = &self.history_dir {
+            if !self.history.entries.is_empty() {
+                // the run is done at this point, so only warn
+                match history::save_history(history_dir, &self.history) {
+                    Ok(path) => info!("History written to {:?}", path.display()),
+                    Err(e) => warn!("could not write history: {e}"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// collect the targets without removing anything, as a plan which
+    /// `apply` can carry out later
+    pub fn scan(&mut self) -> Result<ScanReport> {
+        self.scan_only = true;
+        let scanned = self.execute(None, &mut |_| {}, &mut prompt::Terminal);
+        self.scan_only = false;
+        scanned?;
+        let mut entries = vec![];
+        for item in self.matched.iter() {
+            let fingerprint = match Fingerprint::of(&item.path) {
+                Ok(fingerprint) => fingerprint,
+                Err(e) => {
+                    warn!("skipping {:?}: {e}", item.path.display());
+                    continue;
+                }
+            };
+            entries.push(PlanEntry {
+                path: restore::absolute_path(&item.path)?,
+                pattern: item.pattern.clone(),
+                size: item.size,
+                is_dir: item.is_dir,
+                fingerprint,
+            });
+        }
+        // resolved, so the plan can be applied from another directory
+        let base = |path: &String| {
+            fs::canonicalize(path).map_or(path.clone(), |path| path.display().to_string())
+        };
+        Ok(ScanReport {
+            time: self.history.time.clone(),
+            path: base(&self.path),
+            paths: self.paths.iter().map(base).collect(),
+            entries,
+            truncated: self.truncated,
+        })
+    }
+
+    /// check the assembled targets against the safety caps and the
+    /// `limit_ok` cut-off, then ask for the typed confirmation above
+    /// `confirm_threshold`, before anything is removed
+    ///
+    /// Returns false if the typed confirmation was refused.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ConfigError` if the targets go past `max_delete_size` or
+    /// `max_delete_count`, or were cut off without `limit_ok`.
+    fn check_removal(&mut self, prompter: &mut dyn Prompter) -> Result<bool> {
+        if let Some(max_delete_size) = self.max_delete_size {
+            if self.size > max_delete_size {
+                return Err(CleanError::ConfigError(format!(
+                    "{} matched, more than max_delete_size of {}; nothing was removed",
+                    self.display_size(self.size),
+                    self.display_size(max_delete_size)
+                )));
+            }
+        }
+        if let Some(max_delete_count) = self.max_delete_count {
+            if self.counter as usize > max_delete_count {
+                return Err(CleanError::ConfigError(format!(
+                    "{} item(s) matched, more than max_delete_count of {}; nothing was removed",
+                    self.counter, max_delete_count
+                )));
+            }
+        }
+        if self.truncated && !self.dry_run && !self.limit_ok {
+            return Err(CleanError::ConfigError(
+                "matches were cut off at max_deletions or max_total_size; nothing was removed \
+                 (pass --limit-ok to remove the matches collected so far)"
+                    .to_string(),
+            ));
+        }
+        self.estimate_free_space();
+
+        if self.needs_typed_confirmation() {
+            self.display_confirmation_summary();
+            let count = self.counter as usize;
+            let typed = prompter.type_confirmation(&format!(
+                "This removes {} item(s) totalling {}; type \"delete\" or {} to continue",
+                count,
+                self.display_size(self.size),
+                count
+            ));
+            let typed = typed.trim();
+            if typed != "delete" && typed != count.to_string() {
+                warn!("Cleaning operation cancelled.");
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// true if a planned target is still matched and passes the filters,
+    /// as it would be when walking the base paths of the plan
+    fn still_matches(
+        &self,
+        matchers: &Matchers,
+        plan: &ScanReport,
+        target: &walkdir::DirEntry,
+        item_size: u64,
+    ) -> bool {
+        let path = target.path();
+        let unignored = matchers
+            .unignored
+            .as_ref()
+            .filter(|_| !matchers.only_ignored);
+        let ignored = !self.no_rcleanignore
+            && plan.base_of(path).is_some_and(|root| {
+                IgnoreStack::new(&root).is_ignored(path, target.file_type().is_dir())
+            });
+        unignored.is_none_or(|u| u.contains(path))
+            && !ignored
+            && matchers.match_entry(target).is_some()
+            && self.passes_filters(target, item_size)
+    }
+
+    /// remove the targets of a plan made by `scan`, as this job would
+    /// remove them, e.g. into the trash or in a dry-run
+    ///
+    /// Targets which no longer exist, whose fingerprint changed since the
+    /// plan was made, which this job's patterns, excludes, ignore files or
+    /// filters no longer match, or which lie outside the base paths of the
+    /// plan, e.g. in a plan edited by hand, are reported and left alone
+    /// while the others are still removed. Without any patterns the job
+    /// takes each target by the pattern it was planned with. Targets inside another target of the
+    /// plan go along with it and are not counted, whatever order the plan
+    /// lists them in.
+    ///
+    /// The targets left are held to the same caps and `limit_ok` cut-off
+    /// as those of a run, and above `confirm_threshold` the removal is
+    /// confirmed by typing on the terminal.
+    pub fn apply(&mut self, plan: &ScanReport) -> Result<ApplyReport> {
+        self.apply_with_prompter(plan, &mut prompt::Terminal)
+    }
+
+    /// remove the targets of a plan, letting `prompter` instead of the
+    /// terminal confirm a removal above `confirm_threshold`
+    pub fn apply_with_prompter(
+        &mut self,
+        plan: &ScanReport,
+        prompter: &mut dyn Prompter,
+    ) -> Result<ApplyReport> {
+        let mut matchers = self.build_matchers()?;
+        matchers.match_all = matchers.patterns.is_empty()
+            && matchers.regexes.is_empty()
+            && !matchers.empty_files
+            && !matchers.only_ignored;
+        self.start_run()?;
+        let mut report = ApplyReport::default();
+        let mut planned: HashSet<&Path> = HashSet::new();
+        // a directory has to come before everything inside it
+        let mut entries: Vec<&PlanEntry> = plan.entries.iter().collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in entries {
+            let path = entry.path.as_path();
+            if path.ancestors().skip(1).any(|a| planned.contains(a)) {
+                continue;
+            }
+            let target = WalkDir::new(path).max_depth(0).into_iter().next();
+            let (Some(Ok(target)), Ok(fingerprint)) = (target, Fingerprint::of(path)) else {
+                warn!("skipping missing path {:?}", path.display());
+                report.missing.push(path.to_path_buf());
+                continue;
+            };
+            if !plan.is_inside(path) {
+                warn!("skipping {:?}: not inside the planned path", path.display());
+                report.outside.push(path.to_path_buf());
+                continue;
+            }
+            if fingerprint != entry.fingerprint {
+                warn!(
+                    "skipping {:?}: changed since the plan was made",
+                    path.display()
+                );
+                report.stale.push(path.to_path_buf());
+                continue;
+            }
+            if !self.still_matches(&matchers, plan, &target, entry.size) {
+                warn!("skipping {:?}: no longer matched", path.display());
+                report.stale.push(path.to_path_buf());
+                continue;
+            }
+            planned.insert(path);
+            self.size += entry.size;
+            self.counter += 1;
+            let stat = self.stats.entry(entry.pattern.clone()).or_default();
+            stat.0 += 1;
+            stat.1 += entry.size;
+            if self.truncate_patterns.contains(&entry.pattern) {
+                self.truncating.insert(path.to_path_buf());
+            }
+            self.matched.push(MatchedItem {
+                path: path.to_path_buf(),
+                pattern: entry.pattern.clone(),
+                size: entry.size,
+                is_dir: entry.is_dir,
+            });
+            self.matched_sizes.insert(path.to_path_buf(), entry.size);
+            self.targets.push(target);
+        }
+        self.truncated = plan.truncated;
+        if !self.check_removal(prompter)? {
+            return Ok(report);
+        }
+        self.remove_targets_with(&mut |_| {});
+        report.removed = self
+            .matched
+            .iter()
+            .filter(|item| {
+                !matches!(
+                    self.disposition(&item.path),
+                    Disposition::Kept
+                        | Disposition::Failed
+                        | Disposition::InUse
+                        | Disposition::Skipped
+                )
+            })
+            .map(|item| item.path.clone())
+            .collect();
+        report.failed = self.failed_deletions.clone();
+        report.not_attempted = self.not_attempted.clone();
+        self.run_post_delete_hook_per_run();
+        self.finish_archive()?;
+        self.save_run_records()?;
+        self.report_free_space();
+        self.run_post_hook()?;
+        Ok(report)
+    }
+
+    /// run the `post_hook` command through the shell, if anything was
+    /// removed, passing the totals in `RCLEAN_*` environment variables
+    ///
+    /// A failing hook is only an error with `hook_strict` set.
+    fn run_post_hook(&self) -> Result<()> {
+        let Some(hook) = &self.post_hook else {
+            return Ok(());
+        };
+        let (count, size) = self
+            .matched
+            .iter()
+            .filter(|item| self.disposition(&item.path).is_removal())
+            .fold((0, 0), |(count, size), item| (count + 1, size + item.size));
+        if self.dry_run || count == 0 {
+            return Ok(());
+        }
+        let mut command = shell_command(hook);
+        command
+            .env("RCLEAN_COUNT", count.to_string())
+            .env("RCLEAN_SIZE", size.to_string())
+            .env("RCLEAN_PATH", &self.path);
+        let Err(problem) = self.run_command(command, &format!("post hook {hook:?}")) else {
+            return Ok(());
+        };
+        if self.hook_strict {
+            return Err(CleanError::HookError(problem));
+        }
+        error!("{problem}");
+        Ok(())
+    }
+
+    /// write the structured output of the next run to `writer` instead of
+    /// `output_file` or stdout
+    ///
+    /// In `OutputFormat::Ndjson` every match, removal and failure is
+    /// written as it happens, so the output can be consumed while the run
+    /// goes on. The writer is flushed and dropped once the run is done.
+    pub fn set_output<W: Write + Send + 'static>(&mut self, writer: W) {
+        self.output = Some(Mutex::new(Box::new(writer)));
+    }
+
+    /// write a line of structured output to the writer set with
+    /// `set_output`, `output_file` or stdout
+    fn emit(&self, line: &str) -> std::io::Result<()> {
+        match self.output.as_ref() {
+            Some(output) => writeln!(output.lock().unwrap(), "{line}"),
+            None => writeln!(std::io::stdout(), "{line}"),
+        }
+    }
+
+    /// stream a record in `OutputFormat::Ndjson`, warning if it cannot be
+    /// written
+    fn stream(&self, record: &Record) {
+        if self.output_format == OutputFormat::Ndjson {
+            if let Err(e) = self.emit(&report::to_json_line(record)) {
+                warn!("could not write output: {e}");
+            }
+        }
+    }
+
+    /// report a removed entry to `on_event` and the NDJSON stream
+    fn notify_deleted(&self, path: &Path, size: u64, on_event: &mut dyn FnMut(&CleanEvent)) {
+        self.stream(&Record::Deleted { path, size });
+        on_event(&CleanEvent::Deleted { path, size });
+    }
+
+    /// totals for the job so far
+    pub fn summary(&self) -> Summary {
+        Summary {
+            path: self.path.clone(),
+            dry_run: self.dry_run,
+            count: self.counter as usize,
+            size: self.size,
+            failed: Summary::failures(&self.failed_deletions),
+            skipped_in_use: self.skipped_in_use.len(),
+            skipped_by_hook: self.skipped_by_hook.len(),
+            remaining: self.not_attempted.len(),
+            archive_size: self.archive_size,
+            free_space_before: self
+                .free_space
+                .0
+                .or(self.free_space_estimate.map(|(before, _)| before)),
+            free_space_after: self.free_space.1,
+            free_space_after_estimate: self.free_space_estimate.map(|(_, after)| after),
+            max_deletions: self.max_deletions,
+            max_total_size: self.max_total_size,
+            truncated: self.truncated,
+        }
+    }
+
+    /// snapshot of what the job removed so far
+    pub fn report(&self) -> CleanReport {
+        CleanReport {
+            deleted_count: self.removed.len(),
+            total_size: self
+                .removed
+                .iter()
+                .filter_map(|path| self.matched_sizes.get(path))
+                .sum(),
+            failed: self.failed_deletions.clone(),
+            stats: self.stats.clone(),
+            dry_run: self.dry_run,
+        }
+    }
+
+    /// entries counted by the job, with the pattern each one matched
+    pub fn matched(&self) -> &[MatchedItem] {
+        &self.matched
+    }
+
+    /// paths of the entries counted by the job, with their metadata as
+    /// read when they were matched
+    pub fn matches(&self) -> impl Iterator<Item = (&Path, &fs::Metadata)> {
+        self.matched.iter().filter_map(|item| {
+            let metadata = self.matched_metadata.get(&item.path)?;
+            Some((item.path.as_path(), metadata))
+        })
+    }
+
+    /// paths of the entries counted by the job
+    pub fn matched_paths(&self) -> Vec<PathBuf> {
+        self.matched.iter().map(|item| item.path.clone()).collect()
+    }
+
+    /// the matched entries and summary as a single JSON document
+    pub fn to_json(&self) -> String {
+        let summary = self.summary();
+        let matches = self
+            .matched
+            .iter()
+            .map(|item| ReportItem {
+                item,
+                disposition: self.disposition(&item.path),
+            })
+            .collect();
+        let report = Report {
+            matches,
+            duplicates: &self.duplicate_groups,
+            summary: &summary,
+        };
+        report::to_json_line(&report)
+    }
+
+    /// what happened to a matched entry
+    pub fn disposition(&self, path: &Path) -> Disposition {
+        self.dispositions.get(path).copied().unwrap_or_default()
+    }
+
+    fn count_disposition(&self, disposition: Disposition) -> usize {
+        self.dispositions
+            .values()
+            .filter(|&&d| d == disposition)
+            .count()
+    }
+
+    /// the stats and matched entries as a Markdown report
+    pub fn to_markdown(&self) -> String {
+        report::to_markdown(
+            &self.path,
+            self.dry_run,
+            &self.stats,
+            &self.matched,
+            self.size_units,
+        )
+    }
+
+    /// the matched entries as a tree below the path, marked with what
+    /// happened to each of them
+    pub fn to_tree(&self) -> String {
+        report::render_tree(Path::new(&self.path), &self.matched, |path| {
+            self.disposition(path)
+        })
+    }
+
+    /// the matched entries as CSV, with what happened to each of them
+    pub fn to_csv(&self) -> String {
+        let mut buffer = Vec::new();
+        self.write_csv(&mut buffer)
+            .expect("writing to a buffer cannot fail");
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// write a CSV row for every matched entry with its type, size,
+    /// modification time, pattern and what happened to it, e.g.
+    /// `would-delete` in a dry-run, along with the error of a failure
+    pub fn write_csv<W: std::io::Write>(&self, writer: W) -> std::io::Result<()> {
+        let errors: HashMap<&Path, &str> = self
+            .failed_deletions
+            .iter()
+            .map(|(path, error)| (path.as_path(), error.as_str()))
+            .collect();
+        let rows = self.matched.iter().map(|item| {
+            let metadata = self.matched_metadata.get(&item.path);
+            let kind = match metadata {
+                Some(metadata) if metadata.is_symlink() => "symlink",
+                _ if item.is_dir => "dir",
+                _ => "file",
+            };
+            report::CsvRow {
+                path: &item.path,
+                kind,
+                size_bytes: item.size,
+                modified_rfc3339: metadata
+                    .and_then(|metadata| metadata.modified().ok())
+                    .map(format_rfc3339),
+                matched_pattern: &item.pattern,
+                action: self.disposition(&item.path).to_string().replace(' ', "-"),
+                error: errors.get(item.path.as_path()).copied(),
+            }
+        });
+        report::write_csv(writer, rows)
+    }
+
+    /// create the backup directory, refusing one which is already in use
+    /// or which lies inside the cleaned path
+    fn prepare_backup_dir(&self) -> Result<()> {
+        let Some(backup_dir) = &self.backup_dir else {
+            return Ok(());
+        };
+        fs::create_dir_all(backup_dir)?;
+        if backup_dir.join(MANIFEST_FILENAME).exists() {
+            return Err(CleanError::ConfigError(format!(
+                "backup directory {:?} already contains a manifest",
+                backup_dir.display()
+            )));
+        }
+        self.check_outside_roots(backup_dir, "backup")
+    }
+
+    /// create this run's quarantine directory, refusing one which lies
+    /// inside the cleaned path
+    fn prepare_quarantine_dir(&self) -> Result<()> {
+        let Some(run_dir) = &self.quarantine_run else {
+            return Ok(());
+        };
+        fs::create_dir_all(run_dir)?;
+        self.check_outside_roots(run_dir, "quarantine")
+    }
+
+    /// refuse an existing archive, or one inside the cleaned path
+    fn prepare_archive(&self) -> Result<()> {
+        let Some(archive_path) = &self.archive_path else {
+            return Ok(());
+        };
+        if archive_path.exists() {
+            return Err(CleanError::ConfigError(format!(
+                "archive {:?} already exists",
+                archive_path.display()
+            )));
+        }
+        let dir = archive_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        self.check_outside_roots(dir, "archive")
+    }
+
+    /// pack an entry into the archive, creating it first if needed
+    #[cfg(feature = "archive")]
+    fn archive_entry(&mut self, path: &Path) -> std::io::Result<()> {
+        let name = self.backup_relative_path(path);
+        let archive = match (self.archive.take(), &self.archive_path) {
+            (Some(archive), _) => archive,
+            (None, Some(archive_path)) => archive::Archive::create(archive_path)?,
+            (None, None) => return Ok(()),
+        };
+        self.archive.insert(archive).append(path, &name)
+    }
+
+    #[cfg(not(feature = "archive"))]
+    fn archive_entry(&mut self, _path: &Path) -> std::io::Result<()> {
+        Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+    }
+
+    /// write the end of the archive, if anything went into it, and log
+    /// its size next to the size reclaimed
+    #[cfg(feature = "archive")]
+    fn finish_archive(&mut self) -> Result<()> {
+        let Some(archive) = self.archive.take() else {
+            return Ok(());
+        };
+        let size = archive.finish()?;
+        self.archive_size = Some(size);
+        let report = self.report();
+        info!(
+            target: SUMMARY_LOG_TARGET,
+            "Archived {} item(s) into {:?}: {} archive, {} reclaimed",
+            report.deleted_count,
+            self.archive_path
+                .as_deref()
+                .unwrap_or(Path::new(""))
+                .display(),
+            self.display_size(size),
+            self.display_size(report.total_size)
+        );
+        Ok(())
+    }
+
+    #[cfg(not(feature = "archive"))]
+    fn finish_archive(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// refuse a backup or quarantine directory inside a cleaned path
+    fn check_outside_roots(&self, dir: &Path, kind: &str) -> Result<()> {
+        let dir = fs::canonicalize(dir)?;
+        for root in self.roots() {
+            if dir.starts_with(fs::canonicalize(root)?) {
+                return Err(CleanError::ConfigError(format!(
+                    "{kind} directory {:?} must be outside the cleaned path",
+                    dir.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// log the number and size of matched items per pattern
+    fn display_stats(&self) {
+        // "HH:MM:SS [INFO] " and the indent before each line
+        const LOG_PREFIX_WIDTH: usize = 18;
+        // the log goes to stdout only for the text output format
+        let (terminal, color) = if self.output_format == OutputFormat::Text {
+            (console::Term::stdout(), console::colors_enabled())
+        } else {
+            (console::Term::stderr(), console::colors_enabled_stderr())
+        };
+        let width = terminal
+            .size_checked()
+            .map(|(_, columns)| usize::from(columns).saturating_sub(LOG_PREFIX_WIDTH));
+        info!(target: SUMMARY_LOG_TARGET, "Pattern statistics:");
+        for line in report::render_stats(
+            &self.stats,
+            width,
+            color && width.is_some(),
+            self.size_units,
+        ) {
+            info!(target: SUMMARY_LOG_TARGET, "  {line}");
+        }
+        let mut retained: Vec<_> = self.retained.iter().collect();
+        retained.sort();
+        for (pattern, count) in retained {
+            info!(target: SUMMARY_LOG_TARGET, "  {}: {} item(s) retained", pattern, count);
+        }
+        if !self.duplicate_groups.is_empty() {
+            info!(target: SUMMARY_LOG_TARGET, "Duplicate groups:");
+            for group in self.duplicate_groups.iter() {
+                info!(
+                    target: SUMMARY_LOG_TARGET,
+                    "  {:?}: {} copy(ies) of {}",
+                    group.kept.display(),
+                    group.removed.len(),
+                    self.display_size(group.size)
+                );
+            }
+        }
+    }
+
+    /// list the collected targets grouped by pattern before prompting
+    fn display_confirmation_summary(&self) {
+        let targets: HashSet<&Path> = self.targets.iter().map(|e| e.path()).collect();
+        let mut groups: BTreeMap<&str, (usize, u64)> = BTreeMap::new();
+        for item in self.matched.iter() {
+            if targets.contains(item.path.as_path()) {
+                let group = groups.entry(item.pattern.as_str()).or_default();
+                group.0 += 1;
+                group.1 += item.size;
+            }
+        }
+        info!(
+            target: SUMMARY_LOG_TARGET,
+            "{} the following:",
+            if self.exec.is_some() {
+                "About to run the command on"
+            } else if self.trash_mode {
+                "About to trash"
+            } else {
+                "About to delete"
+            }
+        );
+        let (mut total_count, mut total_size) = (0, 0);
+        for (pattern, (count, size)) in groups {
+            info!(
+                target: SUMMARY_LOG_TARGET,
+                "  {}: {} item(s), {}",
+                pattern,
+                count,
+                self.display_size(size)
+            );
+            total_count += count;
+            total_size += size;
+        }
+        info!(
+            target: SUMMARY_LOG_TARGET,
+            "  total: {} item(s), {}",
+            total_count,
+            self.display_size(total_size)
+        );
+    }
+
+    /// number and total size of matched items per pattern
+    pub fn stats(&self) -> &HashMap<String, (usize, u64)> {
+        &self.stats
+    }
+
+    /// walker over a base path, limited to the configured depths
+    ///
+    /// Depth is relative to the base path: the base path itself is at
+    /// depth 0 and its direct children are at depth 1. Paths are kept as
+    /// given, so that they compare with the base paths; on Windows the
+    /// standard library already reads directories beyond 260 characters.
+    fn walker(&self, root: &Path) -> WalkDir {
+        let mut walker = WalkDir::new(root);
+        if let Some(max_depth) = self.max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+        if let Some(min_depth) = self.min_depth {
+            walker = walker.min_depth(min_depth);
+        }
+        walker
+            .same_file_system(self.same_file_system)
+            .follow_links(self.follow_symlinks)
+    }
+
+    /// entries to be matched, skipping pruned directories, those ignored
+    /// by a `.rcleanignore` file unless `no_rcleanignore` is set, and the
+    /// subtrees ignored by git in `respect_gitignore` mode
+    fn walk<'a>(&self, matchers: &'a Matchers) -> impl Iterator<Item = walkdir::DirEntry> + 'a {
+        let unignored = matchers
+            .unignored
+            .as_ref()
+            .filter(|_| !matchers.only_ignored);
+        let follow_symlinks = self.follow_symlinks;
+        let ignore_files = !self.no_rcleanignore;
+        let walkers: Vec<(PathBuf, WalkDir, Option<u64>)> = self
+            .roots()
+            .into_iter()
+            .map(|root| {
+                let walker = self.walker(&root);
+                let root_device = self.same_file_system.then(|| device_id(&root)).flatten();
+                (root, walker, root_device)
+            })
+            .collect();
+        walkers
+            .into_iter()
+            .flat_map(move |(root, walker, root_device)| {
+                let real_root = follow_symlinks
+                    .then(|| fs::canonicalize(&root).unwrap_or_else(|_| root.clone()));
+                let mut ignore_stack = ignore_files.then(|| IgnoreStack::new(&root));
+                walker
+                    .into_iter()
+                    .filter_entry(move |e| {
+                        unignored.is_none_or(|u| u.contains(e.path()))
+                            && !matchers.is_pruned(e)
+                            && !crosses_file_system(root_device, e.path())
+                            && !real_root.as_ref().is_some_and(|r| escapes_root(r, e))
+                            && !ignore_stack
+                                .as_mut()
+                                .is_some_and(|s| s.is_ignored(e.path(), e.file_type().is_dir()))
+                    })
+                    .filter_map(|e| e.ok())
+                    // the base path itself is never a target, also when a
+                    // pattern such as "**/*" matches it
+                    .filter(move |e| e.depth() > 0 && should_process(&root, e.path()))
+            })
+    }
+
+    /// walk the path and handle each entry matching a pattern
+    fn collect_targets(
+        &mut self,
+        matchers: &Matchers,
+        sizer: &EntrySizer,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        let mut scanned = 0;
+        // retention needs every match before any of them can be handled
+        let mut deferred = vec![];
+        for entry in self.walk(matchers) {
+            scanned += 1;
+            if let Some(pattern) = matchers.match_entry(&entry) {
+                let item_size = sizer.size(entry.path());
+                if self.has_retention() {
+                    deferred.push((entry, pattern, item_size));
+                } else {
+                    self.handle_matched_entry(entry, pattern, item_size, on_event);
+                }
+            }
+            if self.truncated {
+                break;
+            }
+            if scanned % SCAN_PROGRESS_INTERVAL == 0 {
+                on_event(&CleanEvent::ScanProgress {
+                    scanned,
+                    matched: self.matched.len() + deferred.len(),
+                });
+            }
+        }
+        self.handle_matches(deferred, on_event);
+        on_event(&CleanEvent::ScanProgress {
+            scanned,
+            matched: self.matched.len(),
+        });
+    }
+
+    /// walk the path, then handle all but one copy of each set of
+    /// identical matching files
+    fn collect_duplicates(&mut self, matchers: &Matchers, on_event: &mut dyn FnMut(&CleanEvent)) {
+        let mut scanned = 0;
+        let mut files = HashMap::new();
+        for entry in self.walk(matchers) {
+            scanned += 1;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            if let Some(pattern) = matchers.match_entry(&entry) {
+                files.insert(entry.path().to_path_buf(), (entry, pattern));
+            }
+        }
+        on_event(&CleanEvent::ScanProgress {
+            scanned,
+            matched: files.len(),
+        });
+        let max_hash_size = self.max_hash_size.unwrap_or(DEFAULT_MAX_HASH_SIZE);
+        let groups = dedupe::find_duplicates(files.keys().cloned(), max_hash_size, self.keep);
+        for group in groups.iter() {
+            self.log_entry("Keeping", &group.kept);
+            for path in group.removed.iter() {
+                if let Some((entry, pattern)) = files.remove(path) {
+                    self.handle_matched_entry(entry, pattern, group.size, on_event);
+                }
+            }
+        }
+        let reclaimable: u64 = groups
+            .iter()
+            .map(|group| group.size * group.removed.len() as u64)
+            .sum();
+        info!(
+            target: SUMMARY_LOG_TARGET,
+            "Found {} set(s) of duplicates, {} reclaimable",
+            groups.len(),
+            self.display_size(reclaimable)
+        );
+        self.duplicate_groups = groups;
+    }
+
+    /// sets of identical files found in `duplicates` mode
+    pub fn duplicate_groups(&self) -> &[DuplicateGroup] {
+        &self.duplicate_groups
+    }
+
+    /// handle each given path which is inside a base path and matches a pattern
+    fn collect_candidates(
+        &mut self,
+        matchers: &Matchers,
+        sizer: &EntrySizer,
+        paths: &mut dyn Iterator<Item = PathBuf>,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        let roots: Vec<PathBuf> = self
+            .roots()
+            .iter()
+            .filter_map(|root| fs::canonicalize(root).ok())
+            .collect();
+        let mut scanned = 0;
+        let mut deferred = vec![];
+        for path in paths {
+            let entry = match WalkDir::new(&path).max_depth(0).into_iter().next() {
+                Some(Ok(entry)) => entry,
+                _ => {
+                    warn!("skipping missing path {:?}", path.display());
+                    continue;
+                }
+            };
+            // resolve the parent only, so a symlink itself is not followed
+            let resolved = match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) => {
+                    let parent = if parent.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        parent
+                    };
+                    fs::canonicalize(parent)
+                        .map(|parent| parent.join(name))
+                        .ok()
+                }
+                _ => None,
+            };
+            let root = resolved.as_ref().and_then(|resolved| {
+                Self::root_of(&roots, resolved)
+                    .filter(|(_, root)| resolved != root && should_process(root, resolved))
+                    .map(|(_, root)| root)
+            });
+            let Some(root) = root else {
+                warn!("skipping {:?}: not inside the cleaned path", path.display());
+                continue;
+            };
+            let root_device = self.same_file_system.then(|| device_id(root)).flatten();
+            if crosses_file_system(root_device, &path) {
+                continue;
+            }
+            scanned += 1;
+            if let Some(pattern) = matchers.match_entry(&entry) {
+                let item_size = sizer.size(entry.path());
+                if self.has_retention() {
+                    deferred.push((entry, pattern, item_size));
+                } else {
+                    self.handle_matched_entry(entry, pattern, item_size, on_event);
+                }
+            }
+            if self.truncated {
+                break;
+            }
+        }
+        self.handle_matches(deferred, on_event);
+        on_event(&CleanEvent::ScanProgress {
+            scanned,
+            matched: self.matched.len(),
+        });
+    }
+
+    /// walk the path, then match and size the entries across a thread pool
+    ///
+    /// A `jobs` count of 0 uses one thread per available core.
+    fn collect_targets_parallel(
+        &mut self,
+        matchers: &Matchers,
+        sizer: &EntrySizer,
+        jobs: usize,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) -> Result<()> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build()
+            .map_err(|e| CleanError::ConfigError(format!("cannot start thread pool: {e}")))?;
+        let entries: Vec<walkdir::DirEntry> = self.walk(matchers).collect();
+        let scanned = entries.len();
+        let mut matches: Vec<(walkdir::DirEntry, &str, u64)> = pool.install(|| {
+            entries
+                .into_par_iter()
+                .filter_map(|e| {
+                    let pattern = matchers.match_entry(&e)?;
+                    let item_size = sizer.size(e.path());
+                    Some((e, pattern, item_size))
+                })
+                .collect()
+        });
+        matches.sort_by(|a, b| a.0.path().cmp(b.0.path()));
+        self.handle_matches(matches, on_event);
+        on_event(&CleanEvent::ScanProgress {
+            scanned,
+            matched: self.matched.len(),
+        });
+        Ok(())
+    }
+
+    /// handle matches collected in walk order
+    ///
+    /// Retention only chooses among the matches which pass the filters,
+    /// so that an entry skipped anyway never takes the place of one
+    /// which would be removed.
+    fn handle_matches(
+        &mut self,
+        matches: Vec<(walkdir::DirEntry, &str, u64)>,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        let filtered = self.has_retention();
+        let matches = if filtered {
+            let passing = matches
+                .into_iter()
+                .filter(|(entry, _, item_size)| self.passes_filters(entry, *item_size))
+                .collect();
+            self.apply_retention(passing)
+        } else {
+            matches
+        };
+        for (entry, pattern, item_size) in matches {
+            if self.truncated {
+                break;
+            }
+            // entries inside an already removed directory are never reached
+            // by a sequential walk, so skip them here as well
+            if self.removes_while_collecting() && fs::symlink_metadata(entry.path()).is_err() {
+                continue;
+            }
+            if filtered {
+                self.collect_matched_entry(entry, pattern, item_size, on_event);
+            } else {
+                self.handle_matched_entry(entry, pattern, item_size, on_event);
+            }
+        }
+    }
+
+    /// true if matches are removed as soon as they are found, rather than
+    /// once all of them have been collected
+    ///
+    /// The safety caps need the totals before anything is removed. A
+    /// dry-run removes nothing, so the walk still descends into matched
+    /// directories and their nested matches have to be dropped afterwards.
+    /// Stopping at an `error_policy` limit needs to know which targets
+    /// are left, and `parallel_delete` needs all of them at once.
+    fn removes_while_collecting(&self) -> bool {
+        self.skip_confirmation
+            && !self.dry_run
+            && self.max_delete_size.is_none()
+            && self.max_delete_count.is_none()
+            && self.max_deletions.is_none()
+            && self.max_total_size.is_none()
+            && (self.confirm_threshold.is_none() || self.skip_typed_confirmation)
+            && !self.stage
+            && !self.exec_batch
+            && !self.scan_only
+            && !self.confirm_dirs
+            && self.error_policy == ErrorPolicy::Continue
+            && !self.parallel_delete
+    }
+
+    /// true if the matches total more than `confirm_threshold`, so the
+    /// word "delete" or the number of items has to be typed to remove them
+    ///
+    /// With `skip_confirmation` this is only waived by
+    /// `skip_typed_confirmation`; dry-runs never need it.
+    fn needs_typed_confirmation(&self) -> bool {
+        let Some(threshold) = self.confirm_threshold else {
+            return false;
+        };
+        let waived = self.skip_confirmation && self.skip_typed_confirmation;
+        !self.dry_run && !self.targets.is_empty() && self.size > threshold && !waived
+    }
+
+    /// true if counting a match of the given size would go past
+    /// `max_deletions` or `max_total_size`
+    fn exceeds_limits(&self, item_size: u64) -> bool {
+        self.max_deletions
+            .is_some_and(|max| self.counter as usize >= max)
+            || self
+                .max_total_size
+                .is_some_and(|max| self.size + item_size > max)
+    }
+
+    /// true if collection stopped at `max_deletions` or `max_total_size`,
+    /// so matches beyond the limit were left out
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// true if some matches may be retained by `keep_newest`,
+    /// `keep_oldest` or `retention_patterns`
+    fn has_retention(&self) -> bool {
+        self.keep_newest.is_some()
+            || self.keep_oldest.is_some()
+            || !self.retention_patterns.is_empty()
+    }
+
+    /// number of matches of a pattern retained in each directory, and
+    /// whether those are the newest rather than the oldest ones
+    fn retention_for(&self, pattern: &str) -> Option<(usize, bool)> {
+        self.retention_patterns
+            .iter()
+            .find(|retention| retention.pattern == pattern)
+            .map(|retention| (retention.keep, true))
+            .or(self.keep_newest.map(|keep| (keep, true)))
+            .or(self.keep_oldest.map(|keep| (keep, false)))
+    }
+
+    /// drop the newest (or oldest) matches of each pattern in each
+    /// directory, from matches which already passed the filters
+    ///
+    /// Matches are grouped by pattern and parent directory and the
+    /// `retention_for` newest or oldest of each group by modification
+    /// time are retained; the order of the remaining matches is preserved.
+    fn apply_retention<'m>(
+        &mut self,
+        matches: Vec<(walkdir::DirEntry, &'m str, u64)>,
+    ) -> Vec<(walkdir::DirEntry, &'m str, u64)> {
+        if !self.has_retention() {
+            return matches;
+        }
+        let mut groups: BTreeMap<(&str, &Path), Vec<(SystemTime, usize)>> = BTreeMap::new();
+        for (index, (entry, pattern, _)) in matches.iter().enumerate() {
+            if self.retention_for(pattern).is_none() {
+                continue;
+            }
+            let parent = entry.path().parent().unwrap_or(Path::new(""));
+            let modified = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            groups
+                .entry((pattern, parent))
+                .or_default()
+                .push((modified, index));
+        }
+        let mut kept = HashSet::new();
+        for ((pattern, _), mut group) in groups {
+            let (keep, newest) = self.retention_for(pattern).unwrap_or_default();
+            // newest (or oldest) first, ties broken by walk order
+            group.sort_by(|a, b| {
+                let by_age = if newest { b.0.cmp(&a.0) } else { a.0.cmp(&b.0) };
+                by_age.then(a.1.cmp(&b.1))
+            });
+            kept.extend(group.into_iter().take(keep).map(|(_, index)| index));
+        }
+        let mut remaining = vec![];
+        for (index, item) in matches.into_iter().enumerate() {
+            if kept.contains(&index) {
+                self.log_entry("Retained", item.0.path());
+                *self.retained.entry(item.1.to_string()).or_default() += 1;
+            } else {
+                remaining.push(item);
+            }
+        }
+        remaining
+    }
+
+    /// number of matches per pattern retained by `keep_newest`,
+    /// `keep_oldest` or `retention_patterns`
+    pub fn retained(&self) -> &HashMap<String, usize> {
+        &self.retained
+    }
+
+    /// apply the filters to a matched entry, then remove or collect it
+    fn handle_matched_entry(
+        &mut self,
+        entry: walkdir::DirEntry,
+        pattern: &str,
+        item_size: u64,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        if self.passes_filters(&entry, item_size) {
+            self.collect_matched_entry(entry, pattern, item_size, on_event);
+        }
+    }
+
+    /// true if a matched entry passes the protection, age and size
+    /// filters, logging why it is skipped otherwise
+    fn passes_filters(&self, entry: &walkdir::DirEntry, item_size: u64) -> bool {
+        let entry_path = entry.path();
+        if self.is_protected(entry) {
+            self.log_entry("Protected (recently modified)", entry_path);
+            return false;
+        }
+        if !self.is_within_age(entry) {
+            self.log_entry("Skipped (outside age window)", entry_path);
+            return false;
+        }
+        if let Some(min_size) = self.min_size {
+            if item_size < min_size {
+                self.log_entry("Skipped (below min size)", entry_path);
+                return false;
+            }
+        }
+        if let Some(max_size) = self.max_size {
+            if item_size > max_size {
+                self.log_entry("Skipped (above max size)", entry_path);
+                return false;
+            }
+        }
+        if let Some(min_dir_size) = self.min_dir_size {
+            if entry.file_type().is_dir() && item_size < min_dir_size {
+                self.log_entry("Skipped (below min dir size)", entry_path);
+                return false;
+            }
+        }
+        true
+    }
+
+    /// count a matched entry which passed the filters, then remove or
+    /// collect it
+    fn collect_matched_entry(
+        &mut self,
+        entry: walkdir::DirEntry,
+        pattern: &str,
+        item_size: u64,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        let entry_path = entry.path();
+        if self.exceeds_limits(item_size) {
+            self.truncated = true;
+            warn!(
+                "LIMIT REACHED: stopped collecting at {} item(s) totalling {}; \
+                 further matches were not collected",
+                self.counter,
+                self.display_size(self.size)
+            );
+            return;
+        }
+        self.size += item_size;
+        self.counter += 1;
+        let stat = self.stats.entry(pattern.to_string()).or_default();
+        stat.0 += 1;
+        stat.1 += item_size;
+        let item = MatchedItem {
+            path: entry_path.to_path_buf(),
+            pattern: pattern.to_string(),
+            size: item_size,
+            is_dir: entry.file_type().is_dir(),
+        };
+        self.stream(&Record::Match(&item));
+        on_event(&CleanEvent::Matched {
+            path: entry_path,
+            pattern,
+            size: item_size,
+        });
+        if let Ok(metadata) = entry.metadata() {
+            self.matched_metadata.insert(item.path.clone(), metadata);
+        }
+        self.matched_sizes.insert(item.path.clone(), item.size);
+        if self.truncate_patterns.iter().any(|p| p == pattern) {
+            self.truncating.insert(item.path.clone());
+        }
+        self.matched.push(item);
+        if self.removes_while_collecting() {
+            self.remove_entry_with(&entry, on_event);
+        } else {
+            self.log_entry("Matched", entry_path);
+            self.targets.push(entry);
+        }
+    }
+
+    /// true if the entry, or anything inside a directory entry, was
+    /// modified within `protect_newer_than_secs`
+    fn is_protected(&self, entry: &walkdir::DirEntry) -> bool {
+        let Some(secs) = self.protect_newer_than_secs else {
+            return false;
+        };
+        let cutoff = SystemTime::now()
+            .checked_sub(Duration::from_secs(secs))
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        WalkDir::new(entry.path())
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.metadata().ok()?.modified().ok())
+            .any(|modified| modified >= cutoff)
+    }
+
+    /// check an entry's `age_field` timestamp against the age filters
+    ///
+    /// An entry exactly `older_than_secs` or `newer_than_secs` old passes.
+    fn is_within_age(&self, entry: &walkdir::DirEntry) -> bool {
+        if self.older_than_secs.is_none() && self.newer_than_secs.is_none() {
+            return true;
+        }
+        let timestamp = entry
+            .metadata()
+            .ok()
+            .and_then(|m| self.age_field.timestamp(&m));
+        let Some(timestamp) = timestamp else {
+            warn!("no {} time: {:?}", self.age_field, entry.path().display());
+            return false;
+        };
+        let age = SystemTime::now()
+            .duration_since(timestamp)
+            .unwrap_or(Duration::ZERO)
+            .as_secs();
+        self.older_than_secs.is_none_or(|secs| age >= secs)
+            && self.newer_than_secs.is_none_or(|secs| age <= secs)
+    }
+
+    /// paths which could not be removed, with the reason for each failure
+    pub fn failed_deletions(&self) -> &[(PathBuf, String)] {
+        &self.failed_deletions
+    }
+
+    /// paths left in place by `skip_in_use` because they were open
+    pub fn skipped_in_use(&self) -> &[PathBuf] {
+        &self.skipped_in_use
+    }
+
+    /// paths left in place because `pre_delete_hook` failed for them
+    pub fn skipped_by_hook(&self) -> &[PathBuf] {
+        &self.skipped_by_hook
+    }
+
+    /// paths left untouched because `error_policy` stopped the removal
+    pub fn not_attempted(&self) -> &[PathBuf] {
+        &self.not_attempted
+    }
+
+    /// true if removal stopped at the failure limit of `error_policy`
+    fn aborted(&self) -> bool {
+        self.error_policy.aborts_at(self.failed_deletions.len())
+    }
+
+    /// leave a target untouched once `error_policy` stopped the removal,
+    /// taking it out of the totals
+    fn skip_if_aborted(&mut self, path: &Path) -> bool {
+        if !self.aborted() {
+            return false;
+        }
+        self.unmatch(path);
+        self.not_attempted.push(path.to_path_buf());
+        true
+    }
+
+    /// remove directories left empty by the cleaning, deepest first
+    ///
+    /// Only ancestors of removed items below the base path are considered,
+    /// so directories which were already empty are left alone, as are
+    /// excluded directories and the base path itself. In a dry-run items
+    /// which would have been removed are treated as gone.
+    fn prune_empty_dirs(&mut self, matchers: &Matchers, on_event: &mut dyn FnMut(&CleanEvent)) {
+        let roots = self.roots();
+        let mut candidates = BTreeSet::new();
+        for path in self.removed.iter() {
+            let Some((_, base)) = Self::root_of(&roots, path) else {
+                continue;
+            };
+            for dir in path.ancestors().skip(1) {
+                if dir == base || !dir.starts_with(base) {
+                    break;
+                }
+                candidates.insert(dir.to_path_buf());
+            }
+        }
+        let mut gone: HashSet<PathBuf> = self.removed.iter().cloned().collect();
+        let mut pruned = 0;
+        // a child directory sorts after its parent, so reverse is deepest first
+        for dir in candidates.into_iter().rev() {
+            if gone.contains(&dir) {
+                continue;
+            }
+            let is_empty = match fs::read_dir(&dir) {
+                Ok(mut entries) => entries.all(|e| e.is_ok_and(|e| gone.contains(&e.path()))),
+                Err(_) => false,
+            };
+            if !is_empty {
+                continue;
+            }
+            if matchers.is_excluded(&dir) {
+                self.log_entry("Excluded", &dir);
+                continue;
+            }
+            if self.dry_run {
+                self.log_entry("Would prune", &dir);
+            } else if let Err(e) = fs::remove_dir(&dir) {
+                self.record_failure(&dir, e.to_string(), on_event);
+                continue;
+            } else {
+                self.log_entry("Pruned", &dir);
+                self.audit(Disposition::Deleted, &dir);
+                self.notify_deleted(&dir, 0, on_event);
+            }
+            gone.insert(dir);
+            pruned += 1;
+        }
+        if pruned > 0 {
+            self.counter += pruned as i32;
+            self.stats
+                .entry(EMPTY_DIR_PATTERN.to_string())
+                .or_default()
+                .0 += pruned;
+        }
+    }
+
+    /// remove collected targets
+    pub fn remove_targets(&mut self) {
+        self.remove_targets_with(&mut |_| {});
+    }
+
+    fn remove_targets_with(&mut self, on_event: &mut dyn FnMut(&CleanEvent)) {
+        let targets = std::mem::take(&mut self.targets);
+        on_event(&CleanEvent::RemovalStarted {
+            total: targets.len(),
+        });
+        if self.exec_batch {
+            self.exec_targets_batched(&targets, on_event);
+            self.targets = targets;
+            return;
+        }
+        if self.stage && !self.dry_run {
+            self.remove_targets_staged(&targets, on_event);
+            self.targets = targets;
+            return;
+        }
+        if self.parallel_delete && !self.dry_run {
+            let jobs = self.jobs.unwrap_or(0);
+            match rayon::ThreadPoolBuilder::new().num_threads(jobs).build() {
+                Ok(pool) => {
+                    self.remove_targets_parallel(&targets, &pool, on_event);
+                    self.targets = targets;
+                    return;
+                }
+                Err(e) => warn!("cannot start thread pool, removing one at a time: {e}"),
+            }
+        }
+        for entry in targets.iter() {
+            if self.skip_if_aborted(entry.path()) {
+                continue;
+            }
+            // entries inside an already removed directory are gone as well
+            if self.skip_confirmation && fs::symlink_metadata(entry.path()).is_err() {
+                continue;
+            }
+            self.remove_entry_with(entry, on_event);
+        }
+        self.targets = targets;
+    }
+
+    /// remove the targets across a thread pool, a chunk at a time
+    ///
+    /// Only the removal itself runs on the pool; checks, backups, logging
+    /// and events stay on this thread and keep the order of the targets.
+    /// Nested targets were dropped beforehand, so no two threads ever
+    /// remove the same tree.
+    fn remove_targets_parallel(
+        &mut self,
+        targets: &[walkdir::DirEntry],
+        pool: &rayon::ThreadPool,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        for chunk in targets.chunks(PARALLEL_DELETE_CHUNK) {
+            let mut pending = vec![];
+            for entry in chunk.iter() {
+                if self.skip_confirmation && fs::symlink_metadata(entry.path()).is_err() {
+                    continue;
+                }
+                if let Some(removal) = self.prepare_removal(entry, on_event) {
+                    pending.push((entry.path(), removal));
+                }
+            }
+            let job = &*self;
+            let results: Vec<std::result::Result<Disposition, String>> = pool.install(|| {
+                pending
+                    .par_iter()
+                    .map(|(p, removal)| job.remove_path(p, &removal.target))
+                    .collect()
+            });
+            for ((p, removal), result) in pending.into_iter().zip(results) {
+                self.finish_removal(p, removal, result, on_event);
+            }
+        }
+    }
+
+    /// move all targets into a staging directory below their base path,
+    /// then delete the staging directories
+    ///
+    /// If a target cannot be staged, those already staged are moved back
+    /// and nothing is removed, so a failure never leaves the targets half
+    /// deleted.
+    fn remove_targets_staged(
+        &mut self,
+        targets: &[walkdir::DirEntry],
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        let roots = self.roots();
+        let staging_name = format!("{STAGING_DIR_PREFIX}{}", std::process::id());
+        let mut staging_dirs: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut staged: Vec<(&Path, restore::ManifestEntry)> = vec![];
+        // truncated in place once everything else is staged
+        let mut truncating: Vec<(&Path, fs::Metadata)> = vec![];
+        for entry in targets.iter() {
+            let p = entry.path();
+            // entries inside an already staged directory moved along with it
+            let Ok(metadata) = fs::symlink_metadata(p) else {
+                continue;
+            };
+            if metadata.is_symlink() && !self.include_symlinks {
+                warn!("skipping symlink: {:?}", p.display());
+                continue;
+            }
+            if self.skip_if_in_use(p) || self.skip_if_vetoed(p) {
+                continue;
+            }
+            if self.truncating.contains(p) {
+                truncating.push((p, metadata));
+                continue;
+            }
+            let (base, relative) = match Self::root_of(&roots, p) {
+                Some((_, root)) => (root, p.strip_prefix(root).unwrap_or(p)),
+                None => (
+                    p.parent().unwrap_or(Path::new(".")),
+                    Path::new(p.file_name().unwrap_or_default()),
+                ),
+            };
+            let staging_dir = base.join(&staging_name);
+            let dest = restore::unique_path(&staging_dir.join(relative));
+            staging_dirs.insert(staging_dir);
+            let size = self.matched_size(p);
+            match restore::move_entry(p, &dest, size) {
+                Ok(moved) => staged.push((p, moved)),
+                Err(e) => {
+                    self.record_failure(p, format!("staging failed: {e}"), on_event);
+                    self.unstage(staged, staging_dirs, on_event);
+                    return;
+                }
+            }
+        }
+        for (p, moved) in staged {
+            self.log_entry("Deleted", p);
+            self.audit(Disposition::Deleted, p);
+            self.removed.push(p.to_path_buf());
+            self.dispositions
+                .insert(p.to_path_buf(), Disposition::Deleted);
+            if self.history_dir.is_some() {
+                self.history.entries.push(HistoryEntry {
+                    original: moved.original,
+                    disposition: Disposition::Deleted,
+                    size: moved.size,
+                    location: None,
+                });
+            }
+            self.run_post_delete_hook(p);
+            self.notify_deleted(p, self.matched_size(p), on_event);
+        }
+        for (p, metadata) in truncating {
+            self.truncate_entry(p, &metadata, on_event);
+        }
+        for staging_dir in staging_dirs {
+            let removed = fs::symlink_metadata(&staging_dir)
+                .map_err(|e| e.to_string())
+                .and_then(|metadata| self.remove_path(&staging_dir, &metadata));
+            if let Err(e) = removed {
+                error!(
+                    "could not remove staging directory {:?}: {e}",
+                    staging_dir.display()
+                );
+            }
+        }
+    }
+
+    /// move staged entries back after staging failed part way
+    fn unstage(
+        &mut self,
+        staged: Vec<(&Path, restore::ManifestEntry)>,
+        staging_dirs: BTreeSet<PathBuf>,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        for (p, moved) in staged.into_iter().rev() {
+            if let Err(e) = restore::move_entry(&moved.backup, p, moved.size) {
+                let error = format!(
+                    "could not be moved back from {:?}: {e}",
+                    moved.backup.display()
+                );
+                self.record_failure(p, error, on_event);
+            }
+        }
+        for staging_dir in staging_dirs {
+            // only empty directories are left if everything was moved back
+            if let Err(e) = remove_empty_dirs(&staging_dir) {
+                warn!(
+                    "could not remove staging directory {:?}: {e}",
+                    staging_dir.display()
+                );
+            }
+        }
+        warn!("Staging failed, so the staged items were moved back and nothing was removed.");
+    }
+
+    /// remove the targets once the run is confirmed, still asking about
+    /// each directory with `confirm_dirs`
+    fn remove_confirmed_targets(
+        &mut self,
+        prompter: &mut dyn Prompter,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        if self.confirm_dirs && !self.dry_run {
+            self.remove_targets_interactively(prompter, on_event);
+        } else {
+            self.remove_targets_with(on_event);
+        }
+    }
+
+    /// ask `prompt` before removing each collected target, or only each
+    /// directory target with `confirm_dirs`
+    ///
+    /// Answering 'all' removes the remaining targets without asking again,
+    /// while 'quit' stops without removing any further targets that would
+    /// be asked about. Targets which are kept no longer count towards the
+    /// totals.
+    fn remove_targets_interactively(
+        &mut self,
+        prompter: &mut dyn Prompter,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        let targets = std::mem::take(&mut self.targets);
+        let mut remove_all = false;
+        let mut cancelled = false;
+        'targets: for entry in targets.iter() {
+            if self.skip_if_aborted(entry.path()) {
+                continue;
+            }
+            let asks = !self.confirm_dirs || entry.file_type().is_dir();
+            if cancelled && asks {
+                self.unmatch(entry.path());
+                continue;
+            }
+            while asks && !remove_all {
+                match prompter.ask(&self.target_prompt(entry)) {
+                    Answer::Yes => break,
+                    Answer::All => remove_all = true,
+                    Answer::No => {
+                        self.log_entry("Skipped", entry.path());
+                        self.unmatch(entry.path());
+                        continue 'targets;
+                    }
+                    Answer::Quit => {
+                        if self.confirm_dirs {
+                            warn!("Keeping the remaining directories.");
+                        } else {
+                            warn!("Cleaning operation cancelled.");
+                        }
+                        cancelled = true;
+                        self.unmatch(entry.path());
+                        continue 'targets;
+                    }
+                    Answer::Details => self.list_contents(entry.path()),
+                }
+            }
+            self.remove_entry_with(entry, on_event);
+        }
+        self.targets = targets;
+    }
+
+    /// let `prompter` choose the targets to remove from a checklist
+    ///
+    /// Beyond `SELECT_LIMIT` targets the checklist holds the top-level
+    /// directories of the targets instead, each one standing for all the
+    /// targets inside it.
+    fn remove_selected_targets(
+        &mut self,
+        prompter: &mut dyn Prompter,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) -> Result<()> {
+        let targets = std::mem::take(&mut self.targets);
+        let roots = self.roots();
+        let mut groups: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+        for (index, entry) in targets.iter().enumerate() {
+            let top = match Self::root_of(&roots, entry.path()) {
+                Some((_, root)) => entry
+                    .path()
+                    .strip_prefix(root)
+                    .ok()
+                    .and_then(|relative| relative.components().next())
+                    .map_or(entry.path().to_path_buf(), |top| root.join(top)),
+                None => entry.path().to_path_buf(),
+            };
+            groups.entry(top).or_default().push(index);
+        }
+        let selected: HashSet<usize> = if targets.len() > SELECT_LIMIT {
+            info!(
+                target: SUMMARY_LOG_TARGET,
+                "{} items in {} top-level directories, selecting by directory",
+                targets.len(),
+                groups.len()
+            );
+            let prompts: Vec<TargetPrompt> = groups
+                .iter()
+                .map(|(top, indices)| TargetPrompt {
+                    path: top,
+                    size: indices
+                        .iter()
+                        .map(|&index| self.target_prompt(&targets[index]).size)
+                        .sum(),
+                    age: None,
+                    is_dir: true,
+                    units: self.size_units,
+                })
+                .collect();
+            let chosen = prompter.select(&prompts)?;
+            let indices: Vec<&Vec<usize>> = groups.values().collect();
+            chosen
+                .into_iter()
+                .filter_map(|group| indices.get(group))
+                .flat_map(|group| group.iter().copied())
+                .collect()
+        } else {
+            let prompts: Vec<TargetPrompt> =
+                targets.iter().map(|e| self.target_prompt(e)).collect();
+            prompter.select(&prompts)?.into_iter().collect()
+        };
+        if selected.is_empty() {
+            warn!("Cleaning operation cancelled.");
+        }
+        let mut chosen = vec![];
+        for (index, entry) in targets.into_iter().enumerate() {
+            if selected.contains(&index) {
+                chosen.push(entry);
+            } else {
+                self.log_entry("Skipped", entry.path());
+                self.unmatch(entry.path());
+            }
+        }
+        self.targets = chosen;
+        self.remove_targets_with(on_event);
+        Ok(())
+    }
+
+    /// a size formatted in `size_units`
+    fn display_size(&self, bytes: u64) -> String {
+        format_size_in(bytes, self.size_units)
+    }
+
+    /// what the prompt shows about a target
+    fn target_prompt<'a>(&self, entry: &'a walkdir::DirEntry) -> TargetPrompt<'a> {
+        let age = self
+            .matched_metadata
+            .get(entry.path())
+            .and_then(|metadata| self.age_field.timestamp(metadata))
+            .and_then(|time| SystemTime::now().duration_since(time).ok());
+        TargetPrompt {
+            path: entry.path(),
+            size: self.matched_size(entry.path()),
+            age,
+            is_dir: entry.file_type().is_dir(),
+            units: self.size_units,
+        }
+    }
+
+    /// log the entries of a directory target with their sizes
+    fn list_contents(&self, path: &Path) {
+        const LIMIT: usize = 20;
+        let Ok(entries) = fs::read_dir(path) else {
+            warn!("cannot list {:?}: not a directory", path.display());
+            return;
+        };
+        let mut children: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        children.sort();
+        for child in children.iter().take(LIMIT) {
+            info!(
+                target: SUMMARY_LOG_TARGET,
+                "  {:?} ({})",
+                child.display(),
+                self.display_size(entry_size(child).unwrap_or_default())
+            );
+        }
+        if children.len() > LIMIT {
+            info!(target: SUMMARY_LOG_TARGET, "  ... and {} more", children.len() - LIMIT);
+        }
+    }
+
+    /// drop the targets inside another target, which go along with it,
+    /// so that they are neither counted twice nor fail to be removed
+    ///
+    /// Targets are sorted by path, so a directory comes right before
+    /// everything inside it.
+    fn drop_nested_targets(&mut self) {
+        let mut nested = HashSet::new();
+        let mut parent: Option<&Path> = None;
+        for entry in self.targets.iter() {
+            match parent {
+                Some(parent) if entry.path().starts_with(parent) => {
+                    nested.insert(entry.path().to_path_buf());
+                }
+                _ => parent = Some(entry.path()),
+            }
+        }
+        if nested.is_empty() {
+            return;
+        }
+        for path in nested.iter() {
+            self.unmatch(path);
+            self.matched_metadata.remove(path);
+            self.matched_sizes.remove(path);
+        }
+        self.targets.retain(|entry| !nested.contains(entry.path()));
+        self.matched.retain(|item| !nested.contains(&item.path));
+        self.stats.retain(|_, stat| stat.0 > 0);
+    }
+
+    /// size of a matched entry as it was collected
+    fn matched_size(&self, path: &Path) -> u64 {
+        self.matched_sizes.get(path).copied().unwrap_or_default()
+    }
+
+    /// take a matched entry which is kept after all out of the totals
+    fn unmatch(&mut self, path: &Path) {
+        let Some(item) = self.matched.iter().find(|item| item.path == path) else {
+            return;
+        };
+        self.counter -= 1;
+        self.size = self.size.saturating_sub(item.size);
+        if let Some(stat) = self.stats.get_mut(&item.pattern) {
+            stat.0 = stat.0.saturating_sub(1);
+            stat.1 = stat.1.saturating_sub(item.size);
+        }
+    }
+
+    /// remove file or directory with some safety measures
+    pub fn remove_entry(&mut self, entry: &walkdir::DirEntry) {
+        self.remove_entry_with(entry, &mut |_| {});
+    }
+
+    fn remove_entry_with(
+        &mut self,
+        entry: &walkdir::DirEntry,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        let Some(removal) = self.prepare_removal(entry, on_event) else {
+            return;
+        };
+        let result = self.remove_path(entry.path(), &removal.target);
+        self.finish_removal(entry.path(), removal, result, on_event);
+    }
+
+    /// everything to do for a target before it is removed
+    ///
+    /// Returns None if the target was dealt with some other way, such as
+    /// skipped, archived, truncated, quarantined or reported in a
+    /// dry-run, or if it failed already.
+    fn prepare_removal(
+        &mut self,
+        entry: &walkdir::DirEntry,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) -> Option<Removal> {
+        let p = entry.path();
+        // decide on the entry itself, also for a symlink the walk followed,
+        // so that only `symlink_behavior` ever reaches what it points to
+        let target = match fs::symlink_metadata(p) {
+            Ok(target) => target,
+            Err(e) => {
+                self.record_failure(p, e.to_string(), on_event);
+                return None;
+            }
+        };
+        if target.is_symlink() && !self.include_symlinks {
+            warn!("skipping symlink: {:?}", p.display());
+            return None;
+        }
+        if target.is_symlink() && self.symlink_behavior == SymlinkBehavior::FollowAndDelete {
+            if let Err(e) = self.link_target(p) {
+                self.record_failure(p, e, on_event);
+                return None;
+            }
+        }
+        if !(target.is_symlink() || target.is_file() || target.is_dir()) {
+            warn!("skipping unknowm: {:?}", p.display());
+            return None;
+        }
+        if self.skip_if_in_use(p) || self.skip_if_vetoed(p) {
+            return None;
+        }
+        if self.exec.is_some() {
+            self.exec_on(&[p], on_event);
+            return None;
+        }
+        if self.archive_path.is_some() {
+            if self.dry_run {
+                self.log_entry("Would archive", p);
+            } else if let Err(e) = self.archive_entry(p) {
+                self.record_failure(p, format!("archive failed: {e}"), on_event);
+                return None;
+            }
+        }
+        if self.truncating.contains(p) {
+            self.truncate_entry(p, &target, on_event);
+            return None;
+        }
+        if let Some(run_dir) = &self.quarantine_run {
+            let dest = restore::unique_path(&run_dir.join(self.backup_relative_path(p)));
+            self.quarantine_entry(p, &dest, on_event);
+            return None;
+        }
+        if self.dry_run {
+            let (action, disposition) = if self.trash_mode {
+                ("Would trash", Disposition::WouldTrash)
+            } else {
+                ("Would delete", Disposition::WouldDelete)
+            };
+            self.log_entry(action, p);
+            self.removed.push(p.to_path_buf());
+            self.dispositions.insert(p.to_path_buf(), disposition);
+            return None;
+        }
+        // the parent may be removed along with the entry later on
+        let original = self.history_dir.as_ref().map(|_| {
+            (
+                restore::absolute_path(p).unwrap_or(p.to_path_buf()),
+                self.matched_size(p),
+            )
+        });
+        let backup = match &self.backup_dir {
+            Some(backup_dir) => {
+                let relative = self.backup_relative_path(p);
+                match restore::backup_entry(p, &relative, self.matched_size(p), backup_dir) {
+                    Ok(backup) => Some(backup),
+                    Err(e) => {
+                        self.record_failure(p, format!("backup failed: {e}"), on_event);
+                        return None;
+                    }
+                }
+            }
+            None => None,
+        };
+        Some(Removal {
+            target,
+            original,
+            backup,
+        })
+    }
+
+    /// record the outcome of removing a target
+    fn finish_removal(
+        &mut self,
+        p: &Path,
+        removal: Removal,
+        result: std::result::Result<Disposition, String>,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        match result {
+            Ok(disposition) => {
+                self.log_entry(
+                    if disposition == Disposition::Trashed {
+                        "Trashed"
+                    } else {
+                        "Deleted"
+                    },
+                    p,
+                );
+                self.audit(disposition, p);
+                self.removed.push(p.to_path_buf());
+                self.dispositions.insert(p.to_path_buf(), disposition);
+                self.manifest.entries.extend(removal.backup);
+                if let Some((original, size)) = removal.original {
+                    self.history.entries.push(HistoryEntry {
+                        original,
+                        disposition,
+                        size,
+                        location: None,
+                    });
+                }
+                self.run_post_delete_hook(p);
+                self.notify_deleted(p, self.matched_size(p), on_event);
+            }
+            Err(e) => self.record_failure(p, e, on_event),
+        }
+    }
+
+    /// run the `exec` command on the targets in chunks of paths which
+    /// fit on a command line, instead of removing them
+    fn exec_targets_batched(
+        &mut self,
+        targets: &[walkdir::DirEntry],
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        let mut paths: Vec<&Path> = vec![];
+        for entry in targets.iter() {
+            let p = entry.path();
+            let Ok(metadata) = fs::symlink_metadata(p) else {
+                continue;
+            };
+            if metadata.is_symlink() && !self.include_symlinks {
+                warn!("skipping symlink: {:?}", p.display());
+                continue;
+            }
+            if self.skip_if_in_use(p) || self.skip_if_vetoed(p) {
+                continue;
+            }
+            paths.push(p);
+        }
+        let (mut start, mut bytes) = (0, 0);
+        for (index, p) in paths.iter().enumerate() {
+            let len = p.as_os_str().len() + 1;
+            if index > start && bytes + len > EXEC_BATCH_MAX_BYTES {
+                self.exec_on(&paths[start..index], on_event);
+                (start, bytes) = (index, 0);
+            }
+            bytes += len;
+        }
+        if start < paths.len() {
+            self.exec_on(&paths[start..], on_event);
+        }
+    }
+
+    /// run the `exec` command once on some targets, or only log the
+    /// command line in a dry-run; a failure counts for all of them
+    fn exec_on(&mut self, paths: &[&Path], on_event: &mut dyn FnMut(&CleanEvent)) {
+        // an earlier batch may have reached the `error_policy` limit
+        if self.aborted() {
+            for p in paths.iter() {
+                self.skip_if_aborted(p);
+            }
+            return;
+        }
+        let words = match split_command(self.exec.as_deref().unwrap_or_default()) {
+            Ok(words) => words,
+            Err(e) => {
+                for p in paths.iter() {
+                    self.record_failure(p, e.clone(), on_event);
+                }
+                return;
+            }
+        };
+        let line = exec_command_line(&words, paths);
+        let disposition = if self.dry_run {
+            if !self.quiet {
+                info!("Would run: {}", format_command_line(&line));
+            }
+            Disposition::WouldExecute
+        } else {
+            let mut command = std::process::Command::new(&line[0]);
+            command.args(&line[1..]);
+            if let Err(e) = self.run_command(command, "command") {
+                for p in paths.iter() {
+                    self.record_failure(p, e.clone(), on_event);
+                }
+                return;
+            }
+            if !self.quiet {
+                info!("Ran: {}", format_command_line(&line));
+            }
+            Disposition::Executed
+        };
+        for p in paths.iter() {
+            self.removed.push(p.to_path_buf());
+            self.dispositions.insert(p.to_path_buf(), disposition);
+            if !self.dry_run {
+                self.notify_deleted(p, self.matched_size(p), on_event);
+            }
+        }
+    }
+
+    /// empty a file matching one of the `truncate_patterns` in place,
+    /// which frees its space even while a process keeps writing to it
+    fn truncate_entry(
+        &mut self,
+        p: &Path,
+        target: &fs::Metadata,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        if !target.is_file() {
+            let reason = if target.is_dir() {
+                "is a directory"
+            } else {
+                "not a regular file"
+            };
+            self.record_failure(p, format!("truncate failed: {reason}"), on_event);
+            return;
+        }
+        if self.dry_run {
+            self.log_entry("Would truncate", p);
+            self.removed.push(p.to_path_buf());
+            self.dispositions
+                .insert(p.to_path_buf(), Disposition::WouldTruncate);
+            return;
+        }
+        match fs::OpenOptions::new().write(true).truncate(true).open(p) {
+            Ok(_) => {
+                self.log_entry("Truncated", p);
+                self.audit(Disposition::Truncated, p);
+                self.removed.push(p.to_path_buf());
+                self.dispositions
+                    .insert(p.to_path_buf(), Disposition::Truncated);
+                self.run_post_delete_hook(p);
+                self.notify_deleted(p, self.matched_size(p), on_event);
+            }
+            Err(e) => self.record_failure(p, format!("truncate failed: {e}"), on_event),
+        }
+    }
+
+    /// move an entry into the quarantine directory, or only report the
+    /// planned destination in a dry-run
+    fn quarantine_entry(&mut self, p: &Path, dest: &Path, on_event: &mut dyn FnMut(&CleanEvent)) {
+        if self.dry_run {
+            if !self.quiet {
+                info!(
+                    "Would quarantine: {:?} -> {:?}",
+                    p.display(),
+                    dest.display()
+                );
+            }
+            self.removed.push(p.to_path_buf());
+            self.dispositions
+                .insert(p.to_path_buf(), Disposition::WouldQuarantine);
+            return;
+        }
+        match restore::move_entry(p, dest, self.matched_size(p)) {
+            Ok(entry) => {
+                if !self.quiet {
+                    info!("Quarantined: {:?} -> {:?}", p.display(), dest.display());
+                }
+                self.audit(Disposition::Quarantined, p);
+                self.removed.push(p.to_path_buf());
+                self.dispositions
+                    .insert(p.to_path_buf(), Disposition::Quarantined);
+                self.history.entries.push(HistoryEntry {
+                    original: entry.original.clone(),
+                    disposition: Disposition::Quarantined,
+                    size: entry.size,
+                    location: Some(entry.backup.clone()),
+                });
+                self.manifest.entries.push(entry);
+                self.run_post_delete_hook(p);
+                self.notify_deleted(p, self.matched_size(p), on_event);
+            }
+            Err(e) => self.record_failure(p, format!("quarantine failed: {e}"), on_event),
+        }
+    }
+
+    /// where below the backup directory an entry is stored
+    ///
+    /// With several base paths each one gets a numbered subdirectory,
+    /// so that equally named entries of different base paths do not clash.
+    fn backup_relative_path(&self, path: &Path) -> PathBuf {
+        let roots = self.roots();
+        match Self::root_of(&roots, path) {
+            Some((index, root)) => {
+                let relative = path.strip_prefix(root).unwrap_or(path);
+                if roots.len() > 1 {
+                    Path::new(&index.to_string()).join(relative)
+                } else {
+                    relative.to_path_buf()
+                }
+            }
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// log a removed entry with its size to `AUDIT_LOG_TARGET`, also in
+    /// `quiet` mode
+    fn audit(&self, disposition: Disposition, path: &Path) {
+        let size = self.matched_size(path);
+        info!(
+            target: AUDIT_LOG_TARGET,
+            "{disposition:?} {:?} ({})",
+            path.display(),
+            self.display_size(size)
+        );
+    }
+
+    /// log what happened to an entry, unless in `quiet` mode
+    fn log_entry(&self, action: &str, path: &Path) {
+        if !self.quiet {
+            info!("{}: {:?}", action, path.display());
+        }
+    }
+
+    /// leave a target alone if `skip_in_use` finds it open, taking it out
+    /// of the totals
+    fn skip_if_in_use(&mut self, path: &Path) -> bool {
+        if !self
+            .in_use
+            .as_ref()
+            .is_some_and(|check| check.is_in_use(path))
+        {
+            return false;
+        }
+        warn!("skipping file in use: {:?}", path.display());
+        self.unmatch(path);
+        self.skipped_in_use.push(path.to_path_buf());
+        self.dispositions
+            .insert(path.to_path_buf(), Disposition::InUse);
+        true
+    }
+
+    /// leave a target alone if `pre_delete_hook` fails for it, taking it
+    /// out of the totals
+    ///
+    /// With `hook_per_run` the hook runs once, before the first target,
+    /// and its outcome applies to all of them. Hooks never run in a dry-run.
+    fn skip_if_vetoed(&mut self, path: &Path) -> bool {
+        let Some(hook) = self.pre_delete_hook.clone().filter(|_| !self.dry_run) else {
+            return false;
+        };
+        let passed = match self.pre_hook_passed.filter(|_| self.hook_per_run) {
+            Some(passed) => passed,
+            None => {
+                let subject = if self.hook_per_run {
+                    Path::new(&self.path)
+                } else {
+                    path
+                };
+                let result = self.run_delete_hook(&hook, subject);
+                if let Err(e) = &result {
+                    warn!("{e} for {:?}", subject.display());
+                }
+                self.pre_hook_passed = Some(result.is_ok());
+                result.is_ok()
+            }
+        };
+        if passed {
+            return false;
+        }
+        self.log_entry("Skipped (pre-delete hook failed)", path);
+        self.unmatch(path);
+        self.skipped_by_hook.push(path.to_path_buf());
+        self.dispositions
+            .insert(path.to_path_buf(), Disposition::Skipped);
+        true
+    }
+
+    /// run `post_delete_hook` for a removed target, only logging a failure
+    fn run_post_delete_hook(&self, path: &Path) {
+        let Some(hook) = self
+            .post_delete_hook
+            .as_ref()
+            .filter(|_| !self.hook_per_run)
+        else {
+            return;
+        };
+        if let Err(e) = self.run_delete_hook(hook, path) {
+            error!("{e} for {:?}", path.display());
+        }
+    }
+
+    /// run `post_delete_hook` once for the base path with `hook_per_run`,
+    /// if anything was removed
+    fn run_post_delete_hook_per_run(&self) {
+        let Some(hook) = self.post_delete_hook.as_ref().filter(|_| self.hook_per_run) else {
+            return;
+        };
+        if self.dry_run || self.removed.is_empty() {
+            return;
+        }
+        if let Err(e) = self.run_delete_hook(hook, Path::new(&self.path)) {
+            error!("{e}");
+        }
+    }
+
+    /// run a delete hook for a path, passed as `{}` in the command and in
+    /// `RCLEAN_PATH`
+    ///
+    /// The command is run directly, split into words, unless `hook_shell`
+    /// is set; even then the path only reaches the shell through the
+    /// environment, so it is never parsed as shell code.
+    fn run_delete_hook(&self, hook: &str, path: &Path) -> std::result::Result<(), String> {
+        let mut command = if self.hook_shell {
+            shell_command(&hook.replace("{}", SHELL_PATH_VAR))
+        } else {
+            let words = split_command(hook)?;
+            let mut command = std::process::Command::new(substitute_path(&words[0], path));
+            command.args(words[1..].iter().map(|word| substitute_path(word, path)));
+            command
+        };
+        command.env("RCLEAN_PATH", path);
+        self.run_command(command, &format!("hook {hook:?}"))
+    }
+
+    /// run a command to completion, an error if it fails to start or
+    /// exits unsuccessfully
+    fn run_command(
+        &self,
+        mut command: std::process::Command,
+        what: &str,
+    ) -> std::result::Result<(), String> {
+        // keep stdout parseable for the other output formats
+        if self.output_format != OutputFormat::Text && self.output_file.is_none() {
+            command.stdout(std::io::stderr());
+        }
+        match command.status() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("{what} exited with {status}")),
+            Err(e) => Err(format!("{what} could not be run: {e}")),
+        }
+    }
+
+    /// keep track of a path which could not be removed
+    fn record_failure(
+        &mut self,
+        path: &Path,
+        error: String,
+        on_event: &mut dyn FnMut(&CleanEvent),
+    ) {
+        self.stream(&Record::Failed {
+            path,
+            error: &error,
+        });
+        on_event(&CleanEvent::Failed {
+            path,
+            error: &error,
+        });
+        self.failed_deletions.push((path.to_path_buf(), error));
+        self.dispositions
+            .insert(path.to_path_buf(), Disposition::Failed);
+    }
+
+    /// what a symlink removed with `FollowAndDelete` points to, which
+    /// must lie strictly inside one of the base paths
+    fn link_target(&self, path: &Path) -> std::result::Result<PathBuf, String> {
+        let resolved = fs::canonicalize(path).map_err(|e| format!("cannot follow symlink: {e}"))?;
+        let inside = self
+            .roots()
+            .iter()
+            .filter_map(|root| fs::canonicalize(root).ok())
+            .any(|root| resolved != root && resolved.starts_with(&root));
+        if !inside {
+            return Err(format!(
+                "symlink leads outside the cleaned path, to {:?}",
+                resolved.display()
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// remove a single path, or move it to the system trash in `trash_mode`
+    ///
+    /// With `trash_fallback_delete` a path which cannot be trashed, e.g.
+    /// one on another file system than the trash, is deleted instead.
+    /// With `secure_delete` regular files are overwritten first, and left
+    /// in place if that fails; symlinks are removed without touching
+    /// their target. With `force` an item which cannot be removed for
+    /// lack of permissions is made writable and removed once more. On
+    /// Windows the path is passed in its extended-length form, so that
+    /// deep trees are not limited to 260 characters.
+    fn remove_path(
+        &self,
+        path: &Path,
+        target: &fs::Metadata,
+    ) -> std::result::Result<Disposition, String> {
+        if self.trash_mode {
+            match trash::delete(path) {
+                Ok(()) => return Ok(Disposition::Trashed),
+                Err(e) if self.trash_fallback_delete => {
+                    warn!("could not trash {:?}, deleting: {}", path.display(), e);
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        let path = long_path(path);
+        let path = path.as_ref();
+        if target.is_symlink() && self.symlink_behavior == SymlinkBehavior::FollowAndDelete {
+            let resolved = self.link_target(path)?;
+            let metadata = fs::metadata(&resolved).map_err(|e| e.to_string())?;
+            self.remove_path(&resolved, &metadata)?;
+            delete_entry(path, target).map_err(|e| format!("target removed, not the link: {e}"))?;
+            return Ok(Disposition::Deleted);
+        }
+        if self.secure_delete && !target.is_symlink() {
+            shred(path, target)
+                .map_err(|e| format!("shred failed, data may still be present: {e}"))?;
+        }
+        match delete_entry(path, target) {
+            Err(e) if self.force && e.kind() == std::io::ErrorKind::PermissionDenied => {
+                clear_readonly(path, target)
+                    .and_then(|_| delete_entry(path, target))
+                    .map_err(|e| format!("{e} (after clearing read-only permissions)"))?;
+            }
+            result => result.map_err(|e| e.to_string())?,
+        }
+        Ok(Disposition::Deleted)
+    }
+}
+
+/// compile a list of regex patterns
+fn build_regexes(patterns: &[String], case_insensitive: bool) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Ok(RegexBuilder::new(pattern)
+                .case_insensitive(case_insensitive)
+                .build()?)
+        })
+        .collect()
+}
+
+/// a command running `command` through the platform's shell
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> std::process::Command {
+    let mut shell = std::process::Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> std::process::Command {
+    let mut shell = std::process::Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// what `{}` stands for in a delete hook run through the shell
+#[cfg(not(windows))]
+const SHELL_PATH_VAR: &str = "\"$RCLEAN_PATH\"";
+
+#[cfg(windows)]
+const SHELL_PATH_VAR: &str = "\"%RCLEAN_PATH%\"";
+
+/// the command line of `exec` for some paths
+///
+/// A `{}` word stands for all the paths; for a single path `{}` is also
+/// replaced within a word. Without any `{}` the paths are appended.
+fn exec_command_line(words: &[String], paths: &[&Path]) -> Vec<std::ffi::OsString> {
+    let mut line = vec![];
+    let mut substituted = false;
+    for word in words.iter() {
+        if word == "{}" {
+            line.extend(paths.iter().map(|p| p.as_os_str().to_os_string()));
+            substituted = true;
+        } else if let ([path], true) = (paths, word.contains("{}")) {
+            line.push(substitute_path(word, path));
+            substituted = true;
+        } else {
+            line.push(word.into());
+        }
+    }
+    if !substituted {
+        line.extend(paths.iter().map(|p| p.as_os_str().to_os_string()));
+    }
+    line
+}
+
+/// a command line for the log, quoting words with spaces or quotes
+fn format_command_line(line: &[std::ffi::OsString]) -> String {
+    let words: Vec<String> = line
+        .iter()
+        .map(|word| {
+            let word = word.to_string_lossy();
+            if word.is_empty() || word.contains(|c: char| c.is_whitespace() || "'\"\\".contains(c))
+            {
+                format!("'{}'", word.replace('\'', r"'\''"))
+            } else {
+                word.into_owned()
+            }
+        })
+        .collect();
+    words.join(" ")
+}
+
+/// a word of a delete hook with `{}` replaced by the path
+fn substitute_path(word: &str, path: &Path) -> std::ffi::OsString {
+    if word == "{}" {
+        return path.as_os_str().to_os_string();
+    }
+    word.replace("{}", &path.to_string_lossy()).into()
+}
+
+/// a path relative to the first of `anchors` it lies below, None for an
+/// anchor itself or a path outside of all of them
+fn relative_path<'p>(anchors: &[PathBuf], path: &'p Path) -> Option<Cow<'p, Path>> {
+    let relative = match anchors.iter().find_map(|a| path.strip_prefix(a).ok()) {
+        Some(relative) => Cow::Borrowed(relative),
+        None => {
+            // resolve the parent only, so a symlink itself is not followed
+            let resolved = fs::canonicalize(path.parent()?)
+                .ok()?
+                .join(path.file_name()?);
+            let relative = anchors.iter().find_map(|a| resolved.strip_prefix(a).ok())?;
+            Cow::Owned(relative.to_path_buf())
+        }
+    };
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+/// remove a directory tree holding nothing but directories, failing on
+/// the first one which is not empty
+fn remove_empty_dirs(dir: &Path) -> std::io::Result<()> {
+    for entry in WalkDir::new(dir).contents_first(true) {
+        fs::remove_dir(entry?.path())?;
+    }
+    Ok(())
+}
+
+/// true if a file named `sibling` exists next to `path`
+fn has_sibling(path: &Path, sibling: &str) -> bool {
+    path.parent()
+        .is_some_and(|parent| parent.join(sibling).exists())
+}
+
+/// skip the "." and ".." entries, any path which starts with ".." and
+/// any path outside of the base path it was found under
+fn should_process(root: &Path, entry_path: &Path) -> bool {
+    // silently handle "." || ".." cases
+    if entry_path == Path::new(".") || entry_path == Path::new("..") {
+        return false;
+    }
+    // skip paths which startwith ".." or lie outside of their base path
+    if entry_path.starts_with("..") || !entry_path.starts_with(root) {
+        warn!("skipping {:?}", entry_path.display());
+        return false;
+    }
+    true
+}
+
+/// A target ready to be removed, with what its removal has to record
+struct Removal {
+    target: fs::Metadata,
+    /// absolute path and size for the history, if one is kept
+    original: Option<(PathBuf, u64)>,
+    backup: Option<restore::ManifestEntry>,
+}
+
+/// Sizes matched entries for the summary and stats
+///
+/// By default this is the logical length of files. With `accurate` set
+/// the blocks used on disk are counted instead on unix, which is smaller
+/// for sparse files, and hardlinked files are only counted once per run.
+struct EntrySizer {
+    accurate: bool,
+    seen: Mutex<HashSet<(u64, u64)>>,
+}
+
+impl EntrySizer {
+    fn new(accurate: bool) -> Self {
+        Self {
+            accurate,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn size(&self, entry_path: &Path) -> u64 {
+        if self.accurate {
+            self.disk_usage(entry_path)
+        } else {
+            Self::apparent_size(entry_path)
+        }
+    }
+
+    /// the apparent size, counting an entry which cannot be read as empty
+    fn apparent_size(entry_path: &Path) -> u64 {
+        entry_size(entry_path).unwrap_or_else(|e| {
+            warn!("cannot get the size of {:?}: {}", entry_path.display(), e);
+            0
+        })
+    }
+
+    #[cfg(unix)]
+    fn disk_usage(&self, entry_path: &Path) -> u64 {
+        use std::os::unix::fs::MetadataExt;
+        let mut total = 0;
+        for entry in WalkDir::new(entry_path).into_iter().filter_map(|e| e.ok()) {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.nlink() > 1 && !metadata.is_dir() {
+                let inode = (metadata.dev(), metadata.ino());
+                if !self.seen.lock().unwrap().insert(inode) {
+                    continue;
+                }
+            }
+            // st_blocks is always counted in 512 byte units
+            total += metadata.blocks() * 512;
+        }
+        total
+    }
+
+    #[cfg(not(unix))]
+    fn disk_usage(&self, entry_path: &Path) -> u64 {
+        Self::apparent_size(entry_path)
+    }
+}
+
+/// true if a followed symlink leads outside of its (canonical) root
+///
+/// Only symlinks reached with `follow_symlinks` need checking: entries
+/// below a symlink which passed stay inside the root as well.
+fn escapes_root(real_root: &Path, entry: &walkdir::DirEntry) -> bool {
+    if !entry.path_is_symlink() {
+        return false;
+    }
+    let escapes = !fs::canonicalize(entry.path()).is_ok_and(|target| target.starts_with(real_root));
+    if escapes {
+        warn!(
+            "skipping {:?}: symlink leads outside the cleaned path",
+            entry.path().display()
+        );
+    }
+    escapes
+}
+
+/// true if a path lives on another device than its root, logging the
+/// skipped crossing point
+///
+/// Device ids are only available on unix; elsewhere the walker's own
+/// `same_file_system` check is the only safeguard.
+fn crosses_file_system(root_device: Option<u64>, path: &Path) -> bool {
+    let crossed = root_device.is_some_and(|root| device_id(path).is_some_and(|dev| dev != root));
+    if crossed {
+        info!("Skipped (other file system): {:?}", path.display());
+    }
+    crossed
+}
+
+/// id of the device holding a path, without following a final symlink
+#[cfg(unix)]
+fn device_id(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::symlink_metadata(path).ok().map(|m| m.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// remove a file or symlink, or a directory with its contents
+fn delete_entry(path: &Path, target: &fs::Metadata) -> std::io::Result<()> {
+    if target.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// make an entry, everything below a directory entry and the directory
+/// containing it writable, without following symlinks
+fn clear_readonly(path: &Path, target: &fs::Metadata) -> std::io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        make_writable(parent)?;
+    }
+    if target.is_symlink() {
+        return Ok(());
+    }
+    if !target.is_dir() {
+        return make_writable(path);
+    }
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if !entry.path_is_symlink() {
+            make_writable(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// give the owner write access, and for directories access to their contents
+#[cfg(unix)]
+fn make_writable(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let metadata = fs::symlink_metadata(path)?;
+    let mut permissions = metadata.permissions();
+    let mode = permissions.mode();
+    let required = if metadata.is_dir() { 0o700 } else { 0o200 };
+    if mode & required != required {
+        permissions.set_mode(mode | required);
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+/// clear the read-only attribute
+#[cfg(not(unix))]
+#[allow(clippy::permissions_set_readonly_false)]
+fn make_writable(path: &Path) -> std::io::Result<()> {
+    let mut permissions = fs::symlink_metadata(path)?.permissions();
+    if permissions.readonly() {
+        permissions.set_readonly(false);
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+/// overwrite a regular file with zeros, or every regular file below a
+/// directory, without following symlinks
+fn shred(path: &Path, target: &fs::Metadata) -> std::io::Result<()> {
+    if target.is_file() {
+        return shred_file(path, target.len());
+    }
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            shred_file(entry.path(), entry.metadata()?.len())?;
+        }
+    }
+    Ok(())
+}
+
+/// overwrite the contents of a regular file with a single pass of zeros
+fn shred_file(path: &Path, len: u64) -> std::io::Result<()> {
+    let zeros = [0u8; 64 * 1024];
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..chunk])?;
+        remaining -= chunk as u64;
+    }
+    file.sync_all()
+}
+
+/// size of a file, or the recursive size of a directory
+fn entry_size(entry_path: &Path) -> std::io::Result<u64> {
+    get_size(entry_path).map_err(std::io::Error::other)
 }
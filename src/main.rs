@@ -1,30 +1,164 @@
 // --------------------------------------------------------------------
 // imports
 
-use clap::Parser;
-use log::{error, info};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{error, info, warn, LevelFilter};
 use std::fs;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use rclean::constants::{get_default_patterns, SETTINGS_FILENAME};
-use rclean::CleaningJob;
+use rclean::config::{
+    discover_config, discover_global_config, load_config, load_merged_config, ConfigFormat,
+    EnvConfig,
+};
+use rclean::constants::{
+    get_default_patterns, AUDIT_LOG_TARGET, EXIT_FATAL, EXIT_NO_MATCH, EXIT_PARTIAL, EXIT_SUCCESS,
+    PRESET_NAMES, SETTINGS_FILENAME, SUMMARY_LOG_TARGET,
+};
+use rclean::history::{self, default_history_dir};
+use rclean::plan::ScanReport;
+use rclean::restore;
+use rclean::utils::{format_size_in, parse_duration, parse_size};
+use rclean::{
+    AgeField, CleanError, CleanEvent, CleanReport, CleaningJob, ConditionalPattern, EntryType,
+    ErrorPolicy, HiddenPolicy, KeepPolicy, OutputFormat, RetentionPattern, SizeUnits,
+    SymlinkBehavior,
+};
 
 // --------------------------------------------------------------------
 // cli api
 
+/// the exit codes, listed at the end of `--help`
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success: items were matched (and removed unless --dry-run)
+  1  fatal error, e.g. an invalid pattern, option or settings file
+  2  some matched items could not be removed (0 with --no-fail-on-errors)
+  3  nothing matched, or nothing in an applied plan was removed
+
+Environment variables, used for the options not given on the command line:
+  RCLEAN_PATH, RCLEAN_PATTERNS, RCLEAN_EXCLUDE, RCLEAN_DRY_RUN,
+  RCLEAN_SKIP_CONFIRMATION, RCLEAN_TRASH, RCLEAN_OLDER_THAN, RCLEAN_NEWER_THAN,
+  RCLEAN_MIN_SIZE, RCLEAN_MAX_SIZE";
+
 /// Program to cleanup non-essential files or directories
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_help = EXIT_CODES_HELP)]
 struct Args {
-    /// Working Directory
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Working Directory (may be repeated to clean several directories)
     #[arg(short, long, default_value_os = ".")]
-    path: String,
+    path: Vec<String>,
 
     /// Specify custom glob pattern(s)
     #[arg(short, long)]
     glob: Option<Vec<String>>,
 
-    /// Configure from '.rclean.toml' file
+    /// Add the patterns of a built-in preset, e.g. 'python', 'java' or 'all' (see --list)
+    #[arg(long, value_name = "NAME")]
+    preset: Vec<String>,
+
+    /// Match patterns against paths relative to the working directory, so 'build' only matches at its top
+    #[arg(long)]
+    anchored: bool,
+
+    /// Only match a glob when a sibling file exists, e.g. '**/target=Cargo.toml'
+    #[arg(long, value_name = "PATTERN=FILE")]
+    require_sibling: Vec<ConditionalPattern>,
+
+    /// Keep the N newest matches of a glob in each directory, e.g. '**/*.log=3'
+    #[arg(long, value_name = "PATTERN=N")]
+    retain: Vec<RetentionPattern>,
+
+    /// Empty files matching this glob in place instead of removing them, e.g. live logs
+    #[arg(long, value_name = "GLOB")]
+    truncate: Vec<String>,
+
+    /// Keep the N newest matches of each pattern in each directory
+    #[arg(long, value_name = "N")]
+    keep_newest: Option<usize>,
+
+    /// Keep the N oldest matches of each pattern in each directory
+    #[arg(long, value_name = "N", conflicts_with = "keep_newest")]
+    keep_oldest: Option<usize>,
+
+    /// Specify custom regex pattern(s)
+    #[arg(long)]
+    regex: Vec<String>,
+
+    /// Read exclude glob patterns from a file, one per line ('#' starts a comment)
+    #[arg(long, value_name = "PATH")]
+    exclude_file: Vec<PathBuf>,
+
+    /// Exclude paths matching regex pattern(s)
+    #[arg(long)]
+    exclude_regex: Vec<String>,
+
+    /// Match glob and regex patterns case-insensitively
+    #[arg(long, visible_alias = "case-insensitive")]
+    ignore_case: bool,
+
+    /// Do not descend into directories matching these glob pattern(s)
+    #[arg(long, value_name = "PATTERN")]
+    prune: Vec<String>,
+
+    /// Also descend into directories pruned by default ('**/.git')
+    #[arg(long)]
+    no_default_prune: bool,
+
+    /// Do not honour the .rcleanignore files found while walking
+    #[arg(long)]
+    no_rcleanignore: bool,
+
+    /// Only match entries of this type: f (file), d (dir) or l (symlink)
+    #[arg(long = "type", value_name = "TYPE")]
+    match_type: Vec<EntryType>,
+
+    /// Only match entries ignored by git ('.gitignore', '.git/info/exclude')
+    #[arg(
+        long,
+        visible_alias = "only-ignored",
+        conflicts_with = "respect_gitignore"
+    )]
+    gitignore: bool,
+
+    /// Skip entries ignored by git instead of descending into them
+    #[arg(long)]
+    respect_gitignore: bool,
+
+    /// Never remove entries tracked by git (needs the 'git' build feature)
+    #[arg(long)]
+    untracked_only: bool,
+
+    /// Only match entries owned by this user name or uid (unix only)
+    #[arg(long, value_name = "USER")]
+    owned_by: Option<String>,
+
+    /// Also remove empty regular files
+    #[arg(long)]
+    empty_files: bool,
+
+    /// Remove directories left empty after cleaning
+    #[arg(long)]
+    prune_empty: bool,
+
+    /// Read candidate paths from stdin instead of walking the directory
+    #[arg(long)]
+    stdin: bool,
+
+    /// Paths read by --stdin are NUL separated, e.g. from 'find -print0'
+    #[arg(short = '0', long, requires = "stdin")]
+    null: bool,
+
+    /// Read NUL separated candidate paths from stdin, same as '--stdin -0'
+    #[arg(long, conflicts_with = "stdin")]
+    stdin0: bool,
+
+    /// Configure from '.rclean.toml' or '.rclean.yaml' file
     #[arg(short, long)]
     configfile: bool,
 
@@ -32,6 +166,18 @@ struct Args {
     #[arg(short, long)]
     write_configfile: bool,
 
+    /// Only collect the matches and write them to a plan file (as `rclean plan --out`)
+    #[arg(long, value_name = "FILE", conflicts_with = "apply_plan")]
+    save_plan: Option<PathBuf>,
+
+    /// Remove the matches of a plan file without scanning again (as `rclean apply`)
+    #[arg(long, value_name = "FILE")]
+    apply_plan: Option<PathBuf>,
+
+    /// Format of the file written by --write-configfile: toml or yaml
+    #[arg(long, default_value = "toml")]
+    format: ConfigFormat,
+
     /// Dry-run without actual removal
     #[arg(short, long)]
     dry_run: bool,
@@ -40,95 +186,1033 @@ struct Args {
     #[arg(short = 'y', long)]
     skip_confirmation: bool,
 
+    /// Confirm each item individually, showing its size and age: yes, no, all, quit or details
+    #[arg(short = 'I', long, conflicts_with = "skip_confirmation")]
+    interactive: bool,
+
+    /// Choose the items to delete from a checklist, all of them checked at first
+    #[arg(long, conflicts_with_all = ["skip_confirmation", "interactive"])]
+    select: bool,
+
+    /// Require typing "delete" or the number of items when the matches total more than this size (e.g. 5G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    confirm_threshold: Option<u64>,
+
+    /// Let --skip-confirmation remove matches above --confirm-threshold without typing anything
+    #[arg(long, requires_all = ["skip_confirmation", "confirm_threshold"])]
+    i_know_what_im_doing: bool,
+
+    /// Still confirm each directory, showing its size, while files are removed as confirmed
+    #[arg(long, visible_alias = "confirm-each-dir", conflicts_with_all = ["interactive", "select", "stage"])]
+    confirm_dirs: bool,
+
     /// Include matched symlinks for removal
     #[arg(short, long)]
     include_symlinks: bool,
 
+    /// Descend into symlinked directories which stay inside the working directory
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// What removing a matched symlink does: unlink it, or follow-and-delete its target as well
+    #[arg(
+        long,
+        value_name = "BEHAVIOR",
+        default_value = "unlink",
+        requires = "include_symlinks"
+    )]
+    symlink_behavior: SymlinkBehavior,
+
+    /// Move matched items to the system trash instead of deleting them
+    #[arg(short, long)]
+    trash: bool,
+
+    /// Delete items which cannot be moved to the trash instead of failing
+    #[arg(long, requires = "trash")]
+    trash_fallback_delete: bool,
+
+    /// Move all matches into a staging directory first, deleting it once all moves succeeded
+    #[arg(long, conflicts_with_all = ["trash", "quarantine", "backup_to", "shred", "interactive"])]
+    stage: bool,
+
+    /// Pack matches into this gzip tarball before removing them (needs the 'archive' build feature)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["quarantine", "stage"])]
+    archive: Option<PathBuf>,
+
+    /// Leave files open in some process in place (Linux and Windows only)
+    #[arg(long)]
+    skip_in_use: bool,
+
+    /// Overwrite matched files with zeros before deleting them (single pass)
+    #[arg(long, conflicts_with_all = ["trash", "quarantine"])]
+    shred: bool,
+
+    /// Skip matches smaller than this size (e.g. 512K, 100MB, 1.5GiB)
+    #[arg(long, value_parser = parse_size)]
+    min_size: Option<u64>,
+
+    /// Skip matches larger than this size (e.g. 512K, 100MB, 1.5GiB)
+    #[arg(long, value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    /// Abort without removing anything if the matches total more than this size (e.g. 10G)
+    #[arg(long, value_parser = parse_size)]
+    max_delete_size: Option<u64>,
+
+    /// Abort without removing anything if more than N items match
+    #[arg(long, value_name = "N")]
+    max_delete_count: Option<usize>,
+
+    /// Stop matching after N items; nothing is removed unless --limit-ok is also given
+    #[arg(long = "limit", value_name = "N")]
+    max_deletions: Option<usize>,
+
+    /// Stop matching once the matches would total more than this size (e.g. 10G)
+    #[arg(long = "size-limit", value_name = "SIZE", value_parser = parse_size)]
+    max_total_size: Option<u64>,
+
+    /// Remove the matches found before --limit or --size-limit was reached
+    #[arg(long)]
+    limit_ok: bool,
+
+    /// Stop removing at the first item which cannot be removed
+    #[arg(long, conflicts_with = "max_errors")]
+    abort_on_error: bool,
+
+    /// Stop removing once this many items could not be removed
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_errors: Option<u32>,
+
+    /// Exit with 0 rather than 2 when some items could not be removed
+    #[arg(long, conflicts_with_all = ["abort_on_error", "max_errors"])]
+    no_fail_on_errors: bool,
+
+    /// Skip directory matches smaller than this size, leaving files alone (e.g. 50M)
+    #[arg(long, value_parser = parse_size)]
+    min_dir_size: Option<u64>,
+
+    /// Do not descend more than N levels below the working directory
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Ignore entries less than N levels below the working directory
+    #[arg(long, value_name = "N")]
+    min_depth: Option<usize>,
+
+    /// Do not cross file system boundaries (mount points) below the working directory
+    #[arg(long)]
+    one_file_system: bool,
+
+    /// Count blocks used on disk and hardlinked files once (unix only)
+    #[arg(long)]
+    accurate_size: bool,
+
+    /// Remove all but one copy of identical files matching the patterns
+    #[arg(long, visible_alias = "dedupe")]
+    duplicates: bool,
+
+    /// Copy of identical files kept by --duplicates: oldest or shortest (path)
+    #[arg(long, default_value = "oldest", requires = "duplicates")]
+    keep: KeepPolicy,
+
+    /// Do not hash files larger than this for --duplicates (default 256MiB)
+    #[arg(long, value_parser = parse_size, requires = "duplicates")]
+    max_hash_size: Option<u64>,
+
+    /// Match and size entries in parallel using N threads (0 = all cores)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Remove the matches in parallel, using --jobs threads (default all cores)
+    #[arg(long, conflicts_with_all = ["abort_on_error", "max_errors"])]
+    parallel_delete: bool,
+
+    /// Only remove matches modified at least this long ago (e.g. 30m, 12h, 7d)
+    #[arg(long, value_parser = parse_duration)]
+    older_than: Option<u64>,
+
+    /// Only remove matches modified within this long (e.g. 30m, 12h, 7d)
+    #[arg(long, value_parser = parse_duration)]
+    newer_than: Option<u64>,
+
+    /// Never remove matches modified within this long, looking inside directories (e.g. 1h)
+    #[arg(long, value_parser = parse_duration)]
+    protect_newer_than: Option<u64>,
+
+    /// Only log the final summary and failures, not every matched item
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Log more detail: -v for the per-item lines, -vv for debug, -vvv for trace messages
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log level: off, error, warn, info, debug or trace (default warn, with the summary)
+    #[arg(long, value_name = "LEVEL", conflicts_with = "verbose")]
+    log_level: Option<LevelFilter>,
+
+    /// Also append the log, with a record of every removed item, to this file
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Show the number and size of matched items per pattern
+    #[arg(short, long)]
+    stats: bool,
+
+    /// Report how much free space the removal gave back on the volume
+    #[arg(long)]
+    report_free_space: bool,
+
+    /// Warn if the removal is estimated to leave less free space than this on the volume (e.g. 10G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    min_free_after: Option<u64>,
+
+    /// Show a spinner while scanning and a progress bar while removing
+    #[arg(long = "progress")]
+    show_progress: bool,
+
+    /// Timestamp used by the age filters: modified, accessed or created
+    #[arg(long, default_value = "modified")]
+    age_field: AgeField,
+
+    /// Units in which sizes are shown: binary (KiB, MiB, ...) or decimal (KB, MB, ...)
+    #[arg(long, value_name = "UNITS", default_value = "binary")]
+    size_units: SizeUnits,
+
+    /// Hidden (dot) entries: include them, exclude them or only match them
+    #[arg(long, value_name = "POLICY", default_value = "include")]
+    hidden: HiddenPolicy,
+
+    /// Output format: text, json, ndjson, csv, markdown or print0 (logs go to stderr otherwise)
+    #[arg(long, default_value = "text")]
+    output_format: OutputFormat,
+
+    /// Stream a JSON line to stdout for every match, removal and failure, then a summary
+    #[arg(long, conflicts_with_all = ["output_format", "print0", "tree"])]
+    ndjson: bool,
+
+    /// Only print the matched paths, NUL separated, without removing anything
+    #[arg(long, alias = "null-output", conflicts_with_all = ["output_format", "tree", "interactive", "select"])]
+    print0: bool,
+
+    /// Print the matches as a tree with what happened to each, instead of a flat list
+    #[arg(long)]
+    tree: bool,
+
+    /// Write the --output-format results to this file instead of stdout
+    #[arg(long, value_name = "PATH")]
+    output_file: Option<PathBuf>,
+
+    /// Also write a CSV row for every match, with what happened to it, to this file
+    #[arg(long, value_name = "PATH")]
+    csv: Option<PathBuf>,
+
+    /// Copy removed items into this directory and write a restore manifest
+    #[arg(long, value_name = "DIR")]
+    backup_to: Option<PathBuf>,
+
+    /// Move removed items into a dated subdirectory of DIR, with a restore manifest
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["backup_to", "trash"])]
+    quarantine: Option<PathBuf>,
+
+    /// Restore the items recorded in a backup manifest
+    #[arg(long, value_name = "MANIFEST")]
+    restore: Option<PathBuf>,
+
+    /// Make read-only items writable when they cannot be removed (the default on Windows), or overwrite existing paths when restoring
+    #[arg(long)]
+    force: bool,
+
+    /// Shell command to run after items were removed, with RCLEAN_COUNT, RCLEAN_SIZE and RCLEAN_PATH set
+    #[arg(long, value_name = "CMD")]
+    post_hook: Option<String>,
+
+    /// Fail when the post hook fails, instead of only logging an error
+    #[arg(long, requires = "post_hook")]
+    hook_strict: bool,
+
+    /// Command to run before removing each item, with the path as '{}' and in RCLEAN_PATH; skips the item if it fails
+    #[arg(long, value_name = "CMD")]
+    pre_delete_hook: Option<String>,
+
+    /// Command to run after removing each item, with the path as '{}' and in RCLEAN_PATH
+    #[arg(long, value_name = "CMD")]
+    post_delete_hook: Option<String>,
+
+    /// Run the delete hooks once per run for the working directory instead of once per item
+    #[arg(long)]
+    hook_per_run: bool,
+
+    /// Run the delete hooks through the shell instead of directly
+    #[arg(long)]
+    hook_shell: bool,
+
+    /// Run this command on each match instead of removing it, with the path as '{}' (appended if absent)
+    #[arg(long, value_name = "CMD", conflicts_with_all = ["trash", "quarantine", "backup_to", "stage", "shred", "truncate"])]
+    exec: Option<String>,
+
+    /// Run the --exec command once on as many matches as fit on a command line, passed as '{}'
+    #[arg(long, requires = "exec")]
+    exec_batch: bool,
+
+    /// Do not record the run in the history used by 'rclean undo'
+    #[arg(long)]
+    no_history: bool,
+
     /// list default glob patterns
     #[arg(short, long)]
     list: bool,
 }
 
+/// Commands on the history of previous runs, and for planning a run
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Put back what the last run (or the given history file) trashed or quarantined
+    Undo {
+        /// History file of the run to undo
+        #[arg(value_name = "FILE")]
+        manifest: Option<PathBuf>,
+    },
+    /// List the recorded runs
+    History,
+    /// Only collect the matches and write them to a plan file
+    Plan {
+        /// Plan file to write
+        #[arg(long, value_name = "FILE")]
+        out: PathBuf,
+    },
+    /// Remove the matches of a plan file, skipping those changed since the plan was made
+    Apply {
+        /// Plan file written by `rclean plan`
+        #[arg(value_name = "FILE")]
+        plan: PathBuf,
+    },
+}
+
 // --------------------------------------------------------------------
 // main function
 
-/// configure and initialize logging
+/// the log level given by `--log-level`, or by the number of `-v` flags
+///
+/// The confirmation listing and the summary go to `SUMMARY_LOG_TARGET`,
+/// which is shown at the default `warn` level as well.
+fn log_level(log_level: Option<LevelFilter>, verbose: u8) -> LevelFilter {
+    match (log_level, verbose) {
+        (Some(level), _) => level,
+        (None, 0) => LevelFilter::Warn,
+        (None, 1) => LevelFilter::Info,
+        (None, 2) => LevelFilter::Debug,
+        (None, _) => LevelFilter::Trace,
+    }
+}
+
+/// configure and initialize logging to the terminal and, if given, to a
+/// plain-text log file which also records every removed item
 ///
 /// # Errors
 ///
-/// This function will return an error if the logging cannot be initialized.
-fn init_logging() {
-    let logging_config = simplelog::ConfigBuilder::new()
+/// This function will return an error if the log file cannot be opened.
+fn init_logging(
+    output_format: OutputFormat,
+    level: LevelFilter,
+    log_file: Option<&Path>,
+) -> io::Result<()> {
+    let mut terminal_config = simplelog::ConfigBuilder::new();
+    terminal_config
         .set_level_color(simplelog::Level::Info, Some(simplelog::Color::Green))
-        .set_level_color(simplelog::Level::Trace, Some(simplelog::Color::Magenta))
-        .build();
+        .set_level_color(simplelog::Level::Trace, Some(simplelog::Color::Magenta));
+    // the summary, and the audit records of the log file, are not hidden
+    // at the default level along with the per-item lines, only when
+    // asking for errors only or no log at all
+    let summary_level = if level >= LevelFilter::Warn {
+        level.max(LevelFilter::Info)
+    } else {
+        level
+    };
+
+    // keep stdout parseable when writing json
+    let terminal_mode = match output_format {
+        OutputFormat::Text => simplelog::TerminalMode::Mixed,
+        OutputFormat::Json
+        | OutputFormat::Ndjson
+        | OutputFormat::Csv
+        | OutputFormat::Markdown
+        | OutputFormat::Print0 => simplelog::TerminalMode::Stderr,
+    };
 
-    simplelog::TermLogger::init(
-        simplelog::LevelFilter::Info,
-        logging_config,
-        simplelog::TerminalMode::Mixed,
-        simplelog::ColorChoice::Auto,
-    )
-    .expect("could not initialize logging");
+    let mut loggers: Vec<Box<dyn simplelog::SharedLogger>> = vec![
+        simplelog::TermLogger::new(
+            level,
+            terminal_config
+                .clone()
+                .add_filter_ignore_str(AUDIT_LOG_TARGET)
+                .add_filter_ignore_str(SUMMARY_LOG_TARGET)
+                .build(),
+            terminal_mode,
+            simplelog::ColorChoice::Auto,
+        ),
+        simplelog::TermLogger::new(
+            summary_level,
+            terminal_config
+                .add_filter_allow_str(SUMMARY_LOG_TARGET)
+                .build(),
+            terminal_mode,
+            simplelog::ColorChoice::Auto,
+        ),
+    ];
+    if let Some(log_file) = log_file {
+        // keep colour codes, e.g. of the --stats table, out of the file
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+        let file = fs::File::options()
+            .create(true)
+            .append(true)
+            .open(log_file)?;
+        let mut file_config = simplelog::ConfigBuilder::new();
+        file_config.set_time_format_rfc3339();
+        loggers.push(simplelog::WriteLogger::new(
+            level,
+            file_config
+                .clone()
+                .add_filter_ignore_str(AUDIT_LOG_TARGET)
+                .add_filter_ignore_str(SUMMARY_LOG_TARGET)
+                .build(),
+            file.try_clone()?,
+        ));
+        loggers.push(simplelog::WriteLogger::new(
+            summary_level,
+            file_config
+                .add_filter_allow_str(AUDIT_LOG_TARGET)
+                .add_filter_allow_str(SUMMARY_LOG_TARGET)
+                .build(),
+            file,
+        ));
+    }
+    simplelog::CombinedLogger::init(loggers).expect("could not initialize logging");
+    Ok(())
 }
 
-/// generate default config file: '.rclean.toml'
+/// generate default config file: '.rclean.toml' or '.rclean.yaml'
 ///
 /// # Errors
 ///
 /// This function will return an error if the file cannot be written.
-fn write_configfile(job: &CleaningJob) {
-    let toml: String = toml::to_string(&job).unwrap();
-    let cfg_out = Path::new(SETTINGS_FILENAME);
+fn write_configfile(job: &CleaningJob, format: ConfigFormat) {
+    let filename = format.filename();
+    let contents = match format.serialize(job) {
+        Ok(contents) => contents,
+        Err(e) => {
+            error!("{e}");
+            return;
+        }
+    };
+    let cfg_out = Path::new(filename);
     if !Path::new(cfg_out).exists() {
-        info!("generating default '{SETTINGS_FILENAME}' file");
-        fs::write(cfg_out, toml).unwrap();
+        info!(target: SUMMARY_LOG_TARGET, "generating default '{filename}' file");
+        fs::write(cfg_out, contents).unwrap();
     } else {
-        error!("cannot overwrite existing '{SETTINGS_FILENAME}' file");
+        error!("cannot overwrite existing '{filename}' file");
     }
 }
 
-/// run cleanup job using configuration from '.rclean.toml' file
+/// run cleanup job using configuration from a '.rclean.toml' or
+/// '.rclean.yaml' file, merged over the global settings file if there
+/// is one, returning the exit code
 ///
 /// # Errors
 ///
-/// This function will return an error if the file cannot be read.
-fn run_job_from_configfile() {
-    let settings_file = Path::new(SETTINGS_FILENAME);
-    if settings_file.exists() {
-        info!("using settings file: {:?}", SETTINGS_FILENAME);
-        let Ok(contents) = fs::read_to_string(SETTINGS_FILENAME) else {
-            error!("cannot read file");
-            return;
-        };
-        let mut job: CleaningJob = toml::from_str(&contents).expect("cannot deerialize from .toml");
-        job.run();
+/// This function will return `EXIT_FATAL` if the files cannot be read.
+fn run_job_from_configfile(no_history: bool, show_progress: bool, fail_on_errors: bool) -> i32 {
+    let local = discover_config(Path::new("."));
+    let global = discover_global_config();
+    for settings_file in global.iter().chain(local.iter()) {
+        info!("using settings file: {:?}", settings_file.display());
+    }
+    let job = match (&global, &local) {
+        (Some(global), Some(local)) => load_merged_config(global, local),
+        (Some(settings_file), None) | (None, Some(settings_file)) => load_config(settings_file),
+        (None, None) => {
+            error!("Error: settings file '{SETTINGS_FILENAME}' not found");
+            return EXIT_FATAL;
+        }
+    };
+    match job.and_then(|job| job.validate().map(|_| job)) {
+        Ok(mut job) => {
+            if job.history_dir.is_none() && !no_history {
+                job.history_dir = default_history_dir();
+            }
+            run_job(&mut job, show_progress, fail_on_errors)
+        }
+        Err(e) => {
+            error!("{e}");
+            EXIT_FATAL
+        }
+    }
+}
+
+/// restore removed items from a backup manifest, returning the exit code
+///
+/// # Errors
+///
+/// This function will return `EXIT_PARTIAL` if any item cannot be restored.
+fn restore_from_manifest(manifest: &Path, force: bool) -> i32 {
+    let report = match restore::restore(manifest, force) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("{e}");
+            return EXIT_FATAL;
+        }
+    };
+    for path in report.restored.iter() {
+        info!("Restored: {:?}", path.display());
+    }
+    for (path, reason) in report.failed.iter() {
+        error!("could not restore {:?}: {}", path.display(), reason);
+    }
+    info!(
+        target: SUMMARY_LOG_TARGET,
+        "Restored {} item(s), {} failed",
+        report.restored.len(),
+        report.failed.len()
+    );
+    if report.failed.is_empty() {
+        EXIT_SUCCESS
     } else {
-        error!("Error: settings file '{SETTINGS_FILENAME}' not found");
+        EXIT_PARTIAL
     }
 }
 
+/// undo a recorded run, by default the latest one, returning the exit code
+///
+/// # Errors
+///
+/// This function will return `EXIT_PARTIAL` if any item cannot be restored.
+fn undo(manifest: Option<PathBuf>) -> i32 {
+    let manifest = manifest
+        .or_else(|| default_history_dir().and_then(|dir| history::list_history(&dir).pop()));
+    let Some(manifest) = manifest else {
+        error!("Error: no recorded runs to undo");
+        return EXIT_FATAL;
+    };
+    info!("undoing run: {:?}", manifest.display());
+    let report = match history::restore_from_manifest(&manifest) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("{e}");
+            return EXIT_FATAL;
+        }
+    };
+    for path in report.restored.iter() {
+        info!("Restored: {:?}", path.display());
+    }
+    for path in report.skipped.iter() {
+        warn!("skipping {:?}: path exists", path.display());
+    }
+    for (path, reason) in report.failed.iter() {
+        error!("could not restore {:?}: {}", path.display(), reason);
+    }
+    info!(
+        target: SUMMARY_LOG_TARGET,
+        "Restored {} item(s), {} conflict(s), {} failed",
+        report.restored.len(),
+        report.skipped.len(),
+        report.failed.len()
+    );
+    if report.failed.is_empty() {
+        EXIT_SUCCESS
+    } else {
+        EXIT_PARTIAL
+    }
+}
+
+/// collect the matches of a job into a plan file, returning the exit code
+fn save_plan(job: &mut CleaningJob, out: &Path) -> i32 {
+    let plan = match job.scan() {
+        Ok(plan) => plan,
+        Err(e) => {
+            error!("{e}");
+            return EXIT_FATAL;
+        }
+    };
+    if let Err(e) = plan.save(out) {
+        error!("could not write plan {:?}: {e}", out.display());
+        return EXIT_FATAL;
+    }
+    info!(
+        target: SUMMARY_LOG_TARGET,
+        "Planned {} item(s) totalling {} in {:?}",
+        plan.entries.len(),
+        format_size_in(plan.size(), job.size_units),
+        out.display()
+    );
+    if plan.entries.is_empty() {
+        EXIT_NO_MATCH
+    } else {
+        EXIT_SUCCESS
+    }
+}
+
+/// remove the unchanged matches of a plan file, returning the exit code
+fn apply_plan(job: &mut CleaningJob, plan_path: &Path, fail_on_errors: bool) -> i32 {
+    let plan = match ScanReport::load(plan_path) {
+        Ok(plan) => plan,
+        Err(e) => {
+            error!("{e}");
+            return EXIT_FATAL;
+        }
+    };
+    info!("applying plan made at {} for {:?}", plan.time, plan.path);
+    let report = match job.apply(&plan) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("{e}");
+            return EXIT_FATAL;
+        }
+    };
+    for (path, reason) in report.failed.iter() {
+        error!("could not remove {:?}: {}", path.display(), reason);
+    }
+    info!(
+        target: SUMMARY_LOG_TARGET,
+        "Removed {} item(s), {} stale, {} missing, {} outside, {} failed",
+        report.removed.len(),
+        report.stale.len(),
+        report.missing.len(),
+        report.outside.len(),
+        report.failed.len()
+    );
+    if !report.not_attempted.is_empty() {
+        warn!(
+            "Stopped at the error limit, leaving {} item(s) untouched",
+            report.not_attempted.len()
+        );
+    }
+    if fail_on_errors && !report.failed.is_empty() {
+        EXIT_PARTIAL
+    } else if report.removed.is_empty() {
+        EXIT_NO_MATCH
+    } else {
+        EXIT_SUCCESS
+    }
+}
+
+/// list the recorded runs with what they removed, returning the exit code
+fn list_history(units: SizeUnits) -> i32 {
+    let paths = default_history_dir()
+        .map(|dir| history::list_history(&dir))
+        .unwrap_or_default();
+    if paths.is_empty() {
+        info!(target: SUMMARY_LOG_TARGET, "no recorded runs");
+    }
+    for path in paths.iter() {
+        match history::load_history(path) {
+            Ok(run) => {
+                let restorable = run.entries.iter().filter(|e| e.is_restorable()).count();
+                let size: u64 = run.entries.iter().map(|e| e.size).sum();
+                info!(
+                    target: SUMMARY_LOG_TARGET,
+                    "{}: {} item(s) totalling {}, {} restorable ({:?})",
+                    run.time,
+                    run.entries.len(),
+                    format_size_in(size, units),
+                    restorable,
+                    path.display()
+                );
+            }
+            Err(e) => warn!("{e}"),
+        }
+    }
+    EXIT_SUCCESS
+}
+
+/// Progress of a run with `--progress`: a spinner while the matches are
+/// collected, then a bar over the removal of the confirmed targets, whose
+/// number is known once they have been collected
+///
+/// With `--skip-confirmation` matches are removed while they are
+/// collected, so the removals are counted on the spinner instead.
+struct RemovalProgress {
+    enabled: bool,
+    spinner: Option<ProgressBar>,
+    bar: Option<ProgressBar>,
+    /// entries walked and matched so far
+    scanned: (usize, usize),
+    removed: u64,
+    freed: u64,
+    units: SizeUnits,
+}
+
+impl RemovalProgress {
+    fn new(enabled: bool, units: SizeUnits) -> Self {
+        RemovalProgress {
+            enabled,
+            spinner: None,
+            bar: None,
+            scanned: (0, 0),
+            removed: 0,
+            freed: 0,
+            units,
+        }
+    }
+
+    fn on_event(&mut self, event: &CleanEvent) {
+        if !self.enabled {
+            return;
+        }
+        match event {
+            CleanEvent::ScanProgress { scanned, matched } => {
+                self.scanned = (*scanned, *matched);
+                self.update_spinner();
+            }
+            CleanEvent::RemovalStarted { total } => {
+                if let Some(spinner) = self.spinner.take() {
+                    spinner.finish_and_clear();
+                }
+                let style = ProgressStyle::with_template(
+                    "{prefix} {pos}/{len} [{bar:40.green}] {per_sec}, {msg} freed, \
+                     {elapsed_precise} (ETA {eta})",
+                )
+                .expect("valid progress template")
+                .progress_chars("=> ");
+                let bar = ProgressBar::new(*total as u64)
+                    .with_style(style)
+                    .with_prefix("Deleting")
+                    .with_message(format_size_in(0, self.units));
+                self.bar = Some(bar);
+            }
+            CleanEvent::Deleted { .. } | CleanEvent::Failed { .. } => {
+                // empty directories pruned afterwards are not counted
+                if self.bar.as_ref().is_some_and(|bar| bar.is_finished()) {
+                    return;
+                }
+                if let CleanEvent::Deleted { size, .. } = event {
+                    self.removed += 1;
+                    self.freed += size;
+                }
+                match self.bar.as_ref() {
+                    Some(bar) => {
+                        bar.set_message(format_size_in(self.freed, self.units));
+                        bar.inc(1);
+                        if bar.position() == bar.length().unwrap_or_default() {
+                            self.finish();
+                        }
+                    }
+                    None => self.update_spinner(),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// show the counts so far on the spinner, starting it if needed
+    fn update_spinner(&mut self) {
+        let spinner = self.spinner.get_or_insert_with(|| {
+            let spinner = ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template("{spinner} {msg} {elapsed_precise}")
+                    .expect("valid progress template"),
+            );
+            spinner.enable_steady_tick(Duration::from_millis(100));
+            spinner
+        });
+        let (scanned, matched) = self.scanned;
+        let mut message = format!("Scanning: {scanned} entries, {matched} matched");
+        if self.removed > 0 {
+            message += &format!(
+                ", {} deleted ({} freed)",
+                self.removed,
+                format_size_in(self.freed, self.units)
+            );
+        }
+        spinner.set_message(message);
+    }
+
+    /// leave the bar showing how many targets were actually removed, or
+    /// the spinner showing the final counts
+    fn finish(&self) {
+        if let Some(spinner) = self.spinner.as_ref().filter(|bar| !bar.is_finished()) {
+            spinner.abandon();
+        }
+        if let Some(bar) = self.bar.as_ref().filter(|bar| !bar.is_finished()) {
+            bar.set_position(self.removed);
+            bar.set_prefix("Deleted");
+            bar.abandon();
+        }
+    }
+}
+
+/// run a cleaning job, returning the exit code
+fn run_job(job: &mut CleaningJob, show_progress: bool, fail_on_errors: bool) -> i32 {
+    let mut progress = RemovalProgress::new(show_progress && !job.dry_run, job.size_units);
+    let result = job.run_with_callback(|event| progress.on_event(event));
+    progress.finish();
+    exit_code(job, result, fail_on_errors)
+}
+
+/// run cleaning job on the candidate paths read from stdin, returning the
+/// exit code
+fn run_job_on_stdin(
+    job: &mut CleaningJob,
+    null_separated: bool,
+    show_progress: bool,
+    fail_on_errors: bool,
+) -> i32 {
+    let paths = match read_stdin_paths(null_separated) {
+        Ok(paths) => paths,
+        Err(e) => {
+            error!("could not read paths from stdin: {e}");
+            return EXIT_FATAL;
+        }
+    };
+    let mut progress = RemovalProgress::new(show_progress && !job.dry_run, job.size_units);
+    let result = job.run_on_paths_with_callback(paths, |event| progress.on_event(event));
+    progress.finish();
+    exit_code(job, result, fail_on_errors)
+}
+
+/// map the outcome of a finished job to its exit code, where items which
+/// could not be removed only count as a failure with `fail_on_errors`
+fn exit_code(job: &CleaningJob, result: rclean::Result<CleanReport>, fail_on_errors: bool) -> i32 {
+    match result {
+        // some items may have been removed before the job stopped
+        Err(e @ CleanError::DeletionAborted { .. }) => {
+            error!("{e}");
+            EXIT_PARTIAL
+        }
+        Err(e) => {
+            error!("{e}");
+            EXIT_FATAL
+        }
+        Ok(report) if fail_on_errors && !report.failed.is_empty() => EXIT_PARTIAL,
+        Ok(_) if job.matches().next().is_none() => EXIT_NO_MATCH,
+        Ok(_) => EXIT_SUCCESS,
+    }
+}
+
+/// read newline or NUL separated paths from stdin, skipping empty ones
+fn read_stdin_paths(null_separated: bool) -> io::Result<Vec<PathBuf>> {
+    let mut input = vec![];
+    io::stdin().read_to_end(&mut input)?;
+    let separator = if null_separated { b'\0' } else { b'\n' };
+    Ok(input
+        .split(|&b| b == separator)
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .map(path_from_bytes)
+        .collect())
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// fill in the options not given on the command line from `RCLEAN_*`
+/// environment variables, returning the globs to exclude
+fn merge_env(args: &mut Args, env: EnvConfig, path_given: bool) -> Vec<String> {
+    if let (false, Some(path)) = (path_given, env.path.clone()) {
+        args.path = vec![path];
+    }
+    args.glob = args.glob.take().or(env.patterns.clone());
+    args.dry_run |= env.dry_run.unwrap_or_default();
+    args.skip_confirmation |= env.skip_confirmation.unwrap_or_default();
+    args.trash |= env.trash_mode.unwrap_or_default();
+    args.older_than = args.older_than.or(env.older_than_secs);
+    args.newer_than = args.newer_than.or(env.newer_than_secs);
+    args.min_size = args.min_size.or(env.min_size);
+    args.max_size = args.max_size.or(env.max_size);
+    env.negated_excludes()
+}
+
 /// main function
 fn main() {
-    init_logging();
-    let args = Args::parse();
-    if args.configfile {
-        run_job_from_configfile();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if args.print0 {
+        // a listing for other tools, which never removes or asks anything
+        args.output_format = OutputFormat::Print0;
+        args.dry_run = true;
+        args.skip_confirmation = true;
+    }
+    if args.ndjson {
+        args.output_format = OutputFormat::Ndjson;
+    }
+    if let Err(e) = init_logging(
+        args.output_format,
+        log_level(args.log_level, args.verbose),
+        args.log_file.as_deref(),
+    ) {
+        eprintln!("could not open log file: {e}");
+        std::process::exit(EXIT_FATAL);
+    }
+    let code = if let Some(Command::Undo { manifest }) = args.command {
+        undo(manifest)
+    } else if let Some(Command::History) = args.command {
+        list_history(args.size_units)
+    } else if let Some(manifest) = args.restore {
+        restore_from_manifest(&manifest, args.force)
+    } else if args.configfile {
+        run_job_from_configfile(args.no_history, args.show_progress, !args.no_fail_on_errors)
     } else if args.list {
-        info!("default patterns: {:?}", get_default_patterns());
+        info!(target: SUMMARY_LOG_TARGET, "default patterns: {:?}", get_default_patterns());
+        info!(target: SUMMARY_LOG_TARGET, "presets: {}", PRESET_NAMES.join(", "));
+        EXIT_SUCCESS
     } else {
+        let env = match EnvConfig::from_env() {
+            Ok(env) => env,
+            Err(e) => {
+                error!("{e}");
+                std::process::exit(EXIT_FATAL);
+            }
+        };
+        let path_given = matches.value_source("path") == Some(ValueSource::CommandLine);
+        let excludes = merge_env(&mut args, env, path_given);
+        // fall back to the default globs only if no patterns were given,
+        // and not at all for a plan, whose targets keep their own patterns
+        let mut globs = match args.glob {
+            Some(globs) => globs,
+            None if args.duplicates => vec!["**".to_string()],
+            None if !args.regex.is_empty()
+                || args.apply_plan.is_some()
+                || matches!(args.command, Some(Command::Apply { .. }))
+                || !args.preset.is_empty()
+                || args.stdin
+                || args.stdin0
+                || !args.truncate.is_empty()
+                || !args.require_sibling.is_empty()
+                || !args.retain.is_empty()
+                || args.empty_files
+                || args.gitignore =>
+            {
+                vec![]
+            }
+            None => get_default_patterns(),
+        };
+        globs.extend(excludes);
+        let mut paths = args.path;
+        let path = paths.remove(0);
         let mut job = CleaningJob::new(
-            args.path,
-            args.glob
-                .unwrap_or(get_default_patterns().iter().map(|x| x.to_string()).collect()),
+            path,
+            globs,
             args.dry_run,
             args.skip_confirmation,
             args.include_symlinks,
         );
-        if args.write_configfile {
-            write_configfile(&job);
+        job.paths = paths;
+        job.presets = args.preset;
+        job.anchored = args.anchored;
+        job.exclude_files = args.exclude_file;
+        job.trash_mode = args.trash;
+        job.trash_fallback_delete = args.trash_fallback_delete;
+        job.secure_delete = args.shred;
+        job.stage = args.stage;
+        job.skip_in_use = args.skip_in_use;
+        job.archive_path = args.archive;
+        // already set on Windows
+        job.force |= args.force;
+        job.min_size = args.min_size;
+        job.max_size = args.max_size;
+        job.max_delete_size = args.max_delete_size;
+        job.max_delete_count = args.max_delete_count;
+        job.max_deletions = args.max_deletions;
+        job.max_total_size = args.max_total_size;
+        job.limit_ok = args.limit_ok;
+        job.error_policy = match (args.abort_on_error, args.max_errors) {
+            (true, _) => ErrorPolicy::AbortOnFirstError,
+            (false, Some(max)) => ErrorPolicy::AbortAfterN(max),
+            (false, None) => ErrorPolicy::Continue,
+        };
+        job.min_dir_size = args.min_dir_size;
+        job.jobs = args.jobs;
+        job.parallel_delete = args.parallel_delete;
+        job.older_than_secs = args.older_than;
+        job.newer_than_secs = args.newer_than;
+        job.protect_newer_than_secs = args.protect_newer_than;
+        job.age_field = args.age_field;
+        job.size_units = args.size_units;
+        job.hidden_policy = args.hidden;
+        job.regex_patterns = args.regex;
+        job.exclude_regex_patterns = args.exclude_regex;
+        job.stats_mode = args.stats;
+        job.report_free_space = args.report_free_space;
+        job.min_free_after = args.min_free_after;
+        job.quiet = args.quiet;
+        job.remove_empty_files = args.empty_files;
+        job.match_types = args.match_type;
+        job.prune_empty_dirs = args.prune_empty;
+        job.output_format = args.output_format;
+        job.tree = args.tree;
+        job.output_file = args.output_file;
+        job.csv_file = args.csv;
+        job.backup_dir = args.backup_to;
+        job.quarantine_dir = args.quarantine;
+        job.max_depth = args.max_depth;
+        job.min_depth = args.min_depth;
+        job.use_gitignore = args.gitignore;
+        job.respect_gitignore = args.respect_gitignore;
+        job.untracked_only = args.untracked_only;
+        job.case_insensitive = args.ignore_case;
+        job.interactive = args.interactive;
+        job.confirm_dirs = args.confirm_dirs;
+        job.select = args.select;
+        job.confirm_threshold = args.confirm_threshold;
+        job.skip_typed_confirmation = args.i_know_what_im_doing;
+        job.conditional_patterns = args.require_sibling;
+        job.owned_by = args.owned_by;
+        job.prune_patterns = args.prune;
+        job.no_default_prune = args.no_default_prune;
+        job.no_rcleanignore = args.no_rcleanignore;
+        job.accurate_size = args.accurate_size;
+        job.same_file_system = args.one_file_system;
+        job.follow_symlinks = args.follow_symlinks;
+        job.symlink_behavior = args.symlink_behavior;
+        job.duplicates = args.duplicates;
+        job.keep = args.keep;
+        job.max_hash_size = args.max_hash_size;
+        job.keep_newest = args.keep_newest;
+        job.keep_oldest = args.keep_oldest;
+        job.retention_patterns = args.retain;
+        job.truncate_patterns = args.truncate;
+        job.post_hook = args.post_hook;
+        job.pre_delete_hook = args.pre_delete_hook;
+        job.post_delete_hook = args.post_delete_hook;
+        job.hook_per_run = args.hook_per_run;
+        job.hook_shell = args.hook_shell;
+        job.exec = args.exec;
+        job.exec_batch = args.exec_batch;
+        job.hook_strict = args.hook_strict;
+        let (save_plan_to, apply_plan_from) = match args.command {
+            Some(Command::Plan { out }) => (Some(out), None),
+            Some(Command::Apply { plan }) => (None, Some(plan)),
+            _ => (args.save_plan, args.apply_plan),
+        };
+        if let Some(out) = save_plan_to {
+            save_plan(&mut job, &out)
+        } else if args.write_configfile {
+            write_configfile(&job, args.format);
+            EXIT_SUCCESS
         } else {
-            job.run();
+            if !args.no_history {
+                job.history_dir = default_history_dir();
+            }
+            if let Some(plan) = apply_plan_from {
+                apply_plan(&mut job, &plan, !args.no_fail_on_errors)
+            } else if args.stdin || args.stdin0 {
+                run_job_on_stdin(
+                    &mut job,
+                    args.null || args.stdin0,
+                    args.show_progress,
+                    !args.no_fail_on_errors,
+                )
+            } else {
+                run_job(&mut job, args.show_progress, !args.no_fail_on_errors)
+            }
         }
-    }
+    };
+    std::process::exit(code);
 }
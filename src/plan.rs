@@ -0,0 +1,124 @@
+// --------------------------------------------------------------------
+// plan
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{entry_size, CleanError, Result};
+
+/// What an entry looked like when it was planned, to tell whether it
+/// changed before the plan is applied
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// apparent size in bytes, of all contents for a directory
+    pub len: u64,
+    /// modification time in nanoseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_ns: Option<u64>,
+}
+
+impl Fingerprint {
+    /// the fingerprint of an entry as it is now, not following a symlink
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let metadata = fs::symlink_metadata(path)?;
+        let len = if metadata.is_dir() {
+            entry_size(path)?
+        } else {
+            metadata.len()
+        };
+        let modified_ns = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .and_then(|since| u64::try_from(since.as_nanos()).ok());
+        Ok(Self { len, modified_ns })
+    }
+}
+
+/// A target recorded in a plan
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PlanEntry {
+    /// absolute path of the target
+    pub path: PathBuf,
+    pub pattern: String,
+    pub size: u64,
+    pub is_dir: bool,
+    pub fingerprint: Fingerprint,
+}
+
+/// The targets found by `CleaningJob::scan`, to be removed later by
+/// `CleaningJob::apply`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanReport {
+    /// UTC time of the scan
+    pub time: String,
+    /// base path of the job which made the plan
+    pub path: String,
+    /// further base paths of that job
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    pub entries: Vec<PlanEntry>,
+    /// true if the scan stopped at `max_deletions` or `max_total_size`
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl ScanReport {
+    /// read a plan from a json file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            CleanError::ConfigError(format!("cannot read {:?}: {e}", path.display()))
+        })?;
+        serde_json::from_str(&contents)
+            .map_err(|e| CleanError::ConfigError(format!("{:?}: {e}", path.display())))
+    }
+
+    /// write the plan as json
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+
+    /// total size of the planned targets
+    pub fn size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// true if a path lies strictly inside one of the base paths of the
+    /// plan, resolving its parent but not a symlink at the path itself
+    pub fn is_inside(&self, path: &Path) -> bool {
+        self.base_of(path).is_some()
+    }
+
+    /// the resolved base path of the plan which a path lies strictly
+    /// inside, if any
+    pub fn base_of(&self, path: &Path) -> Option<PathBuf> {
+        let (Some(parent), Some(_)) = (path.parent(), path.file_name()) else {
+            return None;
+        };
+        let parent = fs::canonicalize(parent).ok()?;
+        std::iter::once(&self.path)
+            .chain(self.paths.iter())
+            .filter_map(|root| fs::canonicalize(root).ok())
+            .find(|root| parent.starts_with(root))
+    }
+}
+
+/// Outcome of applying a plan
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ApplyReport {
+    /// targets removed, or which would be in a dry-run
+    pub removed: Vec<PathBuf>,
+    /// targets left alone because they changed since the plan was made
+    pub stale: Vec<PathBuf>,
+    /// targets which no longer exist
+    pub missing: Vec<PathBuf>,
+    /// targets outside the base paths of the plan, never removed
+    pub outside: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+    /// targets left untouched because `error_policy` stopped the removal
+    pub not_attempted: Vec<PathBuf>,
+}
@@ -0,0 +1,160 @@
+// --------------------------------------------------------------------
+// prompt
+
+use dialoguer::{Input, MultiSelect};
+use std::fmt;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::utils::{format_age, format_size_in, SizeUnits};
+use crate::{CleanError, Result};
+
+/// A target about to be removed, as shown in `interactive` mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetPrompt<'a> {
+    pub path: &'a Path,
+    pub size: u64,
+    /// time since the `age_field` timestamp, if it could be read
+    pub age: Option<Duration>,
+    pub is_dir: bool,
+    /// units in which the size is shown
+    pub units: SizeUnits,
+}
+
+impl fmt::Display for TargetPrompt<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Delete {:?} ({}",
+            self.path.display(),
+            format_size_in(self.size, self.units)
+        )?;
+        if let Some(age) = self.age {
+            write!(f, ", {} old", format_age(age))?;
+        }
+        write!(f, ")?")
+    }
+}
+
+/// Answer to the prompt for a target in `interactive` mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    /// remove this target
+    Yes,
+    /// keep this target
+    No,
+    /// remove this and all remaining targets without asking again
+    All,
+    /// keep this and all remaining targets
+    Quit,
+    /// list the contents of a directory target, then ask again
+    Details,
+}
+
+impl FromStr for Answer {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" => Ok(Answer::Yes),
+            "n" | "no" => Ok(Answer::No),
+            "a" | "all" => Ok(Answer::All),
+            "q" | "quit" => Ok(Answer::Quit),
+            "d" | "details" => Ok(Answer::Details),
+            _ => Err(format!("invalid answer: '{s}' (expected y, n, a, q or d)")),
+        }
+    }
+}
+
+/// Asks which targets to remove, in `interactive` and `select` mode
+pub trait Prompter {
+    /// the answer for a single target, in `interactive` mode
+    fn ask(&mut self, target: &TargetPrompt) -> Answer;
+
+    /// the indices of the targets to remove, in `select` mode, by
+    /// default chosen from a checklist on the terminal
+    fn select(&mut self, targets: &[TargetPrompt]) -> Result<Vec<usize>> {
+        select(targets)
+    }
+
+    /// the text typed to confirm a run above `confirm_threshold`, by
+    /// default read from the terminal
+    fn type_confirmation(&mut self, prompt: &str) -> String {
+        type_confirmation(prompt)
+    }
+}
+
+impl<F: FnMut(&TargetPrompt) -> Answer> Prompter for F {
+    fn ask(&mut self, target: &TargetPrompt) -> Answer {
+        self(target)
+    }
+}
+
+/// Prompter asking on the terminal
+pub struct Terminal;
+
+impl Prompter for Terminal {
+    fn ask(&mut self, target: &TargetPrompt) -> Answer {
+        ask(target)
+    }
+}
+
+/// ask about a target on the terminal, quitting if it cannot be asked
+pub fn ask(target: &TargetPrompt) -> Answer {
+    let choices = if target.is_dir {
+        "y/n/a/q/d"
+    } else {
+        "y/n/a/q"
+    };
+    Input::<String>::new()
+        .with_prompt(format!("{target} [{choices}]"))
+        .validate_with(|input: &String| input.parse::<Answer>().map(|_| ()))
+        .interact_text()
+        .ok()
+        .and_then(|input| input.parse().ok())
+        .unwrap_or(Answer::Quit)
+}
+
+/// read a line typed on the terminal, empty if it cannot be read
+pub fn type_confirmation(prompt: &str) -> String {
+    Input::<String>::new()
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .interact_text()
+        .unwrap_or_default()
+}
+
+/// choose targets from a checklist on the terminal, all of them checked
+/// at first; cancelling chooses none
+///
+/// # Errors
+///
+/// Returns a `ConfigError` if there is no terminal to show the list on.
+pub fn select(targets: &[TargetPrompt]) -> Result<Vec<usize>> {
+    if !std::io::stderr().is_terminal() {
+        return Err(CleanError::ConfigError(
+            "selecting targets needs a terminal (use --skip-confirmation or --dry-run instead)"
+                .to_string(),
+        ));
+    }
+    let items: Vec<String> = targets
+        .iter()
+        .map(|target| {
+            format!(
+                "{} ({})",
+                target.path.display(),
+                format_size_in(target.size, target.units)
+            )
+        })
+        .collect();
+    let selection = MultiSelect::new()
+        .with_prompt("Select the items to delete (space toggles, enter confirms, esc cancels)")
+        .items(&items)
+        .defaults(&vec![true; items.len()])
+        .max_length(20)
+        .interact_opt()
+        .map_err(|dialoguer::Error::IO(e)| CleanError::IoError(e))?;
+    Ok(selection.unwrap_or_default())
+}
@@ -0,0 +1,503 @@
+// --------------------------------------------------------------------
+// report
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::dedupe::DuplicateGroup;
+use crate::utils::{format_size, format_size_in, SizeUnits};
+
+/// Format of the results written to stdout
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// log lines only
+    #[default]
+    Text,
+    /// a single JSON document once the job has finished
+    Json,
+    /// one JSON object per line as entries are matched, then a summary
+    Ndjson,
+    /// one CSV row per matched entry once the job has finished
+    Csv,
+    /// a Markdown table of patterns and a list of paths, e.g. for a PR
+    Markdown,
+    /// the matched paths, each followed by a NUL byte, e.g. for `xargs -0`
+    Print0,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Ndjson => write!(f, "ndjson"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Markdown => write!(f, "markdown"),
+            OutputFormat::Print0 => write!(f, "print0"),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "print0" => Ok(OutputFormat::Print0),
+            _ => Err(format!(
+                "invalid output format: '{s}' (expected text, json, ndjson, csv, markdown or print0)"
+            )),
+        }
+    }
+}
+
+/// A matched entry with the pattern it matched and its size
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MatchedItem {
+    pub path: PathBuf,
+    pub pattern: String,
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// What happened to a matched entry
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    /// left in place, e.g. not confirmed or filtered out
+    #[default]
+    Kept,
+    /// removed permanently
+    Deleted,
+    /// moved to the system trash
+    Trashed,
+    /// moved into the quarantine directory
+    Quarantined,
+    /// would have been removed, in a dry-run
+    WouldDelete,
+    /// would have been moved to the trash, in a dry-run
+    WouldTrash,
+    /// would have been moved into the quarantine directory, in a dry-run
+    WouldQuarantine,
+    /// could not be removed
+    Failed,
+    /// left in place by `skip_in_use` because it was open
+    InUse,
+    /// emptied in place, matching one of the `truncate_patterns`
+    Truncated,
+    /// would have been emptied in place, in a dry-run
+    WouldTruncate,
+    /// left in place because `pre_delete_hook` failed for it
+    Skipped,
+    /// handed to the `exec` command instead of being removed
+    Executed,
+    /// would have been handed to the `exec` command, in a dry-run
+    WouldExecute,
+}
+
+impl fmt::Display for Disposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Disposition::Kept => write!(f, "kept"),
+            Disposition::Deleted => write!(f, "deleted"),
+            Disposition::Trashed => write!(f, "trashed"),
+            Disposition::Quarantined => write!(f, "quarantined"),
+            Disposition::WouldDelete => write!(f, "would delete"),
+            Disposition::WouldTrash => write!(f, "would trash"),
+            Disposition::WouldQuarantine => write!(f, "would quarantine"),
+            Disposition::Failed => write!(f, "failed"),
+            Disposition::InUse => write!(f, "in use"),
+            Disposition::Truncated => write!(f, "truncated"),
+            Disposition::WouldTruncate => write!(f, "would truncate"),
+            Disposition::Skipped => write!(f, "skipped"),
+            Disposition::Executed => write!(f, "executed"),
+            Disposition::WouldExecute => write!(f, "would execute"),
+        }
+    }
+}
+
+impl Disposition {
+    /// true if the entry was actually removed from its place
+    pub fn is_removal(self) -> bool {
+        matches!(
+            self,
+            Disposition::Deleted | Disposition::Trashed | Disposition::Quarantined
+        )
+    }
+}
+
+/// A matched entry in the JSON report, with what happened to it
+#[derive(Serialize)]
+pub struct ReportItem<'a> {
+    #[serde(flatten)]
+    pub item: &'a MatchedItem,
+    pub disposition: Disposition,
+}
+
+/// A path which could not be removed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FailedItem {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Totals for a finished cleaning job
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub path: String,
+    pub dry_run: bool,
+    pub count: usize,
+    pub size: u64,
+    pub failed: Vec<FailedItem>,
+    /// targets left in place by `skip_in_use`
+    #[serde(default)]
+    pub skipped_in_use: usize,
+    /// targets left in place because `pre_delete_hook` failed
+    #[serde(default)]
+    pub skipped_by_hook: usize,
+    /// targets left untouched because `error_policy` stopped the removal
+    #[serde(default)]
+    pub remaining: usize,
+    /// size of the tarball written by `archive_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_size: Option<u64>,
+    /// available space on the volume of the path before and after the
+    /// removal, with `report_free_space`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub free_space_before: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub free_space_after: Option<u64>,
+    /// available space estimated for after the removal, with
+    /// `report_free_space` or `min_free_after`, also in a dry-run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub free_space_after_estimate: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_deletions: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_size: Option<u64>,
+    /// true if matching stopped at one of the limits above
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl Summary {
+    /// collect failures given as (path, reason) pairs
+    pub fn failures(failed: &[(PathBuf, String)]) -> Vec<FailedItem> {
+        failed
+            .iter()
+            .map(|(path, error)| FailedItem {
+                path: path.clone(),
+                error: error.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Snapshot of a finished cleaning job, returned by `CleaningJob::run`
+/// and its variants
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CleanReport {
+    /// entries removed, or which would be in a dry-run
+    pub deleted_count: usize,
+    /// total size of those entries
+    pub total_size: u64,
+    /// entries which could not be removed, with the reason for each
+    pub failed: Vec<(PathBuf, String)>,
+    /// number and total size of the matches of each pattern
+    pub stats: HashMap<String, (usize, u64)>,
+    pub dry_run: bool,
+}
+
+/// A single line of NDJSON output, tagged by its `event`
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum Record<'a> {
+    /// written as soon as an entry is matched
+    Match(&'a MatchedItem),
+    /// written as soon as an entry is removed, trashed, quarantined or
+    /// truncated
+    Deleted { path: &'a Path, size: u64 },
+    /// written as soon as an entry could not be removed
+    Failed { path: &'a Path, error: &'a str },
+    /// written once the run is done
+    Summary(&'a Summary),
+}
+
+/// The full JSON document written in `OutputFormat::Json`
+#[derive(Serialize)]
+pub struct Report<'a> {
+    pub matches: Vec<ReportItem<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub duplicates: &'a [DuplicateGroup],
+    pub summary: &'a Summary,
+}
+
+/// serialize a value as a single line of JSON
+pub fn to_json_line<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("report values are always serializable")
+}
+
+/// A matched entry as a row of the CSV report
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CsvRow<'a> {
+    pub path: &'a Path,
+    /// file, dir or symlink
+    #[serde(rename = "type")]
+    pub kind: &'a str,
+    pub size_bytes: u64,
+    /// modification time, empty if it could not be read
+    pub modified_rfc3339: Option<String>,
+    pub matched_pattern: &'a str,
+    /// what happened to the entry, e.g. "deleted" or "would-delete"
+    pub action: String,
+    /// why the entry could not be removed, empty unless it failed
+    pub error: Option<&'a str>,
+}
+
+/// columns of the CSV report, in order
+const CSV_HEADER: [&str; 7] = [
+    "path",
+    "type",
+    "size_bytes",
+    "modified_rfc3339",
+    "matched_pattern",
+    "action",
+    "error",
+];
+
+/// write rows of the CSV report, with a header row even without any
+///
+/// Fields holding commas, quotes or newlines, which do occur in file
+/// names, are quoted.
+pub fn write_csv<'a, W: io::Write>(
+    writer: W,
+    rows: impl IntoIterator<Item = CsvRow<'a>>,
+) -> io::Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(writer);
+    writer.write_record(CSV_HEADER)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()
+}
+
+/// write the paths of the matched entries, each followed by a NUL byte
+pub fn write_print0<W: io::Write>(mut writer: W, items: &[MatchedItem]) -> io::Result<()> {
+    for item in items.iter() {
+        #[cfg(unix)]
+        writer.write_all(std::os::unix::ffi::OsStrExt::as_bytes(
+            item.path.as_os_str(),
+        ))?;
+        #[cfg(not(unix))]
+        writer.write_all(item.path.to_string_lossy().as_bytes())?;
+        writer.write_all(b"\0")?;
+    }
+    writer.flush()
+}
+
+/// render the per-pattern stats and the matched paths as Markdown
+pub fn to_markdown(
+    path: &str,
+    dry_run: bool,
+    stats: &HashMap<String, (usize, u64)>,
+    items: &[MatchedItem],
+    units: SizeUnits,
+) -> String {
+    let mut stats: Vec<_> = stats.iter().collect();
+    stats.sort();
+    let mut out = String::new();
+    // writing to a String cannot fail
+    let _ = writeln!(out, "# rclean report for `{path}`\n");
+    if dry_run {
+        let _ = writeln!(out, "Dry-run: nothing was removed.\n");
+    }
+    let _ = writeln!(out, "| Pattern | Items | Size |");
+    let _ = writeln!(out, "| --- | ---: | ---: |");
+    let (mut total_count, mut total_size) = (0, 0);
+    for (pattern, (count, size)) in stats {
+        let pattern = pattern.replace('|', "\\|");
+        let _ = writeln!(
+            out,
+            "| `{pattern}` | {count} | {} |",
+            format_size_in(*size, units)
+        );
+        total_count += count;
+        total_size += size;
+    }
+    let _ = writeln!(
+        out,
+        "| **Total** | {total_count} | {} |",
+        format_size_in(total_size, units)
+    );
+    let _ = writeln!(
+        out,
+        "\n<details>\n<summary>{} path(s)</summary>\n",
+        items.len()
+    );
+    for item in items.iter() {
+        let _ = writeln!(out, "- `{}`", item.path.display());
+    }
+    let _ = writeln!(out, "\n</details>");
+    out
+}
+
+/// render the per-pattern stats as a table of pattern, count and size
+/// columns with a total row, one line per entry
+///
+/// Patterns too long for a table of `width` columns are cut short, and
+/// with `color` sizes are coloured by magnitude. Without a width, e.g.
+/// when the output is piped, plain tab-separated lines with sizes in
+/// bytes are rendered instead of sizes in `units`.
+pub fn render_stats(
+    stats: &HashMap<String, (usize, u64)>,
+    width: Option<usize>,
+    color: bool,
+    units: SizeUnits,
+) -> Vec<String> {
+    let mut rows: Vec<(&str, String, u64)> = stats
+        .iter()
+        .map(|(pattern, (count, size))| (pattern.as_str(), count.to_string(), *size))
+        .collect();
+    rows.sort();
+    let total: (usize, u64) = stats
+        .values()
+        .fold((0, 0), |total, stat| (total.0 + stat.0, total.1 + stat.1));
+    rows.push(("Total", total.0.to_string(), total.1));
+    let Some(width) = width else {
+        return std::iter::once("pattern\tcount\tsize".to_string())
+            .chain(
+                rows.iter()
+                    .map(|(pattern, count, size)| format!("{pattern}\t{count}\t{size}")),
+            )
+            .collect();
+    };
+    let sizes: Vec<String> = rows
+        .iter()
+        .map(|row| format_size_in(row.2, units))
+        .collect();
+    let count_width = rows
+        .iter()
+        .map(|row| row.1.len())
+        .chain([5])
+        .max()
+        .unwrap_or_default();
+    let size_width = sizes
+        .iter()
+        .map(|s| s.len())
+        .chain([4])
+        .max()
+        .unwrap_or_default();
+    let pattern_width = rows
+        .iter()
+        .map(|row| row.0.chars().count())
+        .chain([7])
+        .max()
+        .unwrap_or_default()
+        .min(width.saturating_sub(count_width + size_width + 4).max(12));
+    let line = |pattern: &str, count: &str, size: String| {
+        format!("{pattern:<pattern_width$}  {count:>count_width$}  {size}")
+    };
+    let rule = "-".repeat(pattern_width + count_width + size_width + 4);
+    let mut lines = vec![
+        line("Pattern", "Count", format!("{:>size_width$}", "Size")),
+        rule.clone(),
+    ];
+    for (index, ((pattern, count, size), size_text)) in rows.iter().zip(sizes).enumerate() {
+        let pattern = if pattern.chars().count() > pattern_width {
+            let cut: String = pattern.chars().take(pattern_width - 1).collect();
+            format!("{cut}…")
+        } else {
+            pattern.to_string()
+        };
+        let size_text = format!("{size_text:>size_width$}");
+        // the caller already decided whether the terminal takes colour
+        let style = console::Style::new().force_styling(true);
+        let size_text = match (color, *size) {
+            (false, _) => size_text,
+            (true, size) if size >= 1 << 30 => style.red().apply_to(size_text).to_string(),
+            (true, size) if size >= 1 << 20 => style.yellow().apply_to(size_text).to_string(),
+            (true, _) => style.green().apply_to(size_text).to_string(),
+        };
+        if index == rows.len() - 1 {
+            lines.push(rule.clone());
+        }
+        lines.push(line(&pattern, count, size_text));
+    }
+    lines
+}
+
+/// A directory level of the tree drawn by `render_tree`
+#[derive(Default)]
+struct TreeNode<'a> {
+    item: Option<&'a MatchedItem>,
+    children: BTreeMap<String, TreeNode<'a>>,
+}
+
+/// render the matched entries as an indented tree below `root`, like the
+/// `tree` command, annotating each of them with its size and disposition
+pub fn render_tree(
+    root: &Path,
+    items: &[MatchedItem],
+    disposition: impl Fn(&Path) -> Disposition,
+) -> String {
+    let mut tree = TreeNode::default();
+    for item in items.iter() {
+        let relative = item.path.strip_prefix(root).unwrap_or(&item.path);
+        let mut node = &mut tree;
+        for component in relative.components() {
+            if component == std::path::Component::CurDir {
+                continue;
+            }
+            let name = component.as_os_str().to_string_lossy().into_owned();
+            node = node.children.entry(name).or_default();
+        }
+        node.item = Some(item);
+    }
+    let mut out = String::new();
+    // writing to a String cannot fail
+    let _ = writeln!(out, "{}", root.display());
+    write_tree_level(&mut out, &tree, "", &disposition);
+    out
+}
+
+/// draw the children of a tree node, each prefixed with its branch
+fn write_tree_level(
+    out: &mut String,
+    node: &TreeNode,
+    prefix: &str,
+    disposition: &impl Fn(&Path) -> Disposition,
+) {
+    let last = node.children.len().saturating_sub(1);
+    for (index, (name, child)) in node.children.iter().enumerate() {
+        let (branch, indent) = if index == last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        let _ = write!(out, "{prefix}{branch}{name}");
+        if let Some(item) = child.item {
+            let _ = write!(
+                out,
+                "{} ({}) [{}]",
+                if item.is_dir { "/" } else { "" },
+                format_size(item.size),
+                disposition(&item.path)
+            );
+        }
+        let _ = writeln!(out);
+        write_tree_level(out, child, &format!("{prefix}{indent}"), disposition);
+    }
+}
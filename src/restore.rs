@@ -0,0 +1,237 @@
+// --------------------------------------------------------------------
+// restore
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::{CleanError, EntryType, Result};
+
+/// name of the manifest written into a backup directory
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+
+/// A removed entry and where its backup copy was stored
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub original: PathBuf,
+    pub backup: PathBuf,
+    pub size: u64,
+    pub kind: EntryType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<PathBuf>,
+}
+
+/// Record of the entries removed by a run with a backup directory
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Outcome of restoring from a manifest
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RestoreReport {
+    pub restored: Vec<PathBuf>,
+    /// entries left alone because their original path exists again
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl Manifest {
+    /// read a manifest from a json file
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|e| {
+            CleanError::ConfigError(format!("cannot read {:?}: {e}", path.display()))
+        })?;
+        serde_json::from_str(&contents)
+            .map_err(|e| CleanError::ConfigError(format!("{:?}: {e}", path.display())))
+    }
+
+    /// write the manifest as json
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+}
+
+/// copy an entry into the backup directory before it is removed
+///
+/// `relative` is the entry's path relative to the cleaned base path and
+/// determines where the copy is stored below `backup_dir`.
+pub fn backup_entry(
+    path: &Path,
+    relative: &Path,
+    size: u64,
+    backup_dir: &Path,
+) -> io::Result<ManifestEntry> {
+    let metadata = path.symlink_metadata()?;
+    let original = absolute_path(path)?;
+    let backup = backup_dir.join("files").join(relative);
+    let (kind, link_target) = if metadata.is_symlink() {
+        (EntryType::Symlink, Some(fs::read_link(path)?))
+    } else if metadata.is_dir() {
+        copy_tree(path, &backup)?;
+        (EntryType::Dir, None)
+    } else {
+        if let Some(parent) = backup.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(path, &backup)?;
+        (EntryType::File, None)
+    };
+    Ok(ManifestEntry {
+        original,
+        backup,
+        size,
+        kind,
+        link_target,
+    })
+}
+
+/// move an entry to `dest`, e.g. into a quarantine directory
+///
+/// The entry is renamed if possible; across file systems it is copied
+/// and then removed instead.
+pub fn move_entry(path: &Path, dest: &Path, size: u64) -> io::Result<ManifestEntry> {
+    let metadata = path.symlink_metadata()?;
+    let original = absolute_path(path)?;
+    let (kind, link_target) = if metadata.is_symlink() {
+        (EntryType::Symlink, Some(fs::read_link(path)?))
+    } else if metadata.is_dir() {
+        (EntryType::Dir, None)
+    } else {
+        (EntryType::File, None)
+    };
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(path, dest).is_err() {
+        match kind {
+            EntryType::Dir => {
+                copy_tree(path, dest)?;
+                fs::remove_dir_all(path)?;
+            }
+            EntryType::Symlink => {
+                create_symlink(link_target.as_deref().unwrap_or(Path::new("")), dest)?;
+                fs::remove_file(path)?;
+            }
+            EntryType::File => {
+                fs::copy(path, dest)?;
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    Ok(ManifestEntry {
+        original,
+        backup: absolute_path(dest)?,
+        size,
+        kind,
+        link_target,
+    })
+}
+
+/// a path which does not exist yet: `path` itself, or failing that
+/// `path` with a numeric suffix such as "build.1"
+pub fn unique_path(path: &Path) -> PathBuf {
+    let mut candidate = path.to_path_buf();
+    let mut suffix = 0;
+    while candidate.symlink_metadata().is_ok() {
+        suffix += 1;
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{suffix}"));
+        candidate = path.with_file_name(name);
+    }
+    candidate
+}
+
+/// put the entries of a manifest back in their original locations
+///
+/// Existing paths are only overwritten when `force` is set. Entries which
+/// cannot be restored are reported rather than aborting the restore.
+pub fn restore(manifest_path: &Path, force: bool) -> Result<RestoreReport> {
+    let manifest = Manifest::load(manifest_path)?;
+    let mut report = RestoreReport::default();
+    for entry in manifest.entries.iter() {
+        match restore_entry(entry, force) {
+            Ok(()) => report.restored.push(entry.original.clone()),
+            Err(e) => report.failed.push((entry.original.clone(), e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// restore a single manifest entry
+fn restore_entry(entry: &ManifestEntry, force: bool) -> io::Result<()> {
+    let original = &entry.original;
+    if let Ok(existing) = original.symlink_metadata() {
+        if !force {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "path already exists (use --force to overwrite)",
+            ));
+        }
+        if existing.is_dir() {
+            fs::remove_dir_all(original)?;
+        } else {
+            fs::remove_file(original)?;
+        }
+    }
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match entry.kind {
+        EntryType::File => fs::copy(&entry.backup, original).map(|_| ()),
+        EntryType::Dir => copy_tree(&entry.backup, original),
+        EntryType::Symlink => {
+            let Some(target) = &entry.link_target else {
+                return Err(io::Error::other("symlink target missing from manifest"));
+            };
+            create_symlink(target, original)
+        }
+    }
+}
+
+/// recursively copy a directory, recreating symlinks rather than following them
+fn copy_tree(src: &Path, dest: &Path) -> io::Result<()> {
+    for entry in WalkDir::new(src) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(src).map_err(io::Error::other)?;
+        let target = dest.join(relative);
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if file_type.is_symlink() {
+            create_symlink(&fs::read_link(entry.path())?, &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// absolute form of a path without resolving a final symlink
+pub(crate) fn absolute_path(path: &Path) -> io::Result<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::canonicalize(parent)?,
+        _ => std::env::current_dir()?,
+    };
+    match path.file_name() {
+        Some(name) => Ok(parent.join(name)),
+        None => fs::canonicalize(path),
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
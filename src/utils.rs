@@ -0,0 +1,327 @@
+// --------------------------------------------------------------------
+// utils
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// parse a human size string such as '512K', '100MB' or '1.5GiB' into bytes
+///
+/// Single letter suffixes (K, M, G, T) and IEC suffixes (KiB, MiB, ...)
+/// use binary multiples, while SI suffixes (KB, MB, ...) use decimal
+/// multiples. Suffixes are case-insensitive and a bare number or a 'B'
+/// suffix is taken as a count of bytes.
+///
+/// # Errors
+///
+/// This function will return an error if the number or suffix is invalid.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, suffix) = s.split_at(split);
+    let multiplier: u64 = match suffix.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KIB" => 1 << 10,
+        "M" | "MIB" => 1 << 20,
+        "G" | "GIB" => 1 << 30,
+        "T" | "TIB" => 1 << 40,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        _ => return Err(format!("invalid size suffix: '{s}'")),
+    };
+    if number.contains('.') {
+        let value: f64 = number.parse().map_err(|_| format!("invalid size: '{s}'"))?;
+        let bytes = (value * multiplier as f64).round();
+        if !bytes.is_finite() || bytes >= u64::MAX as f64 {
+            return Err(format!("size too large: '{s}'"));
+        }
+        return Ok(bytes as u64);
+    }
+    let value: u64 = number.parse().map_err(|_| format!("invalid size: '{s}'"))?;
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size too large: '{s}'"))
+}
+
+/// parse a human duration string such as '30s', '15m', '2h', '7d' or '1w' into seconds
+///
+/// A bare number is taken as a count of seconds.
+///
+/// # Errors
+///
+/// This function will return an error if the number or suffix is invalid.
+pub fn parse_duration(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, suffix) = s.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: '{s}'"))?;
+    let multiplier: u64 = match suffix.trim().to_ascii_lowercase().as_str() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid duration suffix: '{s}'")),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("duration too large: '{s}'"))
+}
+
+/// format a number of seconds as the shortest duration string which
+/// `parse_duration` reads back exactly, e.g. '30d' or '90m'
+pub fn format_duration(secs: u64) -> String {
+    match secs {
+        0 => "0s".to_string(),
+        secs if secs % 86400 == 0 => format!("{}d", secs / 86400),
+        secs if secs % 3600 == 0 => format!("{}h", secs / 3600),
+        secs if secs % 60 == 0 => format!("{}m", secs / 60),
+        secs => format!("{secs}s"),
+    }
+}
+
+/// the extended-length form of a path on Windows, e.g. `\\?\C:\dir` or
+/// `\\?\UNC\server\share\dir`, which file system calls accept beyond
+/// 260 characters
+///
+/// The path is made absolute first, resolving `.` and `..` since these
+/// are not interpreted in the extended-length form. Paths which already
+/// have that form, cannot be made absolute, and all paths on other
+/// platforms are returned unchanged.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        use std::ffi::OsString;
+        use std::path::{Component, PathBuf, Prefix};
+        let Ok(absolute) = std::path::absolute(path) else {
+            return Cow::Borrowed(path);
+        };
+        let Some(Component::Prefix(prefix)) = absolute.components().next() else {
+            return Cow::Borrowed(path);
+        };
+        let mut long = match prefix.kind() {
+            Prefix::Disk(_) => OsString::from(r"\\?\"),
+            Prefix::UNC(server, share) => {
+                let mut long = OsString::from(r"\\?\UNC\");
+                long.push(server);
+                long.push(r"\");
+                long.push(share);
+                let rest: PathBuf = absolute.components().skip(1).collect();
+                long.push(rest.as_os_str());
+                return Cow::Owned(long.into());
+            }
+            // already verbatim, or a device path
+            _ => return Cow::Borrowed(path),
+        };
+        long.push(absolute.as_os_str());
+        Cow::Owned(long.into())
+    }
+    #[cfg(not(windows))]
+    {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Units in which sizes are shown
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnits {
+    /// multiples of 1024: KiB, MiB, GiB, ...
+    #[default]
+    Binary,
+    /// multiples of 1000: KB, MB, GB, ...
+    Decimal,
+}
+
+impl fmt::Display for SizeUnits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeUnits::Binary => write!(f, "binary"),
+            SizeUnits::Decimal => write!(f, "decimal"),
+        }
+    }
+}
+
+impl FromStr for SizeUnits {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "binary" | "iec" => Ok(SizeUnits::Binary),
+            "decimal" | "si" => Ok(SizeUnits::Decimal),
+            _ => Err(format!(
+                "invalid size units: '{s}' (expected binary or decimal)"
+            )),
+        }
+    }
+}
+
+/// format a byte count for display using binary units, e.g. '1.50 KiB'
+pub fn format_size(bytes: u64) -> String {
+    format_size_in(bytes, SizeUnits::Binary)
+}
+
+/// format a byte count for display in the given units, e.g. '1.50 KiB'
+/// or '1.54 KB' for 1536 bytes
+pub fn format_size_in(bytes: u64, units: SizeUnits) -> String {
+    let (base, names) = match units {
+        SizeUnits::Binary => (1024, ["KiB", "MiB", "GiB", "TiB", "PiB"]),
+        SizeUnits::Decimal => (1000, ["KB", "MB", "GB", "TB", "PB"]),
+    };
+    if bytes < base {
+        return format!("{bytes} B");
+    }
+    let base = base as f64;
+    let mut size = bytes as f64 / base;
+    let mut unit = 0;
+    while size >= base && unit < names.len() - 1 {
+        size /= base;
+        unit += 1;
+    }
+    format!("{size:.2} {}", names[unit])
+}
+
+/// format a duration as a rough age in the largest whole unit, e.g. '3d'
+pub fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        secs if secs >= 86400 => format!("{}d", secs / 86400),
+        secs if secs >= 3600 => format!("{}h", secs / 3600),
+        secs if secs >= 60 => format!("{}m", secs / 60),
+        secs => format!("{secs}s"),
+    }
+}
+
+/// format a time as a UTC timestamp usable in file names, e.g.
+/// "20231016-134501"
+pub fn format_timestamp(time: SystemTime) -> String {
+    let (year, month, day, secs_of_day) = civil_time(time);
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// format a time as an RFC 3339 UTC timestamp, e.g. "2023-10-16T13:45:01Z"
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let (year, month, day, secs_of_day) = civil_time(time);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// UTC year, month, day and second of the day of a time, the epoch for
+/// times before it
+fn civil_time(time: SystemTime) -> (i64, i64, i64, u64) {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+    // civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, secs_of_day)
+}
+
+/// split a '!'-prefixed glob into its negation flag and the glob itself
+///
+/// A leading '!!' escapes a literal '!' at the start of a glob.
+pub fn split_negation(pattern: &str) -> (bool, &str) {
+    match pattern.strip_prefix('!') {
+        Some(rest) if rest.starts_with('!') => (false, rest),
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    }
+}
+
+/// split a command line into the program and its arguments, like a
+/// shell would but without expanding anything
+///
+/// Words are separated by whitespace; single quotes keep everything up
+/// to the closing quote, double quotes and backslashes escape the next
+/// character.
+///
+/// # Errors
+///
+/// This function will return an error for an unclosed quote or an empty
+/// command.
+pub fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some('\\') if c == '"' => word.extend(chars.next()),
+                        Some(other) => word.push(other),
+                        None => return Err(format!("unclosed quote in command: {command}")),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    if words.is_empty() {
+        return Err("empty command".to_string());
+    }
+    Ok(words)
+}
+
+/// resolve a user name or numeric uid to a uid
+///
+/// # Errors
+///
+/// This function will return an error if no such user exists.
+#[cfg(unix)]
+pub fn resolve_uid(user: &str) -> Result<u32, String> {
+    if let Ok(uid) = user.parse::<u32>() {
+        return Ok(uid);
+    }
+    let name = std::ffi::CString::new(user).map_err(|_| format!("invalid user name: '{user}'"))?;
+    // SAFETY: passwd is plain data which getpwnam_r fills in, pointing
+    // its strings into buffer, and neither outlives this function
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 16 * 1024];
+    let mut result = std::ptr::null_mut();
+    let rc = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if rc != 0 || result.is_null() {
+        return Err(format!("unknown user: '{user}'"));
+    }
+    Ok(passwd.pw_uid)
+}
@@ -0,0 +1,85 @@
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use flate2::read::GzDecoder;
+    use rclean::{CleanError, CleaningJob};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn make_job(path: &Path, archive: &Path, dry_run: bool) -> CleaningJob {
+        let mut job = CleaningJob::new(
+            path.display().to_string(),
+            vec!["**/build".to_string(), "**/*.log".to_string()],
+            dry_run,
+            true,
+            false,
+        );
+        job.archive_path = Some(archive.to_path_buf());
+        job
+    }
+
+    /// the names of the entries in a gzip tarball
+    fn archived_names(archive: &Path) -> Vec<PathBuf> {
+        let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(archive).unwrap()));
+        let mut names: Vec<PathBuf> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_archive_before_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive = out.path().join("clean.tar.gz");
+        fs::create_dir_all(dir.path().join("build/lib")).unwrap();
+        fs::write(dir.path().join("build/lib/x.o"), "obj").unwrap();
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+        fs::write(dir.path().join("keep.txt"), "txt").unwrap();
+
+        let mut job = make_job(dir.path(), &archive, false);
+        job.run().unwrap();
+        assert!(!dir.path().join("build").exists());
+        assert!(!dir.path().join("a.log").exists());
+        assert!(dir.path().join("keep.txt").exists());
+        assert_eq!(
+            archived_names(&archive),
+            ["a.log", "build", "build/lib", "build/lib/x.o"].map(PathBuf::from)
+        );
+        assert_eq!(
+            job.summary().archive_size,
+            Some(fs::metadata(&archive).unwrap().len())
+        );
+    }
+
+    #[test]
+    fn test_archive_dry_run_creates_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive = out.path().join("clean.tar.gz");
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &archive, true);
+        job.run().unwrap();
+        assert!(!archive.exists());
+        assert!(dir.path().join("a.log").exists());
+        assert_eq!(job.summary().archive_size, None);
+    }
+
+    #[test]
+    fn test_archive_refuses_existing_or_inside() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let archive = out.path().join("clean.tar.gz");
+        fs::write(&archive, "").unwrap();
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &archive, false);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+        let mut job = make_job(dir.path(), &dir.path().join("clean.tar.gz"), false);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+        assert!(dir.path().join("a.log").exists());
+    }
+}
@@ -0,0 +1,336 @@
+#[cfg(test)]
+mod tests {
+    use assert_cmd::Command;
+    use rclean::constants::{EXIT_FATAL, EXIT_NO_MATCH, EXIT_SUCCESS};
+    use std::fs;
+    use std::path::Path;
+
+    fn rclean(path: &Path) -> Command {
+        let mut cmd = Command::cargo_bin("rclean").unwrap();
+        cmd.arg("--path")
+            .arg(path)
+            .args(["--skip-confirmation", "--no-history"]);
+        cmd
+    }
+
+    #[test]
+    fn test_exit_success() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        rclean(dir.path())
+            .args(["-g", "**/*.log"])
+            .assert()
+            .code(EXIT_SUCCESS);
+        assert!(!dir.path().join("debug.log").exists());
+    }
+
+    #[test]
+    fn test_exit_no_match() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("notes.txt"), "notes").unwrap();
+        rclean(dir.path())
+            .args(["-g", "**/*.log"])
+            .assert()
+            .code(EXIT_NO_MATCH);
+    }
+
+    #[test]
+    fn test_exit_fatal() {
+        let dir = tempfile::tempdir().unwrap();
+        rclean(dir.path())
+            .args(["--regex", "("])
+            .assert()
+            .code(EXIT_FATAL);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_partial() {
+        use rclean::constants::EXIT_PARTIAL;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::write(locked.join("debug.log"), "log").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+        // root can remove files from read-only directories
+        let writable = fs::File::create(locked.join("probe")).is_ok();
+        let assert = rclean(dir.path()).args(["-g", "**/*.log"]).assert();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        if !writable {
+            assert.code(EXIT_PARTIAL);
+        }
+    }
+
+    #[test]
+    fn test_exit_partial_on_any_failure() {
+        use rclean::constants::EXIT_PARTIAL;
+
+        let dir = tempfile::tempdir().unwrap();
+        // truncating a directory fails even for root
+        fs::create_dir(dir.path().join("logs")).unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        rclean(dir.path())
+            .args(["-g", "**/*.log", "--truncate", "**/logs"])
+            .assert()
+            .code(EXIT_PARTIAL);
+        assert!(!dir.path().join("debug.log").exists());
+
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        rclean(dir.path())
+            .args([
+                "-g",
+                "**/*.log",
+                "--truncate",
+                "**/logs",
+                "--no-fail-on-errors",
+            ])
+            .assert()
+            .code(EXIT_SUCCESS);
+        assert!(!dir.path().join("debug.log").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_no_fail_on_errors() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::write(locked.join("debug.log"), "log").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+        let assert = rclean(dir.path())
+            .args(["-g", "**/*.log", "--no-fail-on-errors"])
+            .assert();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        assert.code(EXIT_SUCCESS);
+    }
+
+    #[test]
+    fn test_save_plan_then_apply_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan_dir = tempfile::tempdir().unwrap();
+        let plan = plan_dir.path().join("plan.json");
+        for name in ["a.log", "b.log"] {
+            fs::write(dir.path().join(name), "log").unwrap();
+        }
+        rclean(dir.path())
+            .args(["-g", "**/*.log", "--save-plan"])
+            .arg(&plan)
+            .assert()
+            .code(EXIT_SUCCESS);
+        assert!(dir.path().join("a.log").exists());
+
+        // changed after planning, so left alone without failing the run
+        fs::write(dir.path().join("b.log"), "changed").unwrap();
+        fs::write(dir.path().join("c.log"), "log").unwrap();
+        Command::cargo_bin("rclean")
+            .unwrap()
+            .current_dir(plan_dir.path())
+            .args(["--no-history", "--apply-plan"])
+            .arg(&plan)
+            .assert()
+            .code(EXIT_SUCCESS);
+        assert!(!dir.path().join("a.log").exists());
+        assert!(dir.path().join("b.log").exists());
+        assert!(dir.path().join("c.log").exists());
+
+        // nothing left in the plan to remove
+        Command::cargo_bin("rclean")
+            .unwrap()
+            .current_dir(plan_dir.path())
+            .args(["--no-history", "--apply-plan"])
+            .arg(&plan)
+            .assert()
+            .code(EXIT_NO_MATCH);
+    }
+
+    #[test]
+    fn test_log_file_records_removals() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_dir = tempfile::tempdir().unwrap();
+        let log_file = log_dir.path().join("rclean.log");
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        rclean(dir.path())
+            .args(["-g", "**/*.log", "--quiet", "--log-file"])
+            .arg(&log_file)
+            .assert()
+            .code(EXIT_SUCCESS);
+        let log = fs::read_to_string(&log_file).unwrap();
+        // recorded even with --quiet, with the item's size
+        assert!(log.contains("Deleted") && log.contains("debug.log") && log.contains("3 B"));
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        let output = rclean(dir.path())
+            .args(["-g", "**/*.log", "--ndjson"])
+            .output()
+            .unwrap();
+        // logs stay on stderr, every line of stdout is a JSON object
+        let events: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let kinds: Vec<&str> = events
+            .iter()
+            .map(|e| e["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, ["match", "deleted", "summary"]);
+    }
+
+    #[test]
+    fn test_size_units() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = |units: &str| {
+            fs::write(dir.path().join("debug.log"), vec![0; 1500]).unwrap();
+            let output = rclean(dir.path())
+                .args(["-g", "**/*.log", "--size-units", units])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        assert!(output("decimal").contains("Deleted 1 item(s) totalling 1.50 KB"));
+        assert!(output("binary").contains("Deleted 1 item(s) totalling 1.46 KiB"));
+    }
+
+    #[test]
+    fn test_log_level() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        let output = |level: &str| {
+            let output = rclean(dir.path())
+                .args(["-g", "**/*.log", "--dry-run", "--log-level", level])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        assert!(output("info").contains("Would delete"));
+        assert!(!output("warn").contains("Would delete"));
+        assert!(output("warn").contains("Dry-run: 1 item(s) totalling 3 B"));
+        assert!(!output("error").contains("Dry-run"));
+    }
+
+    #[test]
+    fn test_verbose_shows_per_item_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        let output = |args: &[&str]| {
+            let output = rclean(dir.path())
+                .args(["-g", "**/*.log", "--dry-run"])
+                .args(args)
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        // only the summary by default
+        let quiet = output(&[]);
+        assert!(!quiet.contains("Matched:") && !quiet.contains("Would delete:"));
+        assert!(quiet.contains("would be removed"));
+        assert!(output(&["-v"]).contains("Would delete:"));
+        assert!(!output(&["-v"]).contains("[DEBUG]"));
+    }
+
+    #[test]
+    fn test_select_needs_terminal() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        Command::cargo_bin("rclean")
+            .unwrap()
+            .arg("--path")
+            .arg(dir.path())
+            .args(["-g", "**/*.log", "--select", "--no-history"])
+            .assert()
+            .code(EXIT_FATAL);
+        assert!(dir.path().join("debug.log").exists());
+    }
+
+    #[test]
+    fn test_dedupe_alias() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.bin"), "same").unwrap();
+        fs::write(dir.path().join("b.bin"), "same").unwrap();
+        rclean(dir.path())
+            .args(["--dedupe", "--dry-run"])
+            .assert()
+            .code(EXIT_SUCCESS);
+        assert!(dir.path().join("a.bin").exists());
+        assert!(dir.path().join("b.bin").exists());
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+        rclean(dir.path())
+            .args(["-g", "*.LOG"])
+            .assert()
+            .code(EXIT_NO_MATCH);
+        assert!(dir.path().join("a.log").exists());
+        rclean(dir.path())
+            .args(["-g", "*.LOG", "--case-insensitive"])
+            .assert()
+            .code(EXIT_SUCCESS);
+        assert!(!dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_print0() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a b.log"), "log").unwrap();
+        fs::write(dir.path().join("c.log"), "log").unwrap();
+        let output = Command::cargo_bin("rclean")
+            .unwrap()
+            .arg("--path")
+            .arg(dir.path())
+            .args(["-g", "**/*.log", "--print0", "--no-history"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(EXIT_SUCCESS));
+        let mut paths: Vec<&[u8]> = output.stdout.split(|&b| b == 0).collect();
+        // every path is terminated, so the last field is empty
+        assert_eq!(paths.pop(), Some(&b""[..]));
+        paths.sort();
+        let expected = [dir.path().join("a b.log"), dir.path().join("c.log")];
+        let expected: Vec<String> = expected.iter().map(|p| p.display().to_string()).collect();
+        let expected: Vec<&[u8]> = expected.iter().map(|p| p.as_bytes()).collect();
+        assert_eq!(paths, expected);
+        assert!(dir.path().join("a b.log").exists());
+    }
+
+    #[test]
+    fn test_env_options() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+        fs::write(dir.path().join("b.tmp"), "tmp").unwrap();
+        fs::write(dir.path().join("keep.log"), "log").unwrap();
+        rclean(dir.path())
+            .env("RCLEAN_PATTERNS", "**/*.log,**/*.tmp")
+            .env("RCLEAN_EXCLUDE", "**/keep.log")
+            .assert()
+            .code(EXIT_SUCCESS);
+        assert!(!dir.path().join("a.log").exists());
+        assert!(!dir.path().join("b.tmp").exists());
+        assert!(dir.path().join("keep.log").exists());
+
+        // command line options take precedence
+        fs::write(dir.path().join("c.tmp"), "tmp").unwrap();
+        rclean(dir.path())
+            .env("RCLEAN_PATTERNS", "**/*.tmp")
+            .env("RCLEAN_PATH", "/nonexistent")
+            .args(["-g", "**/*.log"])
+            .assert()
+            .code(EXIT_SUCCESS);
+        assert!(dir.path().join("c.tmp").exists());
+        assert!(!dir.path().join("keep.log").exists());
+
+        rclean(dir.path())
+            .env("RCLEAN_OLDER_THAN", "soon")
+            .assert()
+            .code(EXIT_FATAL);
+    }
+}
@@ -0,0 +1,391 @@
+#[cfg(test)]
+mod tests {
+    use rclean::config::{
+        discover_config, load_config, load_merged_config, merge, CleanConfig, ConfigFormat,
+        EnvConfig,
+    };
+    use rclean::{
+        AgeField, CleanError, CleaningJob, ConditionalPattern, EntryType, ErrorPolicy,
+        HiddenPolicy, KeepPolicy, OutputFormat, RetentionPattern, SizeUnits, SymlinkBehavior,
+    };
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_config_load_toml() {
+        let job = load_config(Path::new("tests/.rclean.toml")).unwrap();
+        assert_eq!(job.path, ".");
+        assert!(job.patterns.contains(&"**/*.pyc".to_string()));
+        assert!(!job.dry_run);
+    }
+
+    #[test]
+    fn test_config_yaml_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = CleaningJob::default();
+        job.patterns = vec!["**/*.log".to_string()];
+        job.min_size = Some(1024);
+
+        let yaml = ConfigFormat::Yaml.serialize(&job).unwrap();
+        let path = dir.path().join(".rclean.yaml");
+        fs::write(&path, yaml).unwrap();
+
+        let loaded = load_config(&path).unwrap();
+        assert_eq!(loaded.patterns, job.patterns);
+        assert_eq!(loaded.min_size, Some(1024));
+        assert_eq!(loaded.dry_run, job.dry_run);
+    }
+
+    #[test]
+    fn test_config_toml_depth_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = CleaningJob::default();
+        job.max_depth = Some(3);
+        job.min_depth = Some(1);
+
+        let path = dir.path().join(".rclean.toml");
+        fs::write(&path, ConfigFormat::Toml.serialize(&job).unwrap()).unwrap();
+
+        let loaded = load_config(&path).unwrap();
+        assert_eq!(loaded.max_depth, Some(3));
+        assert_eq!(loaded.min_depth, Some(1));
+    }
+
+    #[test]
+    fn test_config_toml_conditional_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".rclean.toml");
+        fs::write(
+            &path,
+            r#"
+path = "."
+patterns = []
+dry_run = true
+skip_confirmation = false
+include_symlinks = false
+conditional_patterns = [
+    { pattern = "**/target", requires_sibling = "Cargo.toml" },
+    { pattern = "**/node_modules", requires_sibling = "package.json" },
+]
+"#,
+        )
+        .unwrap();
+
+        let job = load_config(&path).unwrap();
+        assert_eq!(job.conditional_patterns.len(), 2);
+        assert_eq!(job.conditional_patterns[1].requires_sibling, "package.json");
+    }
+
+    #[test]
+    fn test_config_toml_retention_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".rclean.toml");
+        fs::write(
+            &path,
+            r#"
+path = "."
+patterns = []
+dry_run = true
+skip_confirmation = false
+include_symlinks = false
+keep_newest = 1
+retention_patterns = [{ pattern = "**/*.log", keep = 3 }]
+"#,
+        )
+        .unwrap();
+
+        let job = load_config(&path).unwrap();
+        assert_eq!(job.keep_newest, Some(1));
+        assert_eq!(job.retention_patterns[0].keep, 3);
+    }
+
+    #[test]
+    fn test_config_yml_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".rclean.yml");
+        fs::write(
+            &path,
+            "path: .\npatterns: ['**/*.tmp']\ndry_run: true\nskip_confirmation: false\ninclude_symlinks: false\n",
+        )
+        .unwrap();
+
+        let job = load_config(&path).unwrap();
+        assert_eq!(job.patterns, vec!["**/*.tmp".to_string()]);
+    }
+
+    #[test]
+    fn test_config_malformed_yaml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".rclean.yaml");
+        fs::write(&path, "path: [unclosed\n").unwrap();
+        assert!(matches!(
+            load_config(&path),
+            Err(CleanError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_validate_reports_all_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".rclean.toml");
+        fs::write(
+            &path,
+            r#"
+path = "does/not/exist"
+patterns = ["**/[unclosed", "!**/keep.log"]
+dry_run = true
+skip_confirmation = false
+include_symlinks = false
+older_than_secs = 3600
+newer_than_secs = 60
+"#,
+        )
+        .unwrap();
+
+        let job = load_config(&path).unwrap();
+        match job.validate() {
+            Err(CleanError::ConfigError(msg)) => {
+                assert!(msg.contains("invalid glob pattern"), "{msg}");
+                assert!(msg.contains("not an existing directory"), "{msg}");
+                assert!(msg.contains("older than 3600s"), "{msg}");
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        let mut job = CleaningJob::new(
+            dir.path().display().to_string(),
+            vec!["!**/keep.log".to_string()],
+            true,
+            false,
+            false,
+        );
+        assert!(
+            matches!(job.validate(), Err(CleanError::ConfigError(msg)) if msg == "no patterns to match")
+        );
+        job.patterns.push("**/*.log".to_string());
+        assert!(job.validate().is_ok());
+    }
+
+    /// a job with most options changed from their defaults
+    fn make_full_job() -> CleaningJob {
+        let mut job = CleaningJob::default();
+        job.paths = vec!["src".to_string()];
+        job.patterns = vec!["**/*.log".to_string(), "!**/keep.log".to_string()];
+        job.dry_run = true;
+        job.skip_confirmation = true;
+        job.include_symlinks = true;
+        job.trash_mode = true;
+        job.trash_fallback_delete = true;
+        job.skip_in_use = true;
+        job.report_free_space = true;
+        job.min_free_after = Some(10 << 30);
+        job.csv_file = Some(PathBuf::from("report.csv"));
+        job.force = true;
+        job.min_size = Some(1);
+        job.max_size = Some(1 << 30);
+        job.max_deletions = Some(100);
+        job.limit_ok = true;
+        job.error_policy = ErrorPolicy::AbortAfterN(3);
+        job.jobs = Some(4);
+        job.parallel_delete = true;
+        job.older_than_secs = Some(30 * 86400);
+        job.newer_than_secs = Some(90 * 60);
+        job.protect_newer_than_secs = Some(45);
+        job.age_field = AgeField::Accessed;
+        job.size_units = SizeUnits::Decimal;
+        job.hidden_policy = HiddenPolicy::Exclude;
+        job.regex_patterns = vec![r"\.tmp$".to_string()];
+        job.match_types = vec![EntryType::File];
+        job.output_format = OutputFormat::Json;
+        job.backup_dir = Some(PathBuf::from("/tmp/backup"));
+        job.max_depth = Some(3);
+        job.conditional_patterns = vec!["**/target=Cargo.toml"
+            .parse::<ConditionalPattern>()
+            .unwrap()];
+        job.prune_patterns = vec!["**/node_modules".to_string()];
+        job.no_rcleanignore = true;
+        job.confirm_dirs = true;
+        job.keep = KeepPolicy::Shortest;
+        job.keep_newest = Some(2);
+        job.retention_patterns = vec!["**/*.gz=3".parse::<RetentionPattern>().unwrap()];
+        job.symlink_behavior = SymlinkBehavior::FollowAndDelete;
+        job.presets = vec!["python".to_string()];
+        job.pre_delete_hook = Some("test -w {}".to_string());
+        job.exec = Some("echo {}".to_string());
+        job
+    }
+
+    #[test]
+    fn test_config_full_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let job = make_full_job();
+        for format in [ConfigFormat::Toml, ConfigFormat::Yaml] {
+            let path = dir.path().join(format.filename());
+            fs::write(&path, format.serialize(&job).unwrap()).unwrap();
+            let loaded = load_config(&path).unwrap();
+            assert_eq!(
+                CleanConfig::from(&loaded),
+                CleanConfig::from(&job),
+                "{format}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_config_durations_as_strings() {
+        let toml = ConfigFormat::Toml.serialize(&make_full_job()).unwrap();
+        assert!(toml.contains("older_than = \"30d\""));
+        assert!(toml.contains("newer_than = \"90m\""));
+        assert!(toml.contains("protect_newer_than = \"45s\""));
+        assert!(!toml.contains("_secs"));
+
+        // plain seconds under the earlier names are still read
+        let job = ConfigFormat::Toml
+            .deserialize(
+                r#"
+path = "."
+patterns = ["**/*.log"]
+dry_run = true
+skip_confirmation = false
+include_symlinks = false
+older_than_secs = 3600
+newer_than = "2w"
+"#,
+            )
+            .unwrap();
+        assert_eq!(job.older_than_secs, Some(3600));
+        assert_eq!(job.newer_than_secs, Some(14 * 86400));
+        assert!(ConfigFormat::Yaml
+            .deserialize("path: .\npatterns: []\ndry_run: true\nskip_confirmation: false\ninclude_symlinks: false\nolder_than: 3y\n")
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_merge_global_and_local() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join("config.toml");
+        let local = dir.path().join(".rclean.yaml");
+        fs::write(
+            &global,
+            r#"
+path = "."
+patterns = ["**/__pycache__", "!**/vendor/**"]
+dry_run = true
+skip_confirmation = false
+include_symlinks = false
+quiet = true
+min_size = 1024
+"#,
+        )
+        .unwrap();
+        fs::write(
+            &local,
+            "patterns: ['**/*.log', '**/__pycache__']
+dry_run: false
+min_size: 2048
+",
+        )
+        .unwrap();
+
+        let job = load_merged_config(&global, &local).unwrap();
+        assert_eq!(
+            job.patterns,
+            vec!["**/__pycache__", "!**/vendor/**", "**/*.log"]
+        );
+        // set locally: overridden, set globally only: kept
+        assert!(!job.dry_run);
+        assert_eq!(job.min_size, Some(2048));
+        assert!(job.quiet);
+    }
+
+    #[test]
+    fn test_config_merge_values() {
+        let mut base = serde_json::json!({"patterns": ["a"], "jobs": 2, "dry_run": true});
+        merge(
+            &mut base,
+            serde_json::json!({"patterns": ["b", "a"], "jobs": 4}),
+        );
+        assert_eq!(
+            base,
+            serde_json::json!({"patterns": ["a", "b"], "jobs": 4, "dry_run": true})
+        );
+    }
+
+    #[test]
+    fn test_config_discover_prefers_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(discover_config(dir.path()), None);
+
+        fs::write(dir.path().join(".rclean.yml"), "").unwrap();
+        assert_eq!(
+            discover_config(dir.path()),
+            Some(dir.path().join(".rclean.yml"))
+        );
+
+        fs::write(dir.path().join(".rclean.toml"), "").unwrap();
+        assert_eq!(
+            discover_config(dir.path()),
+            Some(dir.path().join(".rclean.toml"))
+        );
+    }
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a/.rclean.yml")).unwrap(),
+            ConfigFormat::Yaml
+        );
+        assert!(ConfigFormat::from_path(Path::new(".rclean.json")).is_err());
+    }
+
+    fn lookup<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_env_config() {
+        let env = EnvConfig::from_lookup(lookup(&[
+            ("RCLEAN_PATH", "/srv/app"),
+            ("RCLEAN_PATTERNS", "**/*.log, **/tmp"),
+            ("RCLEAN_EXCLUDE", "**/keep.log"),
+            ("RCLEAN_DRY_RUN", "true"),
+            ("RCLEAN_OLDER_THAN", "7d"),
+            ("RCLEAN_MIN_SIZE", "1K"),
+            ("RCLEAN_TRASH", ""),
+        ]))
+        .unwrap();
+        assert_eq!(env.older_than_secs, Some(7 * 86400));
+        assert_eq!(env.trash_mode, None);
+
+        let mut job = CleaningJob::new(".".to_string(), vec![], false, false, false);
+        env.apply(&mut job);
+        assert_eq!(job.path, "/srv/app");
+        assert_eq!(job.patterns, ["**/*.log", "**/tmp", "!**/keep.log"]);
+        assert!(job.dry_run);
+        assert_eq!(job.min_size, Some(1024));
+
+        let config = env.to_config();
+        assert_eq!(config.path, "/srv/app");
+        assert_eq!(config.older_than, Some(7 * 86400));
+        assert_eq!(config.min_size, Some(1024));
+        assert!(!config.trash_mode);
+    }
+
+    #[test]
+    fn test_env_config_rejects_invalid_values() {
+        for vars in [
+            [("RCLEAN_OLDER_THAN", "soon")],
+            [("RCLEAN_DRY_RUN", "maybe")],
+            [("RCLEAN_MAX_SIZE", "big")],
+        ] {
+            match EnvConfig::from_lookup(lookup(&vars)) {
+                Err(CleanError::ConfigError(msg)) => assert!(msg.starts_with(vars[0].0)),
+                other => panic!("expected a config error, got {other:?}"),
+            }
+        }
+    }
+}
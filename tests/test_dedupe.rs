@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+    use rclean::dedupe::{find_duplicates, KeepPolicy};
+    use std::fs;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_find_duplicates_groups_identical_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = ["a.whl", "b/a.whl", "c.whl", "d.whl", "empty1", "empty2"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::write(&paths[0], "wheel").unwrap();
+        fs::write(&paths[1], "wheel").unwrap();
+        // same size, different contents
+        fs::write(&paths[2], "WHEEL").unwrap();
+        fs::write(&paths[3], "other contents").unwrap();
+        fs::write(&paths[4], "").unwrap();
+        fs::write(&paths[5], "").unwrap();
+
+        let groups = find_duplicates(paths.clone(), u64::MAX, KeepPolicy::Shortest);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kept, paths[0]);
+        assert_eq!(groups[0].removed, vec![paths[1].clone()]);
+        assert_eq!(groups[0].size, 5);
+    }
+
+    #[test]
+    fn test_find_duplicates_keeps_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("zz/old.tar.gz");
+        let new = dir.path().join("new.tar.gz");
+        fs::create_dir(dir.path().join("zz")).unwrap();
+        fs::write(&old, "archive").unwrap();
+        fs::write(&new, "archive").unwrap();
+        let file = fs::File::options().write(true).open(&old).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(3600))
+            .unwrap();
+
+        let groups = find_duplicates(vec![new.clone(), old.clone()], u64::MAX, KeepPolicy::Oldest);
+        assert_eq!(groups[0].kept, old);
+        assert_eq!(groups[0].removed, vec![new]);
+    }
+
+    #[test]
+    fn test_find_duplicates_max_hash_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.bin");
+        let b = dir.path().join("b.bin");
+        fs::write(&a, "0123456789").unwrap();
+        fs::write(&b, "0123456789").unwrap();
+
+        assert!(find_duplicates(vec![a.clone(), b.clone()], 9, KeepPolicy::Oldest).is_empty());
+        assert_eq!(find_duplicates(vec![a, b], 10, KeepPolicy::Oldest).len(), 1);
+    }
+
+    #[test]
+    fn test_keep_policy_from_str() {
+        assert_eq!("oldest".parse(), Ok(KeepPolicy::Oldest));
+        assert_eq!("shortest-path".parse(), Ok(KeepPolicy::Shortest));
+        assert!("newest".parse::<KeepPolicy>().is_err());
+    }
+}
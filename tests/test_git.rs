@@ -0,0 +1,70 @@
+#[cfg(all(test, feature = "git"))]
+mod tests {
+    use rclean::{CleanError, CleaningJob};
+    use std::fs;
+    use std::path::Path;
+
+    fn make_job(path: &Path, patterns: &[&str]) -> CleaningJob {
+        let mut job = CleaningJob::new(
+            path.display().to_string(),
+            patterns.iter().map(|p| p.to_string()).collect(),
+            false,
+            true,
+            false,
+        );
+        job.untracked_only = true;
+        job
+    }
+
+    /// initialise a repository and commit the given files
+    fn commit_files(dir: &Path, files: &[&str]) {
+        let repo = git2::Repository::init(dir).unwrap();
+        let mut index = repo.index().unwrap();
+        for file in files {
+            index.add_path(Path::new(file)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("rclean", "rclean@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_untracked_only_keeps_tracked_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("fixtures/logs")).unwrap();
+        fs::write(dir.path().join("fixtures/logs/sample.log"), "tracked").unwrap();
+        fs::write(dir.path().join("fixtures/logs/run.log"), "untracked").unwrap();
+        fs::write(dir.path().join("debug.log"), "untracked").unwrap();
+        commit_files(dir.path(), &["fixtures/logs/sample.log"]);
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/logs"]);
+        job.run().unwrap();
+        assert!(dir.path().join("fixtures/logs/sample.log").exists());
+        assert!(!dir.path().join("fixtures/logs/run.log").exists());
+        assert!(!dir.path().join("debug.log").exists());
+    }
+
+    #[test]
+    fn test_untracked_only_outside_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"]);
+        job.run().unwrap();
+        assert!(!dir.path().join("debug.log").exists());
+    }
+
+    #[test]
+    fn test_untracked_only_broken_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".git/index"), "not an index").unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"]);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+        assert!(dir.path().join("debug.log").exists());
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use rclean::history::{list_history, load_history, restore_from_manifest};
+    use rclean::{CleaningJob, Disposition};
+    use std::fs;
+    use std::path::Path;
+
+    fn job(dir: &Path, history: &Path) -> CleaningJob {
+        let mut job = CleaningJob::new(
+            dir.display().to_string(),
+            vec!["**/*.log".to_string()],
+            false,
+            true,
+            false,
+        );
+        job.history_dir = Some(history.to_path_buf());
+        job
+    }
+
+    #[test]
+    fn test_undo_quarantine() {
+        let dir = tempfile::tempdir().unwrap();
+        let quarantine = tempfile::tempdir().unwrap();
+        let history = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("logs")).unwrap();
+        fs::write(dir.path().join("logs/debug.log"), "log").unwrap();
+
+        let mut job = job(dir.path(), history.path());
+        job.quarantine_dir = Some(quarantine.path().to_path_buf());
+        job.run().unwrap();
+        assert!(!dir.path().join("logs/debug.log").exists());
+
+        let runs = list_history(history.path());
+        assert_eq!(runs.len(), 1);
+        let run = load_history(&runs[0]).unwrap();
+        assert_eq!(run.entries.len(), 1);
+        assert_eq!(run.entries[0].disposition, Disposition::Quarantined);
+        assert_eq!(run.entries[0].size, 3);
+        assert!(run.entries[0].original.is_absolute());
+
+        let report = restore_from_manifest(&runs[0]).unwrap();
+        assert_eq!(report.restored.len(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("logs/debug.log")).unwrap(),
+            "log"
+        );
+    }
+
+    #[test]
+    fn test_undo_skips_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let quarantine = tempfile::tempdir().unwrap();
+        let history = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "old").unwrap();
+
+        let mut job = job(dir.path(), history.path());
+        job.quarantine_dir = Some(quarantine.path().to_path_buf());
+        job.run().unwrap();
+        fs::write(dir.path().join("debug.log"), "new").unwrap();
+
+        let report = restore_from_manifest(&list_history(history.path())[0]).unwrap();
+        assert!(report.restored.is_empty());
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("debug.log")).unwrap(),
+            "new"
+        );
+    }
+
+    #[test]
+    fn test_deletions_recorded_as_unrestorable() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+
+        job(dir.path(), history.path()).run().unwrap();
+        let runs = list_history(history.path());
+        let run = load_history(&runs[0]).unwrap();
+        assert_eq!(run.entries[0].disposition, Disposition::Deleted);
+        assert!(!run.entries[0].is_restorable());
+
+        let report = restore_from_manifest(&runs[0]).unwrap();
+        assert_eq!(report.failed.len(), 1);
+    }
+
+    #[test]
+    fn test_no_history_without_removals() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+
+        let mut job = job(dir.path(), history.path());
+        job.dry_run = true;
+        job.run().unwrap();
+        assert!(list_history(history.path()).is_empty());
+    }
+}
@@ -0,0 +1,2634 @@
+#[cfg(test)]
+mod tests {
+    use rclean::{
+        AgeField, Answer, CleanError, CleanEvent, CleaningJob, ConditionalPattern, Disposition,
+        EntryType, ErrorPolicy, HiddenPolicy, OutputFormat, Prompter, SymlinkBehavior,
+        TargetPrompt,
+    };
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    fn make_job(path: &Path, patterns: &[&str], dry_run: bool) -> CleaningJob {
+        CleaningJob::new(
+            path.display().to_string(),
+            patterns.iter().map(|p| p.to_string()).collect(),
+            dry_run,
+            true,
+            false,
+        )
+    }
+
+    fn set_age(path: &Path, secs: u64) {
+        let mtime = SystemTime::now() - Duration::from_secs(secs);
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(mtime).unwrap();
+    }
+
+    fn set_access_age(path: &Path, secs: u64) {
+        let atime = SystemTime::now() - Duration::from_secs(secs);
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_times(fs::FileTimes::new().set_accessed(atime))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_job_removes_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("__pycache__")).unwrap();
+        fs::write(dir.path().join("__pycache__/mod.pyc"), "x").unwrap();
+        fs::write(dir.path().join("keep.py"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/__pycache__"], false);
+        job.run().unwrap();
+        assert!(!dir.path().join("__pycache__").exists());
+        assert!(dir.path().join("keep.py").exists());
+        assert!(job.failed_deletions().is_empty());
+    }
+
+    #[test]
+    fn test_job_dry_run_keeps_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.run().unwrap();
+        assert!(dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_job_dry_run_trash_keeps_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.trash_mode = true;
+        job.run().unwrap();
+        assert!(dir.path().join("a.log").exists());
+        assert!(job.failed_deletions().is_empty());
+    }
+
+    #[test]
+    fn test_job_min_size_skips_small_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("small.log"), "x").unwrap();
+        fs::write(dir.path().join("large.log"), vec![b'x'; 2048]).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.min_size = Some(1024);
+        job.run().unwrap();
+        assert!(dir.path().join("small.log").exists());
+        assert!(!dir.path().join("large.log").exists());
+    }
+
+    #[test]
+    fn test_job_min_dir_size_only_applies_to_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("small/node_modules")).unwrap();
+        fs::create_dir_all(dir.path().join("large/node_modules")).unwrap();
+        fs::write(dir.path().join("small/node_modules/a.js"), "x").unwrap();
+        fs::write(dir.path().join("large/node_modules/a.js"), vec![b'x'; 4096]).unwrap();
+        fs::write(dir.path().join("small.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/node_modules", "**/*.log"], false);
+        job.min_dir_size = Some(1024);
+        job.run().unwrap();
+        assert!(dir.path().join("small/node_modules").exists());
+        assert!(!dir.path().join("large/node_modules").exists());
+        assert!(!dir.path().join("small.log").exists());
+        assert_eq!(job.summary().count, 2);
+        assert_eq!(job.stats()["**/node_modules"], (1, 4096));
+    }
+
+    #[test]
+    fn test_job_max_size_skips_large_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("small.log"), "x").unwrap();
+        fs::write(dir.path().join("large.log"), vec![b'x'; 2048]).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.max_size = Some(1024);
+        job.run().unwrap();
+        assert!(!dir.path().join("small.log").exists());
+        assert!(dir.path().join("large.log").exists());
+    }
+
+    #[test]
+    fn test_job_max_size_uses_directory_size() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("cache")).unwrap();
+        fs::write(dir.path().join("cache/blob"), vec![b'x'; 2048]).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/cache"], false);
+        job.max_size = Some(1024);
+        job.run().unwrap();
+        assert!(dir.path().join("cache/blob").exists());
+    }
+
+    #[test]
+    fn test_job_rejects_inverted_size_range() {
+        let mut job = make_job(Path::new("."), &["**/*.log"], true);
+        job.min_size = Some(2048);
+        job.max_size = Some(1024);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_parallel_removes_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..20 {
+            let sub = dir.path().join(format!("pkg{i}/__pycache__"));
+            fs::create_dir_all(&sub).unwrap();
+            fs::write(sub.join("mod.pyc"), "x").unwrap();
+            fs::write(dir.path().join(format!("pkg{i}/mod.py")), "x").unwrap();
+        }
+
+        let mut job = make_job(dir.path(), &["**/__pycache__", "**/*.pyc"], false);
+        job.jobs = Some(4);
+        job.run().unwrap();
+        for i in 0..20 {
+            assert!(!dir.path().join(format!("pkg{i}/__pycache__")).exists());
+            assert!(dir.path().join(format!("pkg{i}/mod.py")).exists());
+        }
+        assert!(job.failed_deletions().is_empty());
+    }
+
+    #[test]
+    fn test_job_newer_than_skips_old_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("old.log"), "x").unwrap();
+        fs::write(dir.path().join("new.log"), "x").unwrap();
+        set_age(&dir.path().join("old.log"), 3 * 86400);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.newer_than_secs = Some(86400);
+        job.run().unwrap();
+        assert!(dir.path().join("old.log").exists());
+        assert!(!dir.path().join("new.log").exists());
+    }
+
+    #[test]
+    fn test_job_age_window() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, days) in [("a.log", 0), ("b.log", 2), ("c.log", 5)] {
+            fs::write(dir.path().join(name), "x").unwrap();
+            set_age(&dir.path().join(name), days * 86400);
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.older_than_secs = Some(86400);
+        job.newer_than_secs = Some(3 * 86400);
+        job.run().unwrap();
+        assert!(dir.path().join("a.log").exists());
+        assert!(!dir.path().join("b.log").exists());
+        assert!(dir.path().join("c.log").exists());
+    }
+
+    #[test]
+    fn test_job_rejects_impossible_age_window() {
+        let mut job = make_job(Path::new("."), &["**/*.log"], true);
+        job.older_than_secs = Some(3 * 86400);
+        job.newer_than_secs = Some(86400);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_regex_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("app.log.1"), "x").unwrap();
+        fs::write(dir.path().join("app.tmp.22"), "x").unwrap();
+        fs::write(dir.path().join("app.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &[], false);
+        job.regex_patterns = vec![r"\.(log|tmp)\.\d+$".to_string()];
+        job.run().unwrap();
+        assert!(!dir.path().join("app.log.1").exists());
+        assert!(!dir.path().join("app.tmp.22").exists());
+        assert!(dir.path().join("app.log").exists());
+        assert_eq!(job.stats()[r"\.(log|tmp)\.\d+$"], (2, 2));
+    }
+
+    #[test]
+    fn test_job_stats_prefer_globs_over_regexes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+        fs::write(dir.path().join("b.tmp"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.regex_patterns = vec![r"\.(log|tmp)$".to_string()];
+        job.run().unwrap();
+        assert_eq!(job.stats()["**/*.log"], (1, 1));
+        assert_eq!(job.stats()[r"\.(log|tmp)$"], (1, 1));
+    }
+
+    #[test]
+    fn test_job_stats_first_matching_glob_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "x").unwrap();
+        fs::write(dir.path().join("debug.txt"), "xy").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/debug.*"], true);
+        job.run().unwrap();
+        assert_eq!(job.stats()["**/*.log"], (1, 1));
+        assert_eq!(job.stats()["**/debug.*"], (1, 2));
+
+        let mut job = make_job(dir.path(), &["**/debug.*", "**/*.log"], true);
+        job.run().unwrap();
+        assert_eq!(job.stats()["**/debug.*"], (2, 3));
+        assert!(!job.stats().contains_key("**/*.log"));
+    }
+
+    #[test]
+    fn test_job_nested_targets_counted_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("pkg/__pycache__")).unwrap();
+        fs::write(dir.path().join("pkg/__pycache__/a.pyc"), "abc").unwrap();
+        fs::write(dir.path().join("pkg/__pycache__/b.pyc"), "de").unwrap();
+        fs::write(dir.path().join("pkg/c.pyc"), "f").unwrap();
+        let patterns = ["**/__pycache__", "**/*.pyc"];
+
+        let mut job = make_job(dir.path(), &patterns, true);
+        job.run().unwrap();
+        let summary = job.summary();
+        assert_eq!(summary.count, 2);
+        assert_eq!(job.stats()["**/__pycache__"].0, 1);
+        assert_eq!(job.stats()["**/*.pyc"], (1, 1));
+        assert_eq!(job.matched().len(), 2);
+
+        let mut job = make_job(dir.path(), &patterns, false);
+        job.skip_confirmation = false;
+        job.interactive = true;
+        job.run_with_prompt(|_| Answer::All).unwrap();
+        let summary = job.summary();
+        assert_eq!(summary.count, 2);
+        assert_eq!(
+            summary.size,
+            job.matched().iter().map(|i| i.size).sum::<u64>()
+        );
+        assert!(summary.failed.is_empty());
+        assert_eq!(fs::read_dir(dir.path().join("pkg")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_job_rejects_invalid_regex() {
+        let mut job = make_job(Path::new("."), &[], true);
+        job.regex_patterns = vec!["(unclosed".to_string()];
+        assert!(matches!(job.run(), Err(CleanError::RegexError(_))));
+    }
+
+    #[test]
+    fn test_job_newer_than_boundary_is_inclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("edge.log"), "x").unwrap();
+        set_age(&dir.path().join("edge.log"), 3600);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.newer_than_secs = Some(3600);
+        job.run().unwrap();
+        assert!(!dir.path().join("edge.log").exists());
+    }
+
+    #[test]
+    fn test_job_older_than_boundary_is_inclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("edge.log"), "x").unwrap();
+        set_age(&dir.path().join("edge.log"), 3600);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.older_than_secs = Some(3600);
+        job.run().unwrap();
+        assert!(!dir.path().join("edge.log").exists());
+    }
+
+    #[test]
+    fn test_job_age_window_of_one_instant() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("edge.log"), "x").unwrap();
+        fs::write(dir.path().join("new.log"), "x").unwrap();
+        set_age(&dir.path().join("edge.log"), 3600);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.older_than_secs = Some(3600);
+        job.newer_than_secs = Some(3600);
+        job.run().unwrap();
+        assert!(!dir.path().join("edge.log").exists());
+        assert!(dir.path().join("new.log").exists());
+    }
+
+    #[test]
+    fn test_job_age_field_accessed() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("stale.log"), "x").unwrap();
+        fs::write(dir.path().join("fresh.log"), "x").unwrap();
+        set_access_age(&dir.path().join("stale.log"), 3 * 86400);
+        set_age(&dir.path().join("fresh.log"), 3 * 86400);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.older_than_secs = Some(86400);
+        job.age_field = AgeField::Accessed;
+        job.run().unwrap();
+        assert!(!dir.path().join("stale.log").exists());
+        assert!(dir.path().join("fresh.log").exists());
+    }
+
+    #[test]
+    fn test_age_field_created_falls_back_to_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+        let metadata = fs::metadata(dir.path().join("a.log")).unwrap();
+
+        let timestamp = AgeField::Created.timestamp(&metadata);
+        match metadata.created() {
+            Ok(created) => assert_eq!(timestamp, Some(created)),
+            Err(_) => assert_eq!(timestamp, metadata.modified().ok()),
+        }
+    }
+
+    #[test]
+    fn test_age_field_from_str() {
+        assert_eq!("accessed".parse(), Ok(AgeField::Accessed));
+        assert_eq!("Created".parse(), Ok(AgeField::Created));
+        assert!("changed".parse::<AgeField>().is_err());
+    }
+
+    #[test]
+    fn test_job_empty_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/stamp"), "").unwrap();
+        fs::write(dir.path().join("empty.log"), "").unwrap();
+        fs::write(dir.path().join("full.txt"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.remove_empty_files = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("sub/stamp").exists());
+        assert!(!dir.path().join("empty.log").exists());
+        assert!(dir.path().join("sub").exists());
+        assert!(dir.path().join("full.txt").exists());
+        // the empty glob match is counted once, under its glob
+        assert_eq!(job.stats()["**/*.log"], (1, 0));
+        assert_eq!(job.stats()["<empty-file>"], (1, 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_empty_files_ignores_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("empty"), "").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("empty"), dir.path().join("link")).unwrap();
+
+        let mut job = make_job(dir.path(), &[], true);
+        job.remove_empty_files = true;
+        job.run().unwrap();
+        assert_eq!(job.stats()["<empty-file>"], (1, 0));
+    }
+
+    #[test]
+    fn test_job_prune_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        fs::write(dir.path().join("a/b/c/x.pyc"), "x").unwrap();
+        fs::write(dir.path().join("a/b/y.pyc"), "x").unwrap();
+        fs::create_dir_all(dir.path().join("keep/sub")).unwrap();
+        fs::write(dir.path().join("keep/z.pyc"), "x").unwrap();
+        fs::write(dir.path().join("keep/z.py"), "x").unwrap();
+        fs::create_dir(dir.path().join("untouched")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.pyc"], false);
+        job.prune_empty_dirs = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("a").exists());
+        assert!(dir.path().join("keep/z.py").exists());
+        assert!(dir.path().join("keep/sub").exists());
+        assert!(dir.path().join("untouched").exists());
+        assert!(dir.path().exists());
+        assert_eq!(job.stats()["<empty-dir>"], (3, 0));
+    }
+
+    #[test]
+    fn test_job_prune_empty_dirs_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/b/x.pyc"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.pyc"], true);
+        job.prune_empty_dirs = true;
+        job.run().unwrap();
+        assert!(dir.path().join("a/b/x.pyc").exists());
+        assert_eq!(job.stats()["<empty-dir>"], (2, 0));
+    }
+
+    #[test]
+    fn test_job_prune_empty_dirs_keeps_excluded() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("cache/keep")).unwrap();
+        fs::write(dir.path().join("cache/keep/x.pyc"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.pyc", "!**/keep"], false);
+        job.prune_empty_dirs = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("cache/keep/x.pyc").exists());
+        // the excluded directory stays, so its parent is not empty either
+        assert!(dir.path().join("cache/keep").exists());
+        assert!(!job.stats().contains_key("<empty-dir>"));
+    }
+
+    #[test]
+    fn test_job_json_report() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("__pycache__")).unwrap();
+        fs::write(dir.path().join("__pycache__/mod.pyc"), "xyz").unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut job = make_job(dir.path(), &["**/__pycache__", "**/*.log"], true);
+        job.output_format = OutputFormat::Json;
+        job.set_output(out.reopen().unwrap());
+        job.run().unwrap();
+
+        let output = fs::read_to_string(out.path()).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&output).unwrap();
+        let matches = report["matches"].as_array().unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0]["pattern"], "**/__pycache__");
+        assert_eq!(matches[0]["size"], 3);
+        assert_eq!(matches[0]["is_dir"], true);
+        assert_eq!(matches[1]["pattern"], "**/*.log");
+        assert_eq!(report["summary"]["count"], 2);
+        assert_eq!(report["summary"]["size"], 4);
+        assert_eq!(report["summary"]["dry_run"], true);
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("ndjson".parse(), Ok(OutputFormat::Ndjson));
+        assert_eq!("JSON".parse(), Ok(OutputFormat::Json));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_job_exclude_regex_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("keep")).unwrap();
+        fs::write(dir.path().join("keep/core.123"), "x").unwrap();
+        fs::write(dir.path().join("core.456"), "x").unwrap();
+        fs::write(dir.path().join("core.789"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &[], false);
+        job.regex_patterns = vec![r"core\.\d+$".to_string()];
+        job.exclude_regex_patterns = vec!["/keep/".to_string(), "789$".to_string()];
+        job.run().unwrap();
+        assert!(dir.path().join("keep/core.123").exists());
+        assert!(!dir.path().join("core.456").exists());
+        assert!(dir.path().join("core.789").exists());
+    }
+
+    #[test]
+    fn test_job_glob_and_regex_match_counted_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("build-20230101.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.regex_patterns = vec![r"build-2023\d{4}".to_string()];
+        job.run().unwrap();
+        assert_eq!(job.summary().count, 1);
+        assert_eq!(job.stats().len(), 1);
+        assert_eq!(job.stats()["**/*.log"], (1, 1));
+    }
+
+    #[test]
+    fn test_job_rejects_invalid_exclude_regex() {
+        let mut job = make_job(Path::new("."), &["**/*.log"], true);
+        job.exclude_regex_patterns = vec!["[".to_string()];
+        assert!(matches!(job.run(), Err(CleanError::RegexError(_))));
+    }
+
+    #[test]
+    fn test_job_match_types_dir_only() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("crate/target/debug")).unwrap();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        fs::write(dir.path().join("docs/target"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/target"], false);
+        job.match_types = vec![EntryType::Dir];
+        job.run().unwrap();
+        assert!(!dir.path().join("crate/target").exists());
+        assert!(dir.path().join("docs/target").exists());
+    }
+
+    #[test]
+    fn test_job_match_types_file_only() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("logs.log")).unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.match_types = vec![EntryType::File];
+        job.run().unwrap();
+        assert!(!dir.path().join("a.log").exists());
+        assert!(dir.path().join("logs.log").exists());
+    }
+
+    #[test]
+    fn test_entry_type_from_str() {
+        assert_eq!("f".parse(), Ok(EntryType::File));
+        assert_eq!("d".parse(), Ok(EntryType::Dir));
+        assert_eq!("l".parse(), Ok(EntryType::Symlink));
+        assert!("x".parse::<EntryType>().is_err());
+    }
+
+    #[test]
+    fn test_job_run_with_callback_reports_events() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        fs::write(dir.path().join("keep.txt"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        let mut matched = Vec::new();
+        let mut deleted = Vec::new();
+        let mut progress = None;
+        job.run_with_callback(|event| match event {
+            CleanEvent::Matched {
+                path,
+                pattern,
+                size,
+            } => matched.push((path.to_path_buf(), pattern.to_string(), *size)),
+            CleanEvent::Deleted { path, size } => deleted.push((path.to_path_buf(), *size)),
+            CleanEvent::Failed { path, error } => panic!("{:?}: {}", path, error),
+            CleanEvent::ScanProgress { scanned, matched } => progress = Some((*scanned, *matched)),
+            CleanEvent::RemovalStarted { .. } => panic!("removal without confirmation"),
+        })
+        .unwrap();
+        let log = dir.path().join("a.log");
+        assert_eq!(matched, vec![(log.clone(), "**/*.log".to_string(), 3)]);
+        assert_eq!(deleted, vec![(log, 3)]);
+        // both files, but not the base directory
+        assert_eq!(progress, Some((2, 1)));
+    }
+
+    #[test]
+    fn test_job_run_with_callback_dry_run_deletes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.jobs = Some(2);
+        let mut events = Vec::new();
+        job.run_with_callback(|event| events.push(format!("{:?}", event)))
+            .unwrap();
+        assert!(events.iter().any(|e| e.starts_with("Matched")));
+        assert!(!events.iter().any(|e| e.starts_with("Deleted")));
+        assert!(dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_job_max_depth_skips_deep_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let deep = dir.path().join("a/b/c");
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(dir.path().join("a/top.log"), "x").unwrap();
+        fs::write(deep.join("deep.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.max_depth = Some(2);
+        job.run().unwrap();
+        // depth is counted from the base path, so a/top.log is at depth 2
+        assert!(!dir.path().join("a/top.log").exists());
+        assert!(deep.join("deep.log").exists());
+    }
+
+    #[test]
+    fn test_job_min_depth_skips_shallow_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("top.log"), "x").unwrap();
+        fs::write(dir.path().join("a/mid.log"), "x").unwrap();
+        fs::write(dir.path().join("a/b/low.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.min_depth = Some(2);
+        job.max_depth = Some(2);
+        job.run().unwrap();
+        assert!(dir.path().join("top.log").exists());
+        assert!(!dir.path().join("a/mid.log").exists());
+        assert!(dir.path().join("a/b/low.log").exists());
+    }
+
+    #[test]
+    fn test_job_min_depth_above_max_depth_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.min_depth = Some(3);
+        job.max_depth = Some(1);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    fn make_gitignore_fixture(dir: &Path) {
+        fs::write(dir.join(".gitignore"), "build/\n*.o\n").unwrap();
+        fs::create_dir_all(dir.join("build/obj")).unwrap();
+        fs::write(dir.join("build/obj/main.o"), "x").unwrap();
+        fs::write(dir.join("build.rs"), "x").unwrap();
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.o"), "x").unwrap();
+        fs::write(dir.join("src/lib.rs"), "x").unwrap();
+    }
+
+    #[test]
+    fn test_job_gitignore_standalone() {
+        let dir = tempfile::tempdir().unwrap();
+        make_gitignore_fixture(dir.path());
+
+        let mut job = make_job(dir.path(), &[], false);
+        job.use_gitignore = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("build").exists());
+        assert!(!dir.path().join("src/lib.o").exists());
+        assert!(dir.path().join("build.rs").exists());
+        assert!(dir.path().join("src/lib.rs").exists());
+        assert!(dir.path().join(".gitignore").exists());
+        assert!(job.failed_deletions().is_empty());
+    }
+
+    #[test]
+    fn test_job_gitignore_intersects_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        make_gitignore_fixture(dir.path());
+
+        let mut job = make_job(dir.path(), &["**/build*", "**/*.rs"], false);
+        job.use_gitignore = true;
+        job.run().unwrap();
+        // build.rs and src/lib.rs match a glob but are not ignored
+        assert!(!dir.path().join("build").exists());
+        assert!(dir.path().join("build.rs").exists());
+        assert!(dir.path().join("src/lib.rs").exists());
+        assert!(dir.path().join("src/lib.o").exists());
+    }
+
+    #[test]
+    fn test_job_respect_gitignore_skips_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        make_gitignore_fixture(dir.path());
+        fs::write(dir.path().join("build/obj/debug.log"), "x").unwrap();
+        fs::write(dir.path().join("src/debug.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/*.o"], false);
+        job.respect_gitignore = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("src/debug.log").exists());
+        assert!(dir.path().join("build/obj/debug.log").exists());
+        assert!(dir.path().join("build/obj/main.o").exists());
+        assert!(dir.path().join("src/lib.o").exists());
+    }
+
+    #[test]
+    fn test_job_gitignore_modes_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &[], false);
+        job.use_gitignore = true;
+        job.respect_gitignore = true;
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_csv_output_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.output_format = OutputFormat::Csv;
+        job.output_file = Some(out.path().join("report.csv"));
+        job.run().unwrap();
+        let csv = fs::read_to_string(out.path().join("report.csv")).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains("a.log,file,3,"));
+        assert!(lines[1].ends_with("Z,**/*.log,deleted,"));
+    }
+
+    #[test]
+    fn test_job_ndjson_streams_to_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::NamedTempFile::new().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        // truncating a directory always fails
+        fs::create_dir(dir.path().join("logs")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.truncate_patterns = vec!["**/logs".to_string()];
+        job.output_format = OutputFormat::Ndjson;
+        job.set_output(out.reopen().unwrap());
+        job.run().unwrap();
+        let events: Vec<serde_json::Value> = fs::read_to_string(out.path())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let count = |event: &str| events.iter().filter(|e| e["event"] == event).count();
+        assert_eq!(
+            (count("match"), count("deleted"), count("failed")),
+            (2, 1, 1)
+        );
+        let deleted = events.iter().find(|e| e["event"] == "deleted").unwrap();
+        assert!(deleted["path"].as_str().unwrap().ends_with("a.log"));
+        assert_eq!(deleted["size"], 3);
+        let last = events.last().unwrap();
+        assert_eq!(last["event"], "summary");
+        assert_eq!(last["count"], 2);
+    }
+
+    #[test]
+    fn test_job_csv_dry_run_not_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.run().unwrap();
+        assert!(job
+            .to_csv()
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with(",would-delete,"));
+    }
+
+    #[test]
+    fn test_job_csv_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let csv_file = out.path().join("report.csv");
+        fs::create_dir(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("build/x.o"), "obj").unwrap();
+        fs::write(dir.path().join("a, b\nc.log"), "abc").unwrap();
+        fs::write(dir.path().join("d.log"), "log").unwrap();
+        // truncating a directory always fails
+        fs::create_dir(dir.path().join("logs")).unwrap();
+
+        let read_rows = |path: &Path| -> Vec<csv::StringRecord> {
+            let mut reader = csv::Reader::from_path(path).unwrap();
+            assert_eq!(
+                reader.headers().unwrap(),
+                vec![
+                    "path",
+                    "type",
+                    "size_bytes",
+                    "modified_rfc3339",
+                    "matched_pattern",
+                    "action",
+                    "error"
+                ]
+            );
+            reader.records().map(|r| r.unwrap()).collect()
+        };
+
+        let patterns = ["**/build", "**/*.log"];
+        let mut job = make_job(dir.path(), &patterns, true);
+        job.csv_file = Some(csv_file.clone());
+        job.run().unwrap();
+        let rows = read_rows(&csv_file);
+        assert_eq!(rows.len(), job.summary().count);
+        assert!(rows.iter().all(|row| &row[5] == "would-delete"));
+        let odd = rows
+            .iter()
+            .find(|row| row[0].ends_with("a, b\nc.log"))
+            .unwrap();
+        assert_eq!((&odd[1], &odd[2], &odd[4]), ("file", "3", "**/*.log"));
+        assert!(odd[3].ends_with('Z'));
+        assert!(rows.iter().any(|row| &row[1] == "dir"));
+
+        let mut job = make_job(dir.path(), &patterns, false);
+        job.truncate_patterns = vec!["**/logs".to_string()];
+        job.csv_file = Some(csv_file.clone());
+        job.run().unwrap();
+        let rows = read_rows(&csv_file);
+        let deleted = rows.iter().filter(|row| &row[5] == "deleted").count();
+        assert_eq!((rows.len(), deleted), (4, 3));
+        let failed = rows.iter().find(|row| &row[5] == "failed").unwrap();
+        assert_eq!(&failed[6], "truncate failed: is a directory");
+        assert!(!dir.path().join("d.log").exists());
+    }
+
+    #[test]
+    fn test_job_ignore_case() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("mod.pyc"), "x").unwrap();
+        fs::write(dir.path().join("Thumbs.db"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.PYC", "**/thumbs.db"], false);
+        job.run().unwrap();
+        assert!(dir.path().join("mod.pyc").exists());
+        assert!(dir.path().join("Thumbs.db").exists());
+
+        let mut job = make_job(dir.path(), &["**/*.PYC", "**/thumbs.db"], false);
+        job.case_insensitive = true;
+        job.stats_mode = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("mod.pyc").exists());
+        assert!(!dir.path().join("Thumbs.db").exists());
+        assert_eq!(job.stats()["**/*.PYC"].0, 1);
+        assert_eq!(job.stats()["**/thumbs.db"].0, 1);
+    }
+
+    #[test]
+    fn test_job_ignore_case_applies_to_regexes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Debug.LOG"), "x").unwrap();
+        fs::write(dir.path().join("KEEP.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &[], false);
+        job.regex_patterns = vec![r"\.log$".to_string()];
+        job.exclude_regex_patterns = vec!["keep".to_string()];
+        job.case_insensitive = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("Debug.LOG").exists());
+        assert!(dir.path().join("KEEP.log").exists());
+    }
+
+    #[test]
+    fn test_job_negated_pattern_preserves_match() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("logs")).unwrap();
+        fs::write(dir.path().join("logs/debug.log"), "x").unwrap();
+        fs::write(dir.path().join("logs/important.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log", "!**/important.log"], false);
+        job.run().unwrap();
+        assert!(!dir.path().join("logs/debug.log").exists());
+        assert!(dir.path().join("logs/important.log").exists());
+        assert_eq!(job.stats().len(), 1);
+        assert_eq!(job.stats()["**/*.log"].0, 1);
+        assert!(job.matched().iter().all(|m| m.pattern == "**/*.log"));
+    }
+
+    #[test]
+    fn test_job_interactive_ignored_when_skipping_confirmation() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.interactive = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_job_max_delete_size() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("build/out.o"), "0123456789").unwrap();
+        fs::write(dir.path().join("debug.log"), "0123456789").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/build", "**/*.o", "**/*.log"], false);
+        job.max_delete_size = Some(15);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+        // the cap is checked before anything is removed
+        assert!(dir.path().join("build/out.o").exists());
+        assert!(dir.path().join("debug.log").exists());
+
+        let mut job = make_job(dir.path(), &["**/build", "**/*.o", "**/*.log"], false);
+        job.max_delete_size = Some(1000);
+        job.run().unwrap();
+        assert!(job.failed_deletions().is_empty());
+        assert!(!dir.path().join("build").exists());
+        assert!(!dir.path().join("debug.log").exists());
+    }
+
+    #[test]
+    fn test_job_max_delete_count() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(dir.path().join(name), "x").unwrap();
+        }
+
+        let mut job = make_job(dir.path(), &["**/*"], false);
+        job.max_delete_count = Some(2);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+
+        // aborts before prompting as well
+        let mut job = make_job(dir.path(), &["**/*"], false);
+        job.skip_confirmation = false;
+        job.max_delete_count = Some(2);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.max_delete_count = Some(3);
+        job.run().unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_job_max_deletions() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(dir.path().join(name), "x").unwrap();
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.max_deletions = Some(2);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+        assert!(job.truncated());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.max_deletions = Some(2);
+        job.run().unwrap();
+        let summary = job.summary();
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.max_deletions, Some(2));
+        assert!(summary.truncated);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.max_deletions = Some(2);
+        job.limit_ok = true;
+        job.run().unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_job_max_total_size() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(dir.path().join(name), "xyz").unwrap();
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.max_total_size = Some(7);
+        job.run().unwrap();
+        assert_eq!(job.summary().size, 6);
+        assert!(job.truncated());
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.max_total_size = Some(9);
+        job.run().unwrap();
+        assert!(!job.truncated());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_job_interactive_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log", "d.log"] {
+            fs::write(dir.path().join(name), "xy").unwrap();
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.skip_confirmation = false;
+        job.interactive = true;
+        let mut answers = vec![Answer::No, Answer::Yes, Answer::Quit].into_iter();
+        let mut asked = vec![];
+        job.run_with_prompt(|target| {
+            asked.push((target.path.to_path_buf(), target.size));
+            answers.next().unwrap()
+        })
+        .unwrap();
+        assert_eq!(asked.len(), 3);
+        assert_eq!(asked[0], (dir.path().join("a.log"), 2));
+        assert!(dir.path().join("a.log").exists());
+        assert!(!dir.path().join("b.log").exists());
+        assert!(dir.path().join("c.log").exists());
+        assert!(dir.path().join("d.log").exists());
+        // kept targets no longer count towards the totals
+        let summary = job.summary();
+        assert_eq!((summary.count, summary.size), (1, 2));
+        assert_eq!(job.stats()["**/*.log"], (1, 2));
+    }
+
+    #[test]
+    fn test_job_confirm_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a", "b", "c"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("x.log"), "xyz").unwrap();
+        }
+        fs::write(dir.path().join("b.log"), "xy").unwrap();
+
+        let mut job = make_job(dir.path(), &["*/?", "**/*.log"], false);
+        job.confirm_dirs = true;
+        let mut answers = vec![Answer::No, Answer::Quit].into_iter();
+        let mut asked = vec![];
+        job.run_with_prompt(|target| {
+            asked.push((target.path.to_path_buf(), target.size, target.is_dir));
+            answers.next().unwrap()
+        })
+        .unwrap();
+        // only the directories are asked about, with their full size
+        assert_eq!(
+            asked,
+            [
+                (dir.path().join("a"), 3, true),
+                (dir.path().join("b"), 3, true)
+            ]
+        );
+        assert!(dir.path().join("a/x.log").exists());
+        assert!(dir.path().join("b/x.log").exists());
+        assert!(!dir.path().join("b.log").exists());
+        assert!(dir.path().join("c/x.log").exists());
+        assert_eq!(job.summary().count, 1);
+
+        let mut job = make_job(dir.path(), &["*/?"], false);
+        job.confirm_dirs = true;
+        job.run_with_prompt(|_| Answer::All).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_job_interactive_prompt_details_asks_again() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("build/out.o"), "obj").unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/build", "**/*.log"], false);
+        job.skip_confirmation = false;
+        job.interactive = true;
+        let mut answers = vec![Answer::Details, Answer::All].into_iter();
+        let mut asked = vec![];
+        job.run_with_prompt(|target| {
+            asked.push((target.path.to_path_buf(), target.is_dir));
+            answers.next().unwrap()
+        })
+        .unwrap();
+        let build = dir.path().join("build");
+        assert_eq!(asked, vec![(build.clone(), true), (build.clone(), true)]);
+        assert!(!build.exists());
+        assert!(!dir.path().join("debug.log").exists());
+    }
+
+    /// picks fixed checklist entries, recording what it was offered
+    struct Checklist {
+        chosen: Vec<usize>,
+        offered: Vec<PathBuf>,
+    }
+
+    impl Prompter for Checklist {
+        fn ask(&mut self, _target: &TargetPrompt) -> Answer {
+            panic!("asked about a single target in select mode")
+        }
+
+        fn select(&mut self, targets: &[TargetPrompt]) -> rclean::Result<Vec<usize>> {
+            self.offered = targets.iter().map(|t| t.path.to_path_buf()).collect();
+            Ok(self.chosen.clone())
+        }
+    }
+
+    #[test]
+    fn test_job_select() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(dir.path().join(name), "xyz").unwrap();
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.skip_confirmation = false;
+        job.select = true;
+        let mut checklist = Checklist {
+            chosen: vec![0, 2],
+            offered: vec![],
+        };
+        job.run_with_prompter(&mut checklist).unwrap();
+        assert_eq!(checklist.offered.len(), 3);
+        assert!(!dir.path().join("a.log").exists());
+        assert!(dir.path().join("b.log").exists());
+        assert!(!dir.path().join("c.log").exists());
+        let summary = job.summary();
+        assert_eq!((summary.count, summary.size), (2, 6));
+    }
+
+    struct Typist {
+        typed: &'static str,
+        prompts: Vec<String>,
+    }
+
+    impl Prompter for Typist {
+        fn ask(&mut self, _target: &TargetPrompt) -> Answer {
+            panic!("asked about a single target")
+        }
+
+        fn type_confirmation(&mut self, prompt: &str) -> String {
+            self.prompts.push(prompt.to_string());
+            self.typed.to_string()
+        }
+    }
+
+    #[test]
+    fn test_job_confirm_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(dir.path().join(name), "xyz").unwrap();
+        }
+        let typist = |typed| Typist {
+            typed,
+            prompts: vec![],
+        };
+
+        // --yes alone does not get past the threshold
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.confirm_threshold = Some(5);
+        let mut prompter = typist("y");
+        job.run_with_prompter(&mut prompter).unwrap();
+        assert_eq!(prompter.prompts.len(), 1);
+        assert!(prompter.prompts[0].contains("type \"delete\" or 3"));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+
+        // below the threshold nothing is asked
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.confirm_threshold = Some(9);
+        job.dry_run = true;
+        job.run_with_prompter(&mut typist("")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.confirm_threshold = Some(5);
+        job.run_with_prompter(&mut typist(" 3 ")).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_job_confirm_threshold_override() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "xyz").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.confirm_threshold = Some(1);
+        job.skip_typed_confirmation = true;
+        let mut prompter = Typist {
+            typed: "",
+            prompts: vec![],
+        };
+        job.run_with_prompter(&mut prompter).unwrap();
+        assert!(prompter.prompts.is_empty());
+        assert!(!dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_job_select_groups_many_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        for group in ["x", "y"] {
+            fs::create_dir(dir.path().join(group)).unwrap();
+            for i in 0..1001 {
+                fs::write(dir.path().join(group).join(format!("{i}.log")), "").unwrap();
+            }
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.skip_confirmation = false;
+        job.select = true;
+        let mut checklist = Checklist {
+            chosen: vec![1],
+            offered: vec![],
+        };
+        job.run_with_prompter(&mut checklist).unwrap();
+        assert_eq!(
+            checklist.offered,
+            vec![dir.path().join("x"), dir.path().join("y")]
+        );
+        assert_eq!(fs::read_dir(dir.path().join("x")).unwrap().count(), 1001);
+        assert_eq!(fs::read_dir(dir.path().join("y")).unwrap().count(), 0);
+        assert_eq!(job.summary().count, 1001);
+    }
+
+    #[test]
+    fn test_answer_from_str() {
+        assert_eq!("y".parse(), Ok(Answer::Yes));
+        assert_eq!("Quit".parse(), Ok(Answer::Quit));
+        assert_eq!(" d ".parse(), Ok(Answer::Details));
+        assert!("maybe".parse::<Answer>().is_err());
+    }
+
+    #[test]
+    fn test_job_conditional_pattern_requires_sibling() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("crate/target")).unwrap();
+        fs::write(dir.path().join("crate/Cargo.toml"), "x").unwrap();
+        fs::create_dir_all(dir.path().join("docs/target")).unwrap();
+
+        let mut job = make_job(dir.path(), &[], false);
+        job.conditional_patterns = vec!["**/target=Cargo.toml".parse().unwrap()];
+        job.stats_mode = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("crate/target").exists());
+        assert!(dir.path().join("docs/target").exists());
+        assert_eq!(job.stats()["**/target"].0, 1);
+    }
+
+    #[test]
+    fn test_conditional_pattern_from_str() {
+        assert_eq!(
+            "**/node_modules=package.json".parse(),
+            Ok(ConditionalPattern {
+                pattern: "**/node_modules".to_string(),
+                requires_sibling: "package.json".to_string(),
+            })
+        );
+        assert!("**/target".parse::<ConditionalPattern>().is_err());
+        assert!("=Cargo.toml".parse::<ConditionalPattern>().is_err());
+    }
+
+    #[test]
+    fn test_job_depth_levels() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("one/two")).unwrap();
+        let files = ["zero.log", "one/one.log", "one/two/two.log"];
+        for depth in 1..=3 {
+            for file in files.iter() {
+                fs::write(dir.path().join(file), "x").unwrap();
+            }
+            let mut job = make_job(dir.path(), &["**/*.log"], true);
+            job.min_depth = Some(depth);
+            job.max_depth = Some(depth);
+            job.run().unwrap();
+            let matched: Vec<_> = job.matched().iter().map(|m| m.path.clone()).collect();
+            assert_eq!(matched, vec![dir.path().join(files[depth - 1])]);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_owned_by_filters_owner() {
+        use std::os::unix::fs::MetadataExt;
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+        let uid = fs::metadata(dir.path()).unwrap().uid();
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/build"], false);
+        job.owned_by = Some((uid + 1).to_string());
+        job.run().unwrap();
+        assert!(dir.path().join("a.log").exists());
+        assert!(dir.path().join("build").exists());
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/build"], false);
+        job.owned_by = Some(uid.to_string());
+        job.run().unwrap();
+        assert!(!dir.path().join("a.log").exists());
+        assert!(!dir.path().join("build").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_owned_by_checks_link_not_target() {
+        use std::os::unix::fs::MetadataExt;
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("real")).unwrap();
+        let target = dir.path().join("real/target.txt");
+        fs::write(&target, "x").unwrap();
+        let uid = fs::metadata(dir.path()).unwrap().uid();
+        // giving the target another owner needs root
+        if std::os::unix::fs::chown(&target, Some(uid + 1), None).is_err() {
+            return;
+        }
+        std::os::unix::fs::symlink(&target, dir.path().join("a.log")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.follow_symlinks = true;
+        job.include_symlinks = true;
+        job.owned_by = Some((uid + 1).to_string());
+        job.run().unwrap();
+        assert!(fs::symlink_metadata(dir.path().join("a.log")).is_ok());
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.follow_symlinks = true;
+        job.include_symlinks = true;
+        job.owned_by = Some(uid.to_string());
+        job.run().unwrap();
+        assert!(fs::symlink_metadata(dir.path().join("a.log")).is_err());
+        assert!(target.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_owned_by_unknown_user_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.owned_by = Some("no-such-user-rclean".to_string());
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_markdown_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.output_format = OutputFormat::Markdown;
+        job.set_output(out.reopen().unwrap());
+        job.run().unwrap();
+        assert!(dir.path().join("a.log").exists());
+        let markdown = fs::read_to_string(out.path()).unwrap();
+        assert!(markdown.contains("| `**/*.log` | 1 | 3 B |"));
+        assert!(markdown.contains("a.log`"));
+    }
+
+    fn count_scanned(job: &mut CleaningJob) -> usize {
+        let mut scanned = 0;
+        job.run_with_callback(|event| {
+            if let CleanEvent::ScanProgress { scanned: n, .. } = event {
+                scanned = *n;
+            }
+        })
+        .unwrap();
+        scanned
+    }
+
+    #[test]
+    fn test_job_prune_patterns_skip_subtree() {
+        let dir = tempfile::tempdir().unwrap();
+        let deep = dir.path().join("node_modules/a/b/c");
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("x.log"), "x").unwrap();
+        fs::write(dir.path().join("y.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        assert_eq!(count_scanned(&mut job), 6);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.prune_patterns = vec!["**/node_modules".to_string()];
+        // only y.log is visited
+        assert_eq!(count_scanned(&mut job), 1);
+        assert!(deep.join("x.log").exists());
+        assert!(!dir.path().join("y.log").exists());
+    }
+
+    #[test]
+    fn test_job_git_dir_pruned_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".git/objects")).unwrap();
+        fs::write(dir.path().join(".git/objects/pack.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.run().unwrap();
+        assert!(dir.path().join(".git/objects/pack.log").exists());
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.no_default_prune = true;
+        job.run().unwrap();
+        assert!(!dir.path().join(".git/objects/pack.log").exists());
+    }
+
+    #[test]
+    fn test_job_rcleanignore_files_stack() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir_all(sub.join("local")).unwrap();
+        fs::write(dir.path().join(".rcleanignore"), "keep*.log\n").unwrap();
+        fs::write(sub.join(".rcleanignore"), "# mine\nlocal/\n!keep2.log\n").unwrap();
+        let paths = [
+            dir.path().join("a.log"),
+            dir.path().join("keep1.log"),
+            sub.join("b.log"),
+            sub.join("keep2.log"),
+            sub.join("local/x.log"),
+        ];
+        for path in paths.iter() {
+            fs::write(path, "log").unwrap();
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.run().unwrap();
+        let left: Vec<bool> = paths.iter().map(|path| path.exists()).collect();
+        // the deeper file takes keep2.log back in
+        assert_eq!(left, [false, true, false, false, true]);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.no_rcleanignore = true;
+        job.run().unwrap();
+        assert!(paths.iter().all(|path| !path.exists()));
+    }
+
+    #[test]
+    fn test_job_multiple_roots() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fs::write(first.path().join("a.log"), "ab").unwrap();
+        fs::write(second.path().join("b.log"), "abc").unwrap();
+        fs::write(second.path().join("keep.txt"), "x").unwrap();
+
+        let mut job = make_job(first.path(), &["**/*.log"], false);
+        job.paths = vec![second.path().display().to_string()];
+        job.stats_mode = true;
+        job.run().unwrap();
+        assert!(!first.path().join("a.log").exists());
+        assert!(!second.path().join("b.log").exists());
+        assert!(second.path().join("keep.txt").exists());
+        assert_eq!(job.stats()["**/*.log"], (2, 5));
+        assert_eq!(job.summary().count, 2);
+        assert_eq!(job.summary().size, 5);
+    }
+
+    #[test]
+    fn test_job_nested_roots_walked_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/a.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.paths = vec![
+            dir.path().join("sub").display().to_string(),
+            dir.path().display().to_string(),
+        ];
+        job.run().unwrap();
+        assert_eq!(job.matched().len(), 1);
+    }
+
+    #[test]
+    fn test_job_multiple_roots_backup_layout() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let backup = tempfile::tempdir().unwrap();
+        fs::write(first.path().join("a.log"), "x").unwrap();
+        fs::write(second.path().join("a.log"), "y").unwrap();
+
+        let mut job = make_job(first.path(), &["**/*.log"], false);
+        job.paths = vec![second.path().display().to_string()];
+        job.backup_dir = Some(backup.path().join("b"));
+        job.run().unwrap();
+        let files = backup.path().join("b/files");
+        assert_eq!(fs::read_to_string(files.join("0/a.log")).unwrap(), "x");
+        assert_eq!(fs::read_to_string(files.join("1/a.log")).unwrap(), "y");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_accurate_size_counts_hardlinks_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        fs::create_dir(dir.path().join("b")).unwrap();
+        fs::write(dir.path().join("a/x.log"), vec![b'x'; 8192]).unwrap();
+        fs::hard_link(dir.path().join("a/x.log"), dir.path().join("b/x.log")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.run().unwrap();
+        assert_eq!(job.summary().size, 2 * 8192);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.accurate_size = true;
+        job.stats_mode = true;
+        job.run().unwrap();
+        let size = job.summary().size;
+        assert!((8192..2 * 8192).contains(&size), "{size}");
+        assert_eq!(job.stats()["**/*.log"].1, size);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_accurate_size_sparse_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = fs::File::create(dir.path().join("sparse.img")).unwrap();
+        file.set_len(64 * 1024 * 1024).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.img"], true);
+        job.accurate_size = true;
+        job.run().unwrap();
+        assert!(job.summary().size < 64 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_job_run_on_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("__pycache__")).unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+        fs::write(dir.path().join("b.log"), "x").unwrap();
+        fs::write(dir.path().join("keep.txt"), "x").unwrap();
+        fs::write(outside.path().join("c.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/__pycache__"], false);
+        job.run_on_paths(vec![
+            dir.path().join("__pycache__"),
+            dir.path().join("a.log"),
+            dir.path().join("keep.txt"),
+            dir.path().join("missing.log"),
+            outside.path().join("c.log"),
+        ])
+        .unwrap();
+        assert!(!dir.path().join("__pycache__").exists());
+        assert!(!dir.path().join("a.log").exists());
+        // not given, not matching, or outside of the base path
+        assert!(dir.path().join("b.log").exists());
+        assert!(dir.path().join("keep.txt").exists());
+        assert!(outside.path().join("c.log").exists());
+        assert_eq!(job.matched().len(), 2);
+    }
+
+    #[test]
+    fn test_job_run_on_paths_without_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.tmp"), "x").unwrap();
+        fs::write(dir.path().join("important.tmp"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["!**/important.tmp"], false);
+        job.run_on_paths(vec![
+            dir.path().join("a.tmp"),
+            dir.path().join("important.tmp"),
+        ])
+        .unwrap();
+        assert!(!dir.path().join("a.tmp").exists());
+        assert!(dir.path().join("important.tmp").exists());
+        assert_eq!(job.matched()[0].pattern, "<stdin>");
+    }
+
+    #[test]
+    fn test_job_run_on_paths_rejects_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+
+        let mut job = make_job(&dir.path().join("sub"), &["**/*.log"], false);
+        job.run_on_paths(vec![dir.path().join("sub/../a.log")])
+            .unwrap();
+        assert!(dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_job_protect_newer_than() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("old.log"), "x").unwrap();
+        fs::write(dir.path().join("new.log"), "x").unwrap();
+        set_age(&dir.path().join("old.log"), 7200);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.protect_newer_than_secs = Some(3600);
+        job.run().unwrap();
+        assert!(!dir.path().join("old.log").exists());
+        assert!(dir.path().join("new.log").exists());
+        assert_eq!(job.summary().count, 1);
+    }
+
+    #[test]
+    fn test_job_protect_newer_than_looks_inside_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("__pycache__");
+        fs::create_dir(&cache).unwrap();
+        fs::write(cache.join("old.pyc"), "x").unwrap();
+        fs::write(cache.join("new.pyc"), "x").unwrap();
+        set_age(&cache.join("old.pyc"), 7200);
+        let old = fs::File::open(&cache).unwrap();
+        old.set_modified(SystemTime::now() - Duration::from_secs(7200))
+            .unwrap();
+
+        let mut job = make_job(dir.path(), &["**/__pycache__"], false);
+        job.protect_newer_than_secs = Some(3600);
+        job.run().unwrap();
+        assert!(cache.exists());
+    }
+
+    #[test]
+    fn test_job_matches_after_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("__pycache__")).unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/__pycache__"], true);
+        job.run().unwrap();
+        assert_eq!(
+            job.matched_paths(),
+            vec![dir.path().join("__pycache__"), dir.path().join("a.log")]
+        );
+        let matches: Vec<_> = job.matches().collect();
+        assert_eq!(matches.len(), 2);
+        assert!(matches[0].1.is_dir());
+        assert_eq!(matches[1].1.len(), 3);
+    }
+
+    #[test]
+    fn test_job_quiet_still_removes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "x").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.quiet = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("a.log").exists());
+        assert_eq!(job.summary().count, 1);
+    }
+
+    #[test]
+    fn test_job_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("copies")).unwrap();
+        fs::write(dir.path().join("pkg.whl"), "wheel").unwrap();
+        fs::write(dir.path().join("copies/pkg.whl"), "wheel").unwrap();
+        fs::write(dir.path().join("copies/other.whl"), "other").unwrap();
+        fs::write(dir.path().join("copies/pkg.txt"), "wheel").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.whl"], true);
+        job.duplicates = true;
+        job.keep = rclean::KeepPolicy::Shortest;
+        job.run().unwrap();
+        assert_eq!(job.duplicate_groups().len(), 1);
+        assert_eq!(job.matched_paths(), vec![dir.path().join("copies/pkg.whl")]);
+        assert!(dir.path().join("copies/pkg.whl").exists());
+        let report: serde_json::Value = serde_json::from_str(&job.to_json()).unwrap();
+        assert_eq!(
+            report["duplicates"][0]["kept"],
+            dir.path().join("pkg.whl").display().to_string()
+        );
+
+        job.dry_run = false;
+        job.run().unwrap();
+        assert!(dir.path().join("pkg.whl").exists());
+        assert!(!dir.path().join("copies/pkg.whl").exists());
+        assert!(dir.path().join("copies/pkg.txt").exists());
+    }
+
+    #[test]
+    fn test_job_retention_pattern_keeps_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        for day in 1..=5 {
+            let path = dir.path().join(format!("backup-{day}.tar.gz"));
+            fs::write(&path, "backup").unwrap();
+            set_age(&path, day * 86400);
+        }
+
+        let mut job = make_job(dir.path(), &[], true);
+        job.retention_patterns = vec!["**/backup-*.tar.gz=3".parse().unwrap()];
+        job.run().unwrap();
+        assert_eq!(
+            job.matched_paths(),
+            vec![
+                dir.path().join("backup-4.tar.gz"),
+                dir.path().join("backup-5.tar.gz")
+            ]
+        );
+        assert_eq!(job.retained()["**/backup-*.tar.gz"], 3);
+    }
+
+    #[test]
+    fn test_job_keep_newest_per_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["a", "b"] {
+            fs::create_dir(dir.path().join(sub)).unwrap();
+            for age in 1..=2 {
+                let path = dir.path().join(sub).join(format!("{age}.log"));
+                fs::write(&path, "log").unwrap();
+                set_age(&path, age * 60);
+            }
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.keep_newest = Some(1);
+        job.run().unwrap();
+        assert!(dir.path().join("a/1.log").exists());
+        assert!(!dir.path().join("a/2.log").exists());
+        assert!(dir.path().join("b/1.log").exists());
+        assert!(!dir.path().join("b/2.log").exists());
+    }
+
+    #[test]
+    fn test_job_keep_newest_and_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let create = || {
+            for age in 1..=5 {
+                let path = dir.path().join(format!("{age}.log"));
+                fs::write(&path, "log").unwrap();
+                set_age(&path, age * 60);
+            }
+        };
+        let left = || {
+            let mut left: Vec<_> = fs::read_dir(dir.path())
+                .unwrap()
+                .map(|e| e.unwrap().file_name())
+                .collect();
+            left.sort();
+            left
+        };
+
+        create();
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.keep_newest = Some(2);
+        job.run().unwrap();
+        assert_eq!(left(), ["1.log", "2.log"]);
+        assert_eq!(job.summary().count, 3);
+        assert_eq!(job.retained()["**/*.log"], 2);
+
+        create();
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.keep_oldest = Some(2);
+        job.run().unwrap();
+        assert_eq!(left(), ["4.log", "5.log"]);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.keep_newest = Some(2);
+        job.keep_oldest = Some(2);
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_keep_newest_after_age_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        for days in [1, 2, 8, 9, 10, 11] {
+            let path = dir.path().join(format!("{days}.log"));
+            fs::write(&path, "log").unwrap();
+            set_age(&path, days * 86400);
+        }
+
+        // the files too young to be removed do not count towards the kept
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.keep_newest = Some(2);
+        job.older_than_secs = Some(7 * 86400);
+        job.run().unwrap();
+        let mut left: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["1.log", "2.log", "8.log", "9.log"]);
+        assert_eq!(job.retained()["**/*.log"], 2);
+        assert_eq!(job.summary().count, 2);
+    }
+
+    #[test]
+    fn test_job_keep_oldest_after_size_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        for age in 1..=4 {
+            let path = dir.path().join(format!("{age}.log"));
+            // the two oldest are too small to be removed anyway
+            fs::write(&path, if age > 2 { "x" } else { "log" }).unwrap();
+            set_age(&path, age * 60);
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.keep_oldest = Some(1);
+        job.min_size = Some(2);
+        job.run().unwrap();
+        assert!(dir.path().join("4.log").exists());
+        assert!(dir.path().join("3.log").exists());
+        assert!(dir.path().join("2.log").exists());
+        assert!(!dir.path().join("1.log").exists());
+        assert_eq!(job.retained()["**/*.log"], 1);
+    }
+
+    #[test]
+    fn test_job_same_file_system() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/debug.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.same_file_system = true;
+        job.run().unwrap();
+        assert_eq!(job.matched_paths(), vec![dir.path().join("sub/debug.log")]);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.same_file_system = true;
+        job.run_on_paths(vec![dir.path().join("sub/debug.log")])
+            .unwrap();
+        assert_eq!(job.matched_paths(), vec![dir.path().join("sub/debug.log")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_follow_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("real")).unwrap();
+        fs::write(dir.path().join("real/inside.log"), "log").unwrap();
+        fs::write(outside.path().join("outside.log"), "log").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("real"), dir.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.run().unwrap();
+        assert_eq!(
+            job.matched_paths(),
+            vec![dir.path().join("real/inside.log")]
+        );
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.follow_symlinks = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("real/inside.log").exists());
+        // a symlink leading outside the base path is never followed
+        assert!(outside.path().join("outside.log").exists());
+        assert!(dir.path().join("escape").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_follow_symlinks_removes_link_only() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("real/build")).unwrap();
+        fs::write(dir.path().join("real/build/out.o"), "obj").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("real/build"), dir.path().join("cache"))
+            .unwrap();
+
+        let mut job = make_job(dir.path(), &["**/cache"], false);
+        job.follow_symlinks = true;
+        job.include_symlinks = true;
+        job.run().unwrap();
+        assert!(fs::symlink_metadata(dir.path().join("cache")).is_err());
+        assert!(dir.path().join("real/build/out.o").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_symlink_behavior() {
+        let dir = tempfile::tempdir().unwrap();
+        let make_links = || {
+            fs::create_dir_all(dir.path().join("real/dir")).unwrap();
+            fs::write(dir.path().join("real/dir/out.o"), "obj").unwrap();
+            fs::write(dir.path().join("real/file.o"), "obj").unwrap();
+            std::os::unix::fs::symlink(dir.path().join("real/dir"), dir.path().join("dlink"))
+                .unwrap();
+            std::os::unix::fs::symlink(dir.path().join("real/file.o"), dir.path().join("flink"))
+                .unwrap();
+        };
+        let make = |behavior| {
+            let mut job = make_job(dir.path(), &["**/?link"], false);
+            job.include_symlinks = true;
+            job.symlink_behavior = behavior;
+            job
+        };
+
+        make_links();
+        make(SymlinkBehavior::Unlink).run().unwrap();
+        assert!(fs::symlink_metadata(dir.path().join("dlink")).is_err());
+        assert!(fs::symlink_metadata(dir.path().join("flink")).is_err());
+        assert!(dir.path().join("real/dir/out.o").exists());
+        assert!(dir.path().join("real/file.o").exists());
+        fs::remove_dir_all(dir.path().join("real")).unwrap();
+
+        make_links();
+        let mut job = make(SymlinkBehavior::FollowAndDelete);
+        job.run().unwrap();
+        assert!(job.failed_deletions().is_empty());
+        assert!(fs::symlink_metadata(dir.path().join("dlink")).is_err());
+        assert!(fs::symlink_metadata(dir.path().join("flink")).is_err());
+        assert!(!dir.path().join("real/dir").exists());
+        assert!(!dir.path().join("real/file.o").exists());
+
+        let mut job = make(SymlinkBehavior::FollowAndDelete);
+        job.include_symlinks = false;
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_symlink_behavior_stays_inside() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("keep.o"), "obj").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("dlink")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("keep.o"), dir.path().join("flink"))
+            .unwrap();
+        // nor may a link take the base path itself along
+        std::os::unix::fs::symlink(dir.path(), dir.path().join("rlink")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/?link"], false);
+        job.include_symlinks = true;
+        job.symlink_behavior = SymlinkBehavior::FollowAndDelete;
+        job.run().unwrap();
+        assert_eq!(job.failed_deletions().len(), 3);
+        assert!(outside.path().join("keep.o").exists());
+        assert!(fs::symlink_metadata(dir.path().join("dlink")).is_ok());
+        assert!(fs::symlink_metadata(dir.path().join("rlink")).is_ok());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_job_removes_long_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut deep = dir.path().join("node_modules");
+        while deep.as_os_str().len() <= 300 {
+            deep.push("node_modules_package");
+        }
+        fs::create_dir_all(rclean::utils::long_path(&deep)).unwrap();
+        fs::write(rclean::utils::long_path(&deep.join("index.js")), "js").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/node_modules"], false);
+        job.run().unwrap();
+        assert!(job.failed_deletions().is_empty());
+        assert!(!dir.path().join("node_modules").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_secure_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("keys")).unwrap();
+        fs::write(dir.path().join("keys/id.pem.bak"), "secret").unwrap();
+        fs::write(dir.path().join(".bash_history"), "password").unwrap();
+        fs::write(outside.path().join("target"), "untouched").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("target"), dir.path().join("keys/link"))
+            .unwrap();
+        // hard links outlive the unlink, so the overwrite can be observed
+        fs::hard_link(
+            dir.path().join("keys/id.pem.bak"),
+            outside.path().join("pem"),
+        )
+        .unwrap();
+        fs::hard_link(
+            dir.path().join(".bash_history"),
+            outside.path().join("hist"),
+        )
+        .unwrap();
+
+        let mut job = make_job(dir.path(), &["**/keys", "**/.bash_history"], false);
+        job.secure_delete = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("keys").exists());
+        assert!(!dir.path().join(".bash_history").exists());
+        assert_eq!(fs::read(outside.path().join("pem")).unwrap(), vec![0; 6]);
+        assert_eq!(fs::read(outside.path().join("hist")).unwrap(), vec![0; 8]);
+        // symlinks are never shredded through
+        assert_eq!(
+            fs::read_to_string(outside.path().join("target")).unwrap(),
+            "untouched"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_force_clears_readonly() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        fs::create_dir_all(locked.join("build")).unwrap();
+        fs::write(locked.join("build/out.o"), "obj").unwrap();
+        fs::write(locked.join("debug.log"), "log").unwrap();
+        fs::set_permissions(locked.join("build"), fs::Permissions::from_mode(0o555)).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/build", "**/*.log"], false);
+        job.force = true;
+        job.run().unwrap();
+        assert!(job.failed_deletions().is_empty());
+        assert!(!locked.join("build").exists());
+        assert!(!locked.join("debug.log").exists());
+    }
+
+    #[test]
+    fn test_job_secure_delete_conflicts_with_trash() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.secure_delete = true;
+        job.trash_mode = true;
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_stage() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("build/lib")).unwrap();
+        fs::write(dir.path().join("build/lib/x.o"), "obj").unwrap();
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+        fs::write(dir.path().join("keep.txt"), "txt").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/build", "**/*.log", "**/*.o"], false);
+        job.stage = true;
+        job.run().unwrap();
+        let mut left: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["keep.txt"]);
+        assert_eq!(
+            job.disposition(&dir.path().join("a.log")),
+            Disposition::Deleted
+        );
+    }
+
+    #[test]
+    fn test_job_stage_moves_back_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        for base in ["a", "b"] {
+            fs::create_dir(dir.path().join(base)).unwrap();
+            fs::write(dir.path().join(base).join("x.log"), "log").unwrap();
+        }
+        // the staging directory of the second base path cannot be created
+        let staging = format!(".rclean-staging-{}", std::process::id());
+        fs::write(dir.path().join("b").join(staging), "").unwrap();
+
+        let mut job = make_job(&dir.path().join("a"), &["**/*.log"], false);
+        job.paths = vec![dir.path().join("b").display().to_string()];
+        job.stage = true;
+        job.run().unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a/x.log")).unwrap(),
+            "log"
+        );
+        assert!(dir.path().join("b/x.log").exists());
+        assert_eq!(fs::read_dir(dir.path().join("a")).unwrap().count(), 1);
+        assert_eq!(job.summary().failed.len(), 1);
+    }
+
+    #[test]
+    fn test_job_stage_conflicts_with_trash() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.stage = true;
+        job.trash_mode = true;
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_run_returns_report() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        fs::write(dir.path().join("b.tmp"), "de").unwrap();
+        fs::write(dir.path().join("keep.txt"), "txt").unwrap();
+
+        let report = make_job(dir.path(), &["**/*.log", "**/*.tmp"], true)
+            .run()
+            .unwrap();
+        assert!(report.dry_run);
+        assert_eq!((report.deleted_count, report.total_size), (2, 5));
+        assert_eq!(report.stats["**/*.log"], (1, 3));
+        assert!(dir.path().join("a.log").exists());
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/*.tmp"], false);
+        let report = job.run().unwrap();
+        assert!(!report.dry_run);
+        assert_eq!((report.deleted_count, report.total_size), (2, 5));
+        assert!(report.failed.is_empty());
+        assert_eq!(report, job.report());
+    }
+
+    #[test]
+    fn test_job_truncate_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("logs")).unwrap();
+        fs::write(dir.path().join("logs/app.log"), "live log").unwrap();
+        fs::write(dir.path().join("logs/old.log"), "old").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.truncate_patterns = vec!["**/app.log".to_string()];
+        job.run().unwrap();
+        assert_eq!(
+            job.disposition(&dir.path().join("logs/app.log")),
+            Disposition::WouldTruncate
+        );
+        assert_eq!(job.summary().size, 11);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.truncate_patterns = vec!["**/app.log".to_string()];
+        job.run().unwrap();
+        assert_eq!(fs::read(dir.path().join("logs/app.log")).unwrap(), b"");
+        assert!(!dir.path().join("logs/old.log").exists());
+        assert_eq!(job.stats()["**/app.log"], (1, 8));
+        assert_eq!(
+            job.disposition(&dir.path().join("logs/app.log")),
+            Disposition::Truncated
+        );
+    }
+
+    #[test]
+    fn test_job_truncate_directory_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("logs")).unwrap();
+
+        let mut job = make_job(dir.path(), &[], false);
+        job.truncate_patterns = vec!["**/logs".to_string()];
+        job.run().unwrap();
+        assert!(dir.path().join("logs").is_dir());
+        let failed = job.failed_deletions();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].1, "truncate failed: is a directory");
+    }
+
+    #[test]
+    fn test_job_error_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        // truncating a directory always fails, and they sort first
+        fs::create_dir(dir.path().join("a1")).unwrap();
+        fs::create_dir(dir.path().join("a2")).unwrap();
+        let make = |policy| {
+            let mut job = make_job(dir.path(), &["**/*.log"], false);
+            job.truncate_patterns = vec!["**/a?".to_string()];
+            job.error_policy = policy;
+            job
+        };
+        let write_logs = || {
+            for name in ["b.log", "c.log"] {
+                fs::write(dir.path().join(name), "log").unwrap();
+            }
+        };
+
+        write_logs();
+        let mut job = make(ErrorPolicy::AbortOnFirstError);
+        assert!(matches!(
+            job.run(),
+            Err(CleanError::DeletionAborted {
+                failed: 1,
+                remaining: 3
+            })
+        ));
+        assert!(dir.path().join("b.log").exists());
+        assert_eq!(job.not_attempted().len(), 3);
+        assert_eq!(job.summary().remaining, 3);
+
+        let mut job = make(ErrorPolicy::AbortAfterN(2));
+        assert!(matches!(
+            job.run(),
+            Err(CleanError::DeletionAborted {
+                failed: 2,
+                remaining: 2
+            })
+        ));
+        assert!(dir.path().join("c.log").exists());
+
+        let mut job = make(ErrorPolicy::Continue);
+        let report = job.run().unwrap();
+        assert_eq!((report.failed.len(), report.deleted_count), (2, 2));
+        assert!(!dir.path().join("c.log").exists());
+        assert_eq!(job.summary().remaining, 0);
+
+        write_logs();
+        let mut job = make(ErrorPolicy::AbortAfterN(0));
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_report_free_space() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("big.log"), vec![1u8; 1 << 20]).unwrap();
+
+        // a dry-run only estimates what the removal would give back
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.report_free_space = true;
+        job.run().unwrap();
+        let summary = job.summary();
+        let before = summary.free_space_before.unwrap();
+        assert_eq!(summary.free_space_after, None);
+        assert_eq!(summary.free_space_after_estimate, Some(before + (1 << 20)));
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.report_free_space = true;
+        job.run().unwrap();
+        let summary = job.summary();
+        assert!(summary.free_space_before.is_some());
+        assert!(summary.free_space_after.is_some());
+        let json: serde_json::Value = serde_json::from_str(&job.to_json()).unwrap();
+        assert!(json["summary"]["free_space_before"].is_u64());
+        assert!(json["summary"]["free_space_after"].is_u64());
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.run().unwrap();
+        assert_eq!(job.summary().free_space_before, None);
+        assert_eq!(job.summary().free_space_after_estimate, None);
+    }
+
+    #[test]
+    fn test_job_min_free_after() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+
+        // warns without refusing, also without report_free_space
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.min_free_after = Some(u64::MAX);
+        job.run().unwrap();
+        assert!(!dir.path().join("a.log").exists());
+        let summary = job.summary();
+        assert!(summary.free_space_after_estimate.unwrap() >= 3);
+        assert_eq!(summary.free_space_after, None);
+    }
+
+    #[test]
+    fn test_job_parallel_delete() {
+        // the same large tree, with nested matches, for both runs
+        let make_tree = |root: &Path| {
+            for project in 0..20 {
+                let modules = root.join(format!("p{project}/node_modules/dep/node_modules"));
+                fs::create_dir_all(&modules).unwrap();
+                for file in 0..50 {
+                    fs::write(modules.join(format!("{file}.js")), "js").unwrap();
+                    fs::write(root.join(format!("p{project}/{file}.log")), "log").unwrap();
+                }
+                fs::write(root.join(format!("p{project}/keep.txt")), "keep").unwrap();
+            }
+        };
+        let serial_dir = tempfile::tempdir().unwrap();
+        let parallel_dir = tempfile::tempdir().unwrap();
+        make_tree(serial_dir.path());
+        make_tree(parallel_dir.path());
+        let patterns = ["**/node_modules", "**/*.log"];
+
+        let mut serial = make_job(serial_dir.path(), &patterns, false);
+        let expected = serial.run().unwrap();
+        let mut job = make_job(parallel_dir.path(), &patterns, false);
+        job.parallel_delete = true;
+        job.jobs = Some(4);
+        let report = job.run().unwrap();
+        assert_eq!(report.deleted_count, 20 * 51);
+        assert_eq!(
+            (report.deleted_count, report.total_size, report.failed.len()),
+            (expected.deleted_count, expected.total_size, 0)
+        );
+        assert_eq!(report.stats, expected.stats);
+        for project in 0..20 {
+            let project = parallel_dir.path().join(format!("p{project}"));
+            assert!(!project.join("node_modules").exists());
+            assert!(!project.join("0.log").exists());
+            assert!(project.join("keep.txt").exists());
+        }
+
+        let mut job = make_job(parallel_dir.path(), &patterns, false);
+        job.parallel_delete = true;
+        job.error_policy = ErrorPolicy::AbortOnFirstError;
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_job_skip_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("build/open.o"), "obj").unwrap();
+        fs::write(dir.path().join("open.log"), "log").unwrap();
+        fs::write(dir.path().join("closed.log"), "log").unwrap();
+        let _log = fs::File::open(dir.path().join("open.log")).unwrap();
+        let _obj = fs::File::open(dir.path().join("build/open.o")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log", "**/build"], false);
+        job.skip_in_use = true;
+        job.run().unwrap();
+        assert!(dir.path().join("open.log").exists());
+        assert!(dir.path().join("build/open.o").exists());
+        assert!(!dir.path().join("closed.log").exists());
+        assert_eq!(job.skipped_in_use().len(), 2);
+        assert_eq!(
+            job.disposition(&dir.path().join("open.log")),
+            Disposition::InUse
+        );
+        let summary = job.summary();
+        assert_eq!((summary.count, summary.skipped_in_use), (1, 2));
+        assert!(summary.failed.is_empty());
+    }
+
+    #[cfg(not(feature = "archive"))]
+    #[test]
+    fn test_job_archive_requires_archive_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.archive_path = Some(dir.path().join("clean.tar.gz"));
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[cfg(not(feature = "git"))]
+    #[test]
+    fn test_job_untracked_only_requires_git_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.untracked_only = true;
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_delete_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let removed = out.path().join("removed");
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        fs::write(dir.path().join("keep.log"), "de").unwrap();
+        let pre = r#"sh -c 'case "$RCLEAN_PATH" in *keep*) exit 1;; esac'"#;
+        let post = format!("sh -c 'echo \"$1\" >> {}' sh {{}}", removed.display());
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.pre_delete_hook = Some(pre.to_string());
+        job.post_delete_hook = Some(post.clone());
+        job.run().unwrap();
+        // hooks never run in a dry-run
+        assert!(!removed.exists());
+        assert!(job.skipped_by_hook().is_empty());
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.pre_delete_hook = Some(pre.to_string());
+        job.post_delete_hook = Some(post);
+        job.run().unwrap();
+        assert!(!dir.path().join("a.log").exists());
+        assert!(dir.path().join("keep.log").exists());
+        assert_eq!(job.skipped_by_hook(), [dir.path().join("keep.log")]);
+        assert_eq!(
+            job.disposition(&dir.path().join("keep.log")),
+            Disposition::Skipped
+        );
+        assert_eq!((job.summary().count, job.summary().skipped_by_hook), (1, 1));
+        assert_eq!(
+            fs::read_to_string(&removed).unwrap().trim_end(),
+            dir.path().join("a.log").display().to_string()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_delete_hooks_per_run_and_shell() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        fs::write(dir.path().join("$(touch pwned).log"), "de").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.pre_delete_hook = Some("false".to_string());
+        job.hook_per_run = true;
+        job.run().unwrap();
+        assert_eq!(job.skipped_by_hook().len(), 2);
+        assert!(dir.path().join("a.log").exists());
+
+        // the path reaches the shell only through the environment
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.pre_delete_hook = Some(format!("cd {} && echo {{}}", dir.path().display()));
+        job.hook_shell = true;
+        job.run().unwrap();
+        assert_eq!(job.summary().count, 2);
+        assert!(!dir.path().join("pwned").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_exec() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let ran = out.path().join("ran");
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        fs::write(dir.path().join("b c.log"), "de").unwrap();
+        let exec = format!("sh -c 'echo \"$1\" >> {}' sh {{}}", ran.display());
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.exec = Some(exec.clone());
+        job.run().unwrap();
+        assert!(!ran.exists());
+        assert_eq!(
+            job.disposition(&dir.path().join("a.log")),
+            Disposition::WouldExecute
+        );
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.exec = Some(exec);
+        job.run().unwrap();
+        assert!(dir.path().join("a.log").exists());
+        assert!(dir.path().join("b c.log").exists());
+        let mut lines: Vec<String> = fs::read_to_string(&ran)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        lines.sort();
+        assert_eq!(
+            lines,
+            [
+                dir.path().join("a.log").display().to_string(),
+                dir.path().join("b c.log").display().to_string()
+            ]
+        );
+        assert_eq!(
+            job.disposition(&dir.path().join("b c.log")),
+            Disposition::Executed
+        );
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.exec = Some("false".to_string());
+        job.run().unwrap();
+        assert_eq!(job.failed_deletions().len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_exec_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let ran = out.path().join("ran");
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(dir.path().join(name), "log").unwrap();
+        }
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        // the paths are appended without a {}
+        job.exec = Some(format!("sh -c 'echo $# >> {}' sh", ran.display()));
+        job.exec_batch = true;
+        job.run().unwrap();
+        assert_eq!(fs::read_to_string(&ran).unwrap(), "3\n");
+        assert_eq!(job.summary().count, 3);
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.exec = Some("true".to_string());
+        job.trash_mode = true;
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_job_delete_hook_unclosed_quote() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.pre_delete_hook = Some("echo 'oops".to_string());
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_post_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = tempfile::tempdir().unwrap();
+        let report = out.path().join("report");
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        fs::write(dir.path().join("b.log"), "de").unwrap();
+        let hook = format!(
+            "echo \"$RCLEAN_COUNT $RCLEAN_SIZE $RCLEAN_PATH\" > {}",
+            report.display()
+        );
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.post_hook = Some(hook.clone());
+        job.run().unwrap();
+        // nothing is removed in a dry-run
+        assert!(!report.exists());
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.post_hook = Some(hook);
+        job.run().unwrap();
+        assert_eq!(
+            fs::read_to_string(&report).unwrap().trim_end(),
+            format!("2 5 {}", dir.path().display())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_job_post_hook_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.post_hook = Some("exit 3".to_string());
+        job.run().unwrap();
+
+        fs::write(dir.path().join("a.log"), "abc").unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.post_hook = Some("exit 3".to_string());
+        job.hook_strict = true;
+        assert!(matches!(job.run(), Err(CleanError::HookError(_))));
+        assert!(!dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_job_hidden_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".cache")).unwrap();
+        fs::write(dir.path().join(".hidden"), "x").unwrap();
+        fs::write(dir.path().join(".cache/inner"), "x").unwrap();
+        fs::write(dir.path().join("regular"), "x").unwrap();
+
+        let matched = |policy| {
+            let mut job = make_job(dir.path(), &["**/*"], true);
+            job.match_types = vec![EntryType::File];
+            job.hidden_policy = policy;
+            job.run().unwrap();
+            job.matched_paths()
+        };
+        assert_eq!(
+            matched(HiddenPolicy::Include),
+            vec![
+                dir.path().join(".cache/inner"),
+                dir.path().join(".hidden"),
+                dir.path().join("regular"),
+            ]
+        );
+        assert_eq!(
+            matched(HiddenPolicy::Exclude),
+            vec![dir.path().join("regular")]
+        );
+        // entries inside a hidden directory are hidden as well
+        assert_eq!(
+            matched(HiddenPolicy::Only),
+            vec![dir.path().join(".cache/inner"), dir.path().join(".hidden")]
+        );
+    }
+
+    #[test]
+    fn test_job_hidden_policy_ignores_hidden_base_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join(".config");
+        fs::create_dir(&base).unwrap();
+        fs::write(base.join("regular"), "x").unwrap();
+
+        let mut job = make_job(&base, &["**/regular"], true);
+        job.hidden_policy = HiddenPolicy::Exclude;
+        job.run().unwrap();
+        assert_eq!(job.matched_paths(), vec![base.join("regular")]);
+    }
+
+    #[test]
+    fn test_hidden_policy_from_str() {
+        assert_eq!("only".parse(), Ok(HiddenPolicy::Only));
+        assert_eq!("Exclude".parse(), Ok(HiddenPolicy::Exclude));
+        assert!("none".parse::<HiddenPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_job_presets() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.o"), "obj").unwrap();
+        fs::write(dir.path().join("Main.class"), "class").unwrap();
+        fs::write(dir.path().join("notes.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.presets = vec!["cpp".to_string()];
+        job.run().unwrap();
+        assert_eq!(
+            job.matched_paths(),
+            vec![dir.path().join("main.o"), dir.path().join("notes.log")]
+        );
+
+        job.presets = vec!["cobol".to_string()];
+        match job.run() {
+            Err(CleanError::ConfigError(msg)) => assert!(msg.contains("python, go, java")),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_job_anchored() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("build")).unwrap();
+        fs::create_dir_all(dir.path().join("docs/build")).unwrap();
+        fs::write(dir.path().join("build/out.o"), "obj").unwrap();
+        fs::write(dir.path().join("docs/build/index.html"), "html").unwrap();
+
+        let mut job = make_job(dir.path(), &["build", "**/*.html"], true);
+        job.anchored = true;
+        job.run().unwrap();
+        assert_eq!(
+            job.matched_paths(),
+            vec![
+                dir.path().join("build"),
+                dir.path().join("docs/build/index.html")
+            ]
+        );
+        assert_eq!(job.stats()["build"], (1, 3));
+
+        // stdin candidates and exclude patterns are anchored the same way
+        let mut job = make_job(dir.path(), &["build/**", "!build/keep.o"], true);
+        job.anchored = true;
+        fs::write(dir.path().join("build/keep.o"), "obj").unwrap();
+        job.run_on_paths(vec![
+            dir.path().join("build/out.o"),
+            dir.path().join("build/keep.o"),
+            dir.path().join("docs/build/index.html"),
+        ])
+        .unwrap();
+        assert_eq!(job.matched_paths(), vec![dir.path().join("build/out.o")]);
+    }
+
+    #[test]
+    fn test_job_json_disposition() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.trash_mode = true;
+        job.run().unwrap();
+        let report: serde_json::Value = serde_json::from_str(&job.to_json()).unwrap();
+        assert_eq!(report["matches"][0]["disposition"], "would_trash");
+
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.run().unwrap();
+        let report: serde_json::Value = serde_json::from_str(&job.to_json()).unwrap();
+        assert_eq!(report["matches"][0]["disposition"], "deleted");
+        assert_eq!(
+            job.disposition(&dir.path().join("debug.log")),
+            rclean::Disposition::Deleted
+        );
+    }
+
+    #[test]
+    fn test_job_trash_fallback_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+
+        // whether or not a trash is available here, the file must go
+        let mut job = make_job(dir.path(), &["**/*.log"], false);
+        job.trash_mode = true;
+        job.trash_fallback_delete = true;
+        job.run().unwrap();
+        assert!(!dir.path().join("debug.log").exists());
+        assert!(job.failed_deletions().is_empty());
+    }
+
+    #[test]
+    fn test_job_exclude_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let excludes = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("debug.log"), "log").unwrap();
+        fs::write(dir.path().join("audit.log"), "log").unwrap();
+        fs::write(
+            excludes.path().join("excludes"),
+            "# keep audit trails\n\n**/audit.log\n",
+        )
+        .unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], true);
+        job.exclude_files = vec![excludes.path().join("excludes")];
+        job.run().unwrap();
+        assert_eq!(job.matched_paths(), vec![dir.path().join("debug.log")]);
+
+        fs::write(excludes.path().join("excludes"), "**/*.tmp\n**/[unclosed\n").unwrap();
+        match job.run() {
+            Err(CleanError::ConfigError(msg)) => assert!(msg.contains("excludes:2:"), "{msg}"),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
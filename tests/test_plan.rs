@@ -0,0 +1,213 @@
+#[cfg(test)]
+mod tests {
+    use rclean::plan::ScanReport;
+    use rclean::prompt::{Answer, Prompter, TargetPrompt};
+    use rclean::CleaningJob;
+    use std::fs;
+    use std::path::Path;
+
+    fn job(dir: &Path, patterns: &[&str]) -> CleaningJob {
+        CleaningJob::new(
+            dir.display().to_string(),
+            patterns.iter().map(|p| p.to_string()).collect(),
+            false,
+            true,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_scan_then_apply() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("build/x.log"), "nested").unwrap();
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+
+        let plan = job(dir.path(), &["**/build", "**/*.log"]).scan().unwrap();
+        // the nested match goes along with its directory
+        assert_eq!(plan.entries.len(), 2);
+        assert!(plan.entries.iter().all(|entry| entry.path.is_absolute()));
+        assert!(dir.path().join("a.log").exists());
+
+        let path = dir.path().join("plan.json");
+        plan.save(&path).unwrap();
+        let plan = ScanReport::load(&path).unwrap();
+
+        let report = job(dir.path(), &[]).apply(&plan).unwrap();
+        assert_eq!(report.removed.len(), 2);
+        assert!(report.stale.is_empty() && report.missing.is_empty());
+        assert!(!dir.path().join("a.log").exists());
+        assert!(!dir.path().join("build").exists());
+    }
+
+    #[test]
+    fn test_apply_counts_nested_entries_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("__pycache__")).unwrap();
+        fs::write(dir.path().join("__pycache__/a.pyc"), "abc").unwrap();
+        fs::write(dir.path().join("__pycache__/b.pyc"), "de").unwrap();
+
+        let mut plan = job(dir.path(), &["**/*.pyc"]).scan().unwrap();
+        let dirs = job(dir.path(), &["**/__pycache__"]).scan().unwrap();
+        // a hand-merged plan listing the files before their directory
+        plan.entries.extend(dirs.entries);
+        assert_eq!(plan.entries.len(), 3);
+
+        let mut job = job(dir.path(), &[]);
+        let report = job.apply(&plan).unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert!(report.failed.is_empty());
+        assert_eq!((job.summary().count, job.summary().size), (1, 5));
+        assert!(!dir.path().join("__pycache__").exists());
+    }
+
+    #[test]
+    fn test_apply_skips_changed_and_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(dir.path().join(name), "log").unwrap();
+        }
+
+        let plan = job(dir.path(), &["**/*.log"]).scan().unwrap();
+        fs::write(dir.path().join("a.log"), "changed").unwrap();
+        fs::remove_file(dir.path().join("b.log")).unwrap();
+
+        let report = job(dir.path(), &[]).apply(&plan).unwrap();
+        assert_eq!(
+            report.stale,
+            [fs::canonicalize(dir.path()).unwrap().join("a.log")]
+        );
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.log")).unwrap(),
+            "changed"
+        );
+        assert!(!dir.path().join("c.log").exists());
+    }
+
+    #[test]
+    fn test_apply_skips_paths_outside_the_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+        fs::write(elsewhere.path().join("b.log"), "log").unwrap();
+
+        let mut plan = job(dir.path(), &["**/*.log"]).scan().unwrap();
+        assert!(Path::new(&plan.path).is_absolute());
+        // an entry added by hand, outside the planned path
+        let mut foreign = plan.entries[0].clone();
+        foreign.path = fs::canonicalize(elsewhere.path()).unwrap().join("b.log");
+        plan.entries.push(foreign);
+        let mut escaping = plan.entries[0].clone();
+        escaping.path = dir.path().join("..");
+        plan.entries.push(escaping);
+
+        let report = job(dir.path(), &[]).apply(&plan).unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.outside.len(), 2);
+        assert!(!dir.path().join("a.log").exists());
+        assert!(elsewhere.path().join("b.log").exists());
+    }
+
+    #[test]
+    fn test_apply_skips_entries_no_longer_matched() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log", "c.log"] {
+            fs::write(dir.path().join(name), "log").unwrap();
+        }
+
+        let plan = job(dir.path(), &["**/*.log"]).scan().unwrap();
+        // excluded after the plan was made
+        fs::write(dir.path().join(".rcleanignore"), "c.log\n").unwrap();
+        let report = job(dir.path(), &["!**/b.log"]).apply(&plan).unwrap();
+        let base = fs::canonicalize(dir.path()).unwrap();
+        assert_eq!(report.stale, [base.join("b.log"), base.join("c.log")]);
+        assert_eq!(report.removed, [base.join("a.log")]);
+        assert!(dir.path().join("b.log").exists());
+        assert!(dir.path().join("c.log").exists());
+    }
+
+    #[test]
+    fn test_apply_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "log").unwrap();
+
+        let plan = job(dir.path(), &["**/*.log"]).scan().unwrap();
+        let mut job = job(dir.path(), &[]);
+        job.dry_run = true;
+        let report = job.apply(&plan).unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert!(dir.path().join("a.log").exists());
+    }
+
+    #[test]
+    fn test_apply_refuses_over_the_caps() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log"] {
+            fs::write(dir.path().join(name), "log").unwrap();
+        }
+
+        let plan = job(dir.path(), &["**/*.log"]).scan().unwrap();
+        let mut job = job(dir.path(), &[]);
+        job.max_delete_count = Some(1);
+        assert!(job.apply(&plan).is_err());
+        let mut job = self::job(dir.path(), &[]);
+        job.max_delete_size = Some(5);
+        assert!(job.apply(&plan).is_err());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_apply_refuses_a_truncated_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log"] {
+            fs::write(dir.path().join(name), "log").unwrap();
+        }
+
+        let mut scanner = job(dir.path(), &["**/*.log"]);
+        scanner.max_deletions = Some(1);
+        let plan = scanner.scan().unwrap();
+        assert!(plan.truncated);
+        assert!(job(dir.path(), &[]).apply(&plan).is_err());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let mut job = job(dir.path(), &[]);
+        job.limit_ok = true;
+        assert_eq!(job.apply(&plan).unwrap().removed.len(), 1);
+    }
+
+    struct Typist(&'static str);
+
+    impl Prompter for Typist {
+        fn ask(&mut self, _target: &TargetPrompt) -> Answer {
+            panic!("asked about a single target")
+        }
+
+        fn type_confirmation(&mut self, _prompt: &str) -> String {
+            self.0.to_string()
+        }
+    }
+
+    #[test]
+    fn test_apply_confirm_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.log", "b.log"] {
+            fs::write(dir.path().join(name), "log").unwrap();
+        }
+
+        let plan = job(dir.path(), &["**/*.log"]).scan().unwrap();
+        let mut job = job(dir.path(), &[]);
+        job.confirm_threshold = Some(1);
+        let report = job.apply_with_prompter(&plan, &mut Typist("y")).unwrap();
+        assert!(report.removed.is_empty());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let mut job = self::job(dir.path(), &[]);
+        job.confirm_threshold = Some(1);
+        let report = job
+            .apply_with_prompter(&plan, &mut Typist("delete"))
+            .unwrap();
+        assert_eq!(report.removed.len(), 2);
+    }
+}
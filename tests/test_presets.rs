@@ -0,0 +1,41 @@
+#[cfg(test)]
+mod tests {
+    use rclean::constants::{get_default_patterns, get_preset_patterns, PRESET_NAMES};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_presets_known_names() {
+        for name in PRESET_NAMES {
+            assert!(!get_preset_patterns(name).unwrap().is_empty(), "{name}");
+        }
+        assert_eq!(get_preset_patterns("cobol"), None);
+    }
+
+    #[test]
+    fn test_presets_key_patterns() {
+        assert_eq!(get_preset_patterns("python"), Some(get_default_patterns()));
+        let go = get_preset_patterns("go").unwrap();
+        assert!(go.contains(&"**/*.test".to_string()));
+        assert!(!go.contains(&"**/vendor".to_string()));
+        let java = get_preset_patterns("java").unwrap();
+        for pattern in ["**/target", "**/*.class", "**/.gradle", "**/build"] {
+            assert!(java.contains(&pattern.to_string()), "{pattern}");
+        }
+        let cpp = get_preset_patterns("cpp").unwrap();
+        for pattern in ["**/*.o", "**/*.obj", "**/CMakeFiles", "**/cmake-build-*"] {
+            assert!(cpp.contains(&pattern.to_string()), "{pattern}");
+        }
+    }
+
+    #[test]
+    fn test_presets_all_without_duplicates() {
+        let all = get_preset_patterns("all").unwrap();
+        let unique: HashSet<&String> = all.iter().collect();
+        assert_eq!(unique.len(), all.len());
+        for name in PRESET_NAMES.iter().filter(|&&name| name != "all") {
+            for pattern in get_preset_patterns(name).unwrap() {
+                assert!(all.contains(&pattern), "{pattern}");
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+#[cfg(test)]
+mod tests {
+    use rclean::report::{
+        render_stats, render_tree, to_json_line, to_markdown, write_csv, CsvRow, Record,
+    };
+    use rclean::{Disposition, MatchedItem, SizeUnits, Summary};
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_ndjson_records_are_tagged() {
+        let item = MatchedItem {
+            path: PathBuf::from("./a.log"),
+            pattern: "**/*.log".to_string(),
+            size: 12,
+            is_dir: false,
+        };
+        let line = to_json_line(&Record::Match(&item));
+        assert!(!line.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "match");
+        assert_eq!(value["path"], "./a.log");
+        assert_eq!(value["pattern"], "**/*.log");
+        assert_eq!(value["size"], 12);
+        assert_eq!(value["is_dir"], false);
+    }
+
+    #[test]
+    fn test_ndjson_removals_are_tagged() {
+        let line = to_json_line(&Record::Deleted {
+            path: Path::new("./a.log"),
+            size: 12,
+        });
+        assert_eq!(line, r#"{"event":"deleted","path":"./a.log","size":12}"#);
+        let line = to_json_line(&Record::Failed {
+            path: Path::new("./b.log"),
+            error: "denied",
+        });
+        assert_eq!(
+            line,
+            r#"{"event":"failed","path":"./b.log","error":"denied"}"#
+        );
+    }
+
+    #[test]
+    fn test_ndjson_summary_is_tagged() {
+        let summary = Summary {
+            path: ".".to_string(),
+            dry_run: false,
+            count: 3,
+            size: 42,
+            failed: Summary::failures(&[(PathBuf::from("./x"), "denied".to_string())]),
+            skipped_in_use: 0,
+            skipped_by_hook: 0,
+            remaining: 0,
+            archive_size: None,
+            free_space_before: None,
+            free_space_after: None,
+            free_space_after_estimate: None,
+            max_deletions: Some(10),
+            max_total_size: None,
+            truncated: true,
+        };
+        let line = to_json_line(&Record::Summary(&summary));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "summary");
+        assert_eq!(value["count"], 3);
+        assert_eq!(value["size"], 42);
+        assert_eq!(value["failed"][0]["path"], "./x");
+        assert_eq!(value["failed"][0]["error"], "denied");
+        assert_eq!(value["max_deletions"], 10);
+        assert!(value.get("max_total_size").is_none());
+        assert_eq!(value["truncated"], true);
+    }
+
+    #[test]
+    fn test_csv_escapes_paths() {
+        let rows = vec![
+            CsvRow {
+                path: Path::new("./a,\"b\"\n.log"),
+                kind: "file",
+                size_bytes: 3,
+                modified_rfc3339: Some("2024-02-29T12:34:56Z".to_string()),
+                matched_pattern: "**/*.log",
+                action: "failed".to_string(),
+                error: Some("permission denied, read-only"),
+            },
+            CsvRow {
+                path: Path::new("./build"),
+                kind: "dir",
+                size_bytes: 10,
+                modified_rfc3339: None,
+                matched_pattern: "**/build",
+                action: "would-delete".to_string(),
+                error: None,
+            },
+        ];
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, rows).unwrap();
+        let csv = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            csv,
+            "path,type,size_bytes,modified_rfc3339,matched_pattern,action,error\n\
+             \"./a,\"\"b\"\"\n.log\",file,3,2024-02-29T12:34:56Z,**/*.log,failed,\
+             \"permission denied, read-only\"\n\
+             ./build,dir,10,,**/build,would-delete,\n"
+        );
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let records: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(&records[0][0], "./a,\"b\"\n.log");
+    }
+
+    #[test]
+    fn test_csv_header_without_items() {
+        let mut buffer = Vec::new();
+        write_csv(&mut buffer, []).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "path,type,size_bytes,modified_rfc3339,matched_pattern,action,error\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_report() {
+        let items = vec![
+            MatchedItem {
+                path: PathBuf::from("./a.log"),
+                pattern: "**/*.log".to_string(),
+                size: 1024,
+                is_dir: false,
+            },
+            MatchedItem {
+                path: PathBuf::from("./b.log"),
+                pattern: "**/*.log".to_string(),
+                size: 512,
+                is_dir: false,
+            },
+        ];
+        let stats = HashMap::from([("**/*.log".to_string(), (2, 1536))]);
+        let markdown = to_markdown(".", true, &stats, &items, SizeUnits::Binary);
+        assert!(markdown.starts_with("# rclean report for `.`\n"));
+        assert!(markdown.contains("Dry-run: nothing was removed."));
+        assert!(markdown.contains("| `**/*.log` | 2 | 1.50 KiB |"));
+        assert!(markdown.contains("| **Total** | 2 | 1.50 KiB |"));
+        assert!(markdown.contains("<summary>2 path(s)</summary>"));
+        assert!(markdown.contains("- `./b.log`"));
+        assert!(markdown.trim_end().ends_with("</details>"));
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let item = |path: &str, size, is_dir| MatchedItem {
+            path: PathBuf::from(path),
+            pattern: "*".to_string(),
+            size,
+            is_dir,
+        };
+        let items = vec![
+            item("./a/target", 2048, true),
+            item("./a/x.log", 3, false),
+            item("./b/c/y.log", 5, false),
+        ];
+        let tree = render_tree(Path::new("."), &items, |path| {
+            if path.ends_with("x.log") {
+                Disposition::Kept
+            } else {
+                Disposition::WouldDelete
+            }
+        });
+        assert_eq!(
+            tree,
+            ".\n\
+             ├── a\n\
+             │   ├── target/ (2.00 KiB) [would delete]\n\
+             │   └── x.log (3 B) [kept]\n\
+             └── b\n\
+             \u{20}   └── c\n\
+             \u{20}       └── y.log (5 B) [would delete]\n"
+        );
+    }
+
+    fn sample_stats() -> HashMap<String, (usize, u64)> {
+        HashMap::from([
+            ("**/node_modules".to_string(), (3, 3 << 30)),
+            ("**/*.log".to_string(), (12, 2048)),
+        ])
+    }
+
+    #[test]
+    fn test_render_stats_table() {
+        let lines = render_stats(&sample_stats(), Some(80), false, SizeUnits::Binary);
+        assert_eq!(
+            lines,
+            [
+                "Pattern          Count      Size",
+                "--------------------------------",
+                "**/*.log            12  2.00 KiB",
+                "**/node_modules      3  3.00 GiB",
+                "--------------------------------",
+                "Total               15  3.00 GiB",
+            ]
+        );
+        // every line lines up, also in colour
+        let colored = render_stats(&sample_stats(), Some(80), true, SizeUnits::Binary);
+        assert!(colored[2].contains("\u{1b}["));
+        assert_eq!(console::strip_ansi_codes(&colored[3]), lines[3]);
+        let decimal = render_stats(&sample_stats(), Some(80), false, SizeUnits::Decimal);
+        assert_eq!(decimal[2], "**/*.log            12  2.05 KB");
+        assert_eq!(decimal[5], "Total               15  3.22 GB");
+    }
+
+    #[test]
+    fn test_render_stats_narrow_terminal() {
+        let lines = render_stats(&sample_stats(), Some(20), false, SizeUnits::Binary);
+        assert!(lines[3].starts_with("**/node_mod…  "));
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().count() == lines[0].chars().count()));
+    }
+
+    #[test]
+    fn test_render_stats_piped() {
+        let lines = render_stats(&sample_stats(), None, true, SizeUnits::Decimal);
+        assert_eq!(
+            lines,
+            [
+                "pattern\tcount\tsize",
+                "**/*.log\t12\t2048",
+                "**/node_modules\t3\t3221225472",
+                "Total\t15\t3221227520",
+            ]
+        );
+    }
+}
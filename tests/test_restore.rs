@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use rclean::restore::{restore, unique_path, Manifest, MANIFEST_FILENAME};
+    use rclean::{CleanError, CleaningJob, EntryType};
+    use std::fs;
+    use std::path::Path;
+
+    fn make_job(path: &Path, patterns: &[&str], backup_dir: &Path) -> CleaningJob {
+        let mut job = CleaningJob::new(
+            path.display().to_string(),
+            patterns.iter().map(|p| p.to_string()).collect(),
+            false,
+            true,
+            false,
+        );
+        job.backup_dir = Some(backup_dir.to_path_buf());
+        job
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("pkg/__pycache__")).unwrap();
+        fs::write(dir.path().join("pkg/__pycache__/mod.pyc"), "bytecode").unwrap();
+        fs::write(dir.path().join("pkg/debug.log"), "log").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/__pycache__", "**/*.log"], backup.path());
+        job.run().unwrap();
+        assert!(!dir.path().join("pkg/__pycache__").exists());
+        assert!(!dir.path().join("pkg/debug.log").exists());
+
+        let manifest_path = backup.path().join(MANIFEST_FILENAME);
+        let manifest = Manifest::load(&manifest_path).unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+        assert!(manifest
+            .entries
+            .iter()
+            .any(|e| e.kind == EntryType::Dir && e.size == 8));
+
+        let report = restore(&manifest_path, false).unwrap();
+        assert_eq!(report.restored.len(), 2);
+        assert!(report.failed.is_empty());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("pkg/__pycache__/mod.pyc")).unwrap(),
+            "bytecode"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("pkg/debug.log")).unwrap(),
+            "log"
+        );
+    }
+
+    #[test]
+    fn test_restore_refuses_overwrite_without_force() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.log"), "old").unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.log"], backup.path());
+        job.run().unwrap();
+        fs::write(dir.path().join("a.log"), "new").unwrap();
+
+        let manifest_path = backup.path().join(MANIFEST_FILENAME);
+        let report = restore(&manifest_path, false).unwrap();
+        assert!(report.restored.is_empty());
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(fs::read_to_string(dir.path().join("a.log")).unwrap(), "new");
+
+        let report = restore(&manifest_path, true).unwrap();
+        assert_eq!(report.restored.len(), 1);
+        assert_eq!(fs::read_to_string(dir.path().join("a.log")).unwrap(), "old");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_backup_and_restore_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("real.txt"), "x").unwrap();
+        std::os::unix::fs::symlink("real.txt", dir.path().join("link.tmp")).unwrap();
+
+        let mut job = make_job(dir.path(), &["**/*.tmp"], backup.path());
+        job.include_symlinks = true;
+        job.run().unwrap();
+        assert!(dir.path().join("link.tmp").symlink_metadata().is_err());
+
+        restore(&backup.path().join(MANIFEST_FILENAME), false).unwrap();
+        assert_eq!(
+            fs::read_link(dir.path().join("link.tmp")).unwrap(),
+            Path::new("real.txt")
+        );
+    }
+
+    #[test]
+    fn test_backup_dir_inside_path_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], &dir.path().join("backup"));
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_backup_dir_with_manifest_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = tempfile::tempdir().unwrap();
+        fs::write(backup.path().join(MANIFEST_FILENAME), "{}").unwrap();
+        let mut job = make_job(dir.path(), &["**/*.log"], backup.path());
+        assert!(matches!(job.run(), Err(CleanError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_quarantine_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let quarantine = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("pkg/__pycache__")).unwrap();
+        fs::write(dir.path().join("pkg/__pycache__/mod.pyc"), "bytecode").unwrap();
+        fs::write(dir.path().join("pkg/debug.log"), "log").unwrap();
+
+        let mut job = CleaningJob::new(
+            dir.path().display().to_string(),
+            vec!["**/__pycache__".to_string(), "**/*.log".to_string()],
+            true,
+            true,
+            false,
+        );
+        job.quarantine_dir = Some(quarantine.path().to_path_buf());
+        job.run().unwrap();
+        // a dry-run only plans the destinations
+        assert!(dir.path().join("pkg/debug.log").exists());
+        assert_eq!(fs::read_dir(quarantine.path()).unwrap().count(), 0);
+
+        job.dry_run = false;
+        job.run().unwrap();
+        assert!(!dir.path().join("pkg/__pycache__").exists());
+        assert!(!dir.path().join("pkg/debug.log").exists());
+        let run_dir = fs::read_dir(quarantine.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        assert_eq!(
+            fs::read_to_string(run_dir.join("pkg/__pycache__/mod.pyc")).unwrap(),
+            "bytecode"
+        );
+        let manifest = Manifest::load(&run_dir.join(MANIFEST_FILENAME)).unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+
+        let report = restore(&run_dir.join(MANIFEST_FILENAME), false).unwrap();
+        assert_eq!(report.restored.len(), 2);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("pkg/debug.log")).unwrap(),
+            "log"
+        );
+    }
+
+    #[test]
+    fn test_unique_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("build");
+        assert_eq!(unique_path(&path), path);
+        fs::create_dir(&path).unwrap();
+        fs::write(dir.path().join("build.1"), "").unwrap();
+        assert_eq!(unique_path(&path), dir.path().join("build.2"));
+    }
+}
@@ -1,5 +1,6 @@
 #[cfg(test)]
 mod tests {
+    use rclean::AgeField;
     use serde::{Deserialize, Serialize};
     use std::fs;
     use toml::Table;
@@ -83,4 +84,21 @@ mod tests {
         assert!(fs::metadata(outfile).unwrap().is_file());
         std::fs::remove_file(outfile).expect("outfile.toml could not be removed");
     }
+
+    #[test]
+    fn test_toml_age_field_roundtrip() {
+        let mut job = rclean::CleaningJob::default();
+        job.age_field = AgeField::Accessed;
+        let toml = toml::to_string(&job).unwrap();
+        assert!(toml.contains("age_field = \"accessed\""));
+        let job: rclean::CleaningJob = toml::from_str(&toml).unwrap();
+        assert_eq!(job.age_field, AgeField::Accessed);
+    }
+
+    #[test]
+    fn test_toml_age_field_defaults_to_modified() {
+        let contents = fs::read_to_string("tests/.rclean.toml").expect("cannot read file");
+        let job: rclean::CleaningJob = toml::from_str(&contents).unwrap();
+        assert_eq!(job.age_field, AgeField::Modified);
+    }
 }
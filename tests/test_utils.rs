@@ -0,0 +1,173 @@
+#[cfg(test)]
+mod tests {
+    use rclean::utils::{
+        format_age, format_duration, format_rfc3339, format_size, format_size_in, format_timestamp,
+        long_path, parse_duration, parse_size, split_command, split_negation, SizeUnits,
+    };
+
+    #[test]
+    fn test_parse_size_suffixes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("512B"), Ok(512));
+        assert_eq!(parse_size("500K"), Ok(500 * 1024));
+        assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1G"), Ok(1024 * 1024 * 1024));
+        assert_eq!(parse_size("10m"), Ok(10 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_parse_size_units() {
+        assert_eq!(parse_size("512KiB"), Ok(512 * 1024));
+        assert_eq!(parse_size("100MB"), Ok(100_000_000));
+        assert_eq!(parse_size("100mb"), Ok(100_000_000));
+        assert_eq!(parse_size("2GB"), Ok(2_000_000_000));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+        assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+        assert_eq!(parse_size("1.5K"), Ok(1536));
+        assert_eq!(parse_size("0.5MB"), Ok(500_000));
+        assert_eq!(parse_size("10 MB"), Ok(10_000_000));
+    }
+
+    #[test]
+    fn test_parse_size_invalid() {
+        assert!(parse_size("1.2.3M").is_err());
+        assert!(parse_size(".").is_err());
+        assert!(parse_size("1KiBB").is_err());
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("-1K").is_err());
+        assert!(parse_size("99999999999999999999G").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration_suffixes() {
+        assert_eq!(parse_duration("45"), Ok(45));
+        assert_eq!(parse_duration("45s"), Ok(45));
+        assert_eq!(parse_duration("15m"), Ok(15 * 60));
+        assert_eq!(parse_duration("2h"), Ok(2 * 3600));
+        assert_eq!(parse_duration("7d"), Ok(7 * 86400));
+        assert_eq!(parse_duration("1w"), Ok(7 * 86400));
+        assert_eq!(parse_duration("2H"), Ok(2 * 3600));
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("1.5h").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.00 KiB");
+        assert_eq!(format_size(1536), "1.50 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.00 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.00 GiB");
+    }
+
+    #[test]
+    fn test_format_size_decimal() {
+        let si = |bytes| format_size_in(bytes, SizeUnits::Decimal);
+        assert_eq!(si(999), "999 B");
+        assert_eq!(si(1000), "1.00 KB");
+        assert_eq!(si(1536), "1.54 KB");
+        assert_eq!(si(5_000_000), "5.00 MB");
+        assert_eq!(si(3_000_000_000), "3.00 GB");
+        assert_eq!(format_size_in(1536, SizeUnits::Binary), format_size(1536));
+        assert_eq!("SI".parse(), Ok(SizeUnits::Decimal));
+        assert!("metric".parse::<SizeUnits>().is_err());
+    }
+
+    #[test]
+    fn test_split_negation() {
+        assert_eq!(split_negation("**/*.log"), (false, "**/*.log"));
+        assert_eq!(split_negation("!**/keep.log"), (true, "**/keep.log"));
+        assert_eq!(split_negation("!!bang"), (false, "!bang"));
+        assert_eq!(split_negation("a!b"), (false, "a!b"));
+    }
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(split_command("touch {}").unwrap(), ["touch", "{}"]);
+        assert_eq!(
+            split_command(r#"notify  'build dir' "a \"b\"" c\ d"#).unwrap(),
+            ["notify", "build dir", "a \"b\"", "c d"]
+        );
+        assert_eq!(split_command("echo ''").unwrap(), ["echo", ""]);
+        assert!(split_command("echo 'open").is_err());
+        assert!(split_command("  ").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_uid() {
+        use rclean::utils::resolve_uid;
+        assert_eq!(resolve_uid("1234"), Ok(1234));
+        assert_eq!(resolve_uid("root"), Ok(0));
+        assert!(resolve_uid("no-such-user-rclean").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(90 * 60), "90m");
+        assert_eq!(format_duration(7200), "2h");
+        assert_eq!(format_duration(30 * 86400), "30d");
+        for secs in [1, 59, 61, 3601, 86399, 86400 * 14] {
+            assert_eq!(parse_duration(&format_duration(secs)), Ok(secs));
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_long_path_unchanged() {
+        use std::path::Path;
+        let path = Path::new("a/../b");
+        assert_eq!(long_path(path), path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_long_path() {
+        use std::path::Path;
+        assert_eq!(long_path(Path::new(r"C:\a\..\b")), Path::new(r"\\?\C:\b"));
+        assert_eq!(
+            long_path(Path::new(r"\\server\share\dir")),
+            Path::new(r"\\?\UNC\server\share\dir")
+        );
+        assert_eq!(long_path(Path::new(r"\\?\C:\b")), Path::new(r"\\?\C:\b"));
+    }
+
+    #[test]
+    fn test_format_age() {
+        use std::time::Duration;
+        assert_eq!(format_age(Duration::from_secs(42)), "42s");
+        assert_eq!(format_age(Duration::from_secs(90)), "1m");
+        assert_eq!(format_age(Duration::from_secs(7200)), "2h");
+        assert_eq!(format_age(Duration::from_secs(3 * 86400 + 5)), "3d");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        use std::time::{Duration, SystemTime};
+        assert_eq!(format_timestamp(SystemTime::UNIX_EPOCH), "19700101-000000");
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_timestamp(time), "20240229-123456");
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        use std::time::{Duration, SystemTime};
+        assert_eq!(
+            format_rfc3339(SystemTime::UNIX_EPOCH),
+            "1970-01-01T00:00:00Z"
+        );
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(format_rfc3339(time), "2024-02-29T12:34:56Z");
+    }
+}